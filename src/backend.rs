@@ -0,0 +1,54 @@
+use std::sync::mpsc::Sender;
+use crossterm::Result;
+
+use crate::editor::{Dimensions, Location};
+use crate::event_loop::LoopEvent;
+use crate::screen::Change;
+
+mod crossterm_backend;
+#[cfg(test)]
+mod test_backend;
+
+pub use crossterm_backend::CrosstermBackend;
+#[cfg(test)]
+pub use test_backend::TestBackend;
+
+/// The terminal that the program runs in.
+///
+/// Abstracts over the real terminal so that the program can be driven by
+///   scripted input, and its output inspected, in tests.
+pub trait Backend {
+    /// Prepares the terminal for use by the program.
+    fn enter(&mut self) -> Result<()>;
+
+    /// Returns the terminal to the state it was in before `enter`.
+    fn leave(&mut self) -> Result<()>;
+
+    /// Gets the size of the terminal.
+    fn size(&self) -> Result<Dimensions>;
+
+    /// Starts delivering input events, as they arrive, to the event loop.
+    ///
+    /// # Arguments
+    ///
+    /// * `sender` - Where to send input events.
+    fn start_input(&mut self, sender: Sender<LoopEvent>) -> Result<()>;
+
+    /// Draws cells that have changed.
+    ///
+    /// # Arguments
+    ///
+    /// * `clear` - Whether to clear the terminal before drawing.
+    /// * `changes` - The cells to draw, in row order.
+    fn draw(&mut self, clear: bool, changes: &[Change]) -> Result<()>;
+
+    /// Moves the cursor.
+    ///
+    /// # Arguments
+    ///
+    /// * `location` - Where on the screen to place the cursor.
+    fn set_cursor(&mut self, location: Location) -> Result<()>;
+
+    /// Sends anything drawn to the terminal.
+    fn flush(&mut self) -> Result<()>;
+}
@@ -0,0 +1,134 @@
+use std::io::Write;
+use std::sync::mpsc::Sender;
+use std::thread;
+use crossterm::{
+    cursor,
+    event,
+    queue, style, terminal,
+    Result
+};
+
+use crate::editor::{graphemes, Dimensions, Location};
+use crate::event_loop::LoopEvent;
+use crate::screen::{Change, Style};
+use crate::terminal_guard::TerminalGuard;
+use super::Backend;
+
+/// Drives a real terminal through crossterm.
+pub struct CrosstermBackend<W: Write> {
+    w: W,
+
+    /// Restores the terminal when dropped, if the program is using it.
+    guard: Option<TerminalGuard>,
+}
+
+impl<W: Write> CrosstermBackend<W> {
+    /// Returns a backend that draws to a writer connected to the terminal.
+    ///
+    /// # Arguments
+    ///
+    /// * `w` - The writer to draw to, usually stdout.
+    pub fn new(w: W) -> CrosstermBackend<W> {
+        CrosstermBackend { w, guard: None }
+    }
+}
+
+impl<W: Write> Backend for CrosstermBackend<W> {
+    fn enter(&mut self) -> Result<()> {
+        self.guard = Some(TerminalGuard::enter()?);
+        Ok(())
+    }
+
+    fn leave(&mut self) -> Result<()> {
+        self.w.flush()?;
+        self.guard = None;
+        Ok(())
+    }
+
+    fn size(&self) -> Result<Dimensions> {
+        let (columns, rows) = terminal::size()?;
+        Ok(Dimensions::new(columns, rows))
+    }
+
+    fn start_input(&mut self, sender: Sender<LoopEvent>) -> Result<()> {
+        // Reading blocks, so read on a thread of its own and let the event
+        //  loop sleep until something arrives
+        thread::Builder::new()
+            .name("input".to_string())
+            .spawn(move || {
+                loop {
+                    let event = match event::read() {
+                        Ok(event) => LoopEvent::Input(event),
+                        Err(e) => {
+                            // Nothing more can be read, so the program has to
+                            //  stop rather than linger, deaf to keys
+                            let _ = sender.send(LoopEvent::InputLost(e.to_string()));
+                            break;
+                        }
+                    };
+                    if sender.send(event).is_err() {
+                        // The event loop has gone away
+                        break;
+                    }
+                }
+            })?;
+        Ok(())
+    }
+
+    fn draw(&mut self, clear: bool, changes: &[Change]) -> Result<()> {
+        if clear {
+            queue!(self.w, style::ResetColor, terminal::Clear(terminal::ClearType::All))?;
+        }
+        if changes.is_empty() {
+            return Ok(());
+        }
+
+        // Hide the cursor while drawing, so that it doesn't flicker across the
+        //  screen
+        queue!(self.w, cursor::Hide)?;
+
+        // Where the terminal's cursor will be after the last print, and the
+        //  colors it is drawing with, so that redundant commands are skipped
+        let mut position: Option<Location> = None;
+        let mut current_style: Option<Style> = None;
+        for change in changes {
+            if position != Some(change.location) {
+                queue!(self.w, cursor::MoveTo(change.location.column_ix, change.location.row_ix))?;
+            }
+            if current_style != Some(change.cell.style) {
+                let attributes = change.cell.style.attributes;
+                if current_style.is_none_or(|current| current.attributes != attributes) {
+                    // Attributes can only be turned off all at once, which
+                    //  also resets the colors, so set the colors afterwards
+                    queue!(self.w, style::SetAttribute(style::Attribute::Reset))?;
+                    if !attributes.is_empty() {
+                        queue!(self.w, style::SetAttributes(attributes))?;
+                    }
+                }
+                queue!(
+                    self.w,
+                    style::SetForegroundColor(change.cell.style.foreground),
+                    style::SetBackgroundColor(change.cell.style.background)
+                )?;
+                current_style = Some(change.cell.style);
+            }
+            queue!(self.w, style::Print(&change.cell.symbol))?;
+
+            // Terminals don't reliably wrap to the next row after printing in
+            //  the last column, so the next row always starts with a move
+            let width = graphemes::grapheme_width(&change.cell.symbol, 0, 1) as u16;
+            position = Some(Location::new(change.location.column_ix + width, change.location.row_ix));
+        }
+
+        queue!(self.w, style::SetAttribute(style::Attribute::Reset), style::ResetColor, cursor::Show)
+    }
+
+    fn set_cursor(&mut self, location: Location) -> Result<()> {
+        queue!(self.w, cursor::MoveTo(location.column_ix, location.row_ix))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.w.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+use std::collections::VecDeque;
+use std::sync::mpsc::Sender;
+use crossterm::{
+    event::{Event, KeyCode, KeyEvent, KeyModifiers},
+    Result
+};
+
+use crate::editor::{graphemes, Dimensions, Location};
+use crate::event_loop::LoopEvent;
+use crate::screen::{Cell, Change};
+use super::Backend;
+
+/// An in-memory terminal, fed with scripted input events.
+///
+/// Events pushed before the program starts are delivered when it starts, and
+///   events pushed afterwards are delivered straight away.
+///
+/// Keeps the cells drawn to it in a grid, so that what the program displays
+///   can be inspected.
+pub struct TestBackend {
+    size: Dimensions,
+
+    /// Events pushed before input started, oldest first.
+    events: VecDeque<Event>,
+
+    /// Where input is delivered, once it has started.
+    sender: Option<Sender<LoopEvent>>,
+
+    /// The cells drawn so far, row by row.
+    cells: Vec<Cell>,
+
+    /// Where the cursor was last placed.
+    cursor: Location,
+
+    /// Whether the program is between `enter` and `leave`.
+    entered: bool,
+}
+
+impl TestBackend {
+    /// Returns a blank terminal with no scripted events.
+    ///
+    /// # Arguments
+    ///
+    /// * `columns` - The width of the terminal.
+    /// * `rows` - The height of the terminal.
+    pub fn new(columns: u16, rows: u16) -> TestBackend {
+        TestBackend {
+            size: Dimensions::new(columns, rows),
+            events: VecDeque::new(),
+            sender: None,
+            cells: vec![Cell::default(); columns as usize * rows as usize],
+            cursor: Location::default(),
+            entered: false,
+        }
+    }
+
+    /// Queues an input event.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The event to deliver.
+    pub fn push_event(&mut self, event: Event) {
+        match &self.sender {
+            Some(sender) => sender.send(LoopEvent::Input(event)).unwrap(),
+            None => self.events.push_back(event),
+        }
+    }
+
+    /// Queues a key press without modifiers.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The key that is pressed.
+    pub fn push_key(&mut self, code: KeyCode) {
+        self.push_event(Event::Key(KeyEvent::new(code, KeyModifiers::NONE)));
+    }
+
+    /// Queues a key press with modifiers.
+    ///
+    /// # Arguments
+    ///
+    /// * `code` - The key that is pressed.
+    /// * `modifiers` - The modifier keys held down.
+    pub fn push_key_with(&mut self, code: KeyCode, modifiers: KeyModifiers) {
+        self.push_event(Event::Key(KeyEvent::new(code, modifiers)));
+    }
+
+    /// Queues a key press for each character of some text, as if it were typed.
+    ///
+    /// Uppercase characters are pressed with shift, as terminals report them.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The text to type.
+    pub fn push_keys(&mut self, text: &str) {
+        for c in text.chars() {
+            let modifiers = if c.is_uppercase() { KeyModifiers::SHIFT } else { KeyModifiers::NONE };
+            let code = if c == '\n' { KeyCode::Enter } else { KeyCode::Char(c) };
+            self.push_key_with(code, modifiers);
+        }
+    }
+
+    /// Resizes the terminal, and reports the resize as an input event.
+    ///
+    /// # Arguments
+    ///
+    /// * `columns` - The new width of the terminal.
+    /// * `rows` - The new height of the terminal.
+    pub fn resize(&mut self, columns: u16, rows: u16) {
+        self.size = Dimensions::new(columns, rows);
+        self.cells = vec![Cell::default(); columns as usize * rows as usize];
+        self.push_event(Event::Resize(columns, rows));
+    }
+
+    /// Gets the text of a row, as displayed.
+    ///
+    /// # Arguments
+    ///
+    /// * `row_ix` - The row to read.
+    pub fn row_text(&self, row_ix: u16) -> String {
+        let columns = self.size.columns as usize;
+        let start = row_ix as usize * columns;
+        self.cells[start..start + columns].iter().map(|cell| cell.symbol.as_str()).collect()
+    }
+
+    /// Gets a displayed cell.
+    ///
+    /// # Arguments
+    ///
+    /// * `column_ix` - The column of the cell.
+    /// * `row_ix` - The row of the cell.
+    pub fn cell(&self, column_ix: u16, row_ix: u16) -> &Cell {
+        &self.cells[row_ix as usize * self.size.columns as usize + column_ix as usize]
+    }
+
+    /// Gets where the cursor was last placed.
+    pub fn cursor(&self) -> Location {
+        self.cursor
+    }
+
+    /// Whether the program is using the terminal.
+    pub fn is_entered(&self) -> bool {
+        self.entered
+    }
+}
+
+impl Backend for TestBackend {
+    fn enter(&mut self) -> Result<()> {
+        self.entered = true;
+        Ok(())
+    }
+
+    fn leave(&mut self) -> Result<()> {
+        self.entered = false;
+        Ok(())
+    }
+
+    fn size(&self) -> Result<Dimensions> {
+        Ok(self.size)
+    }
+
+    fn start_input(&mut self, sender: Sender<LoopEvent>) -> Result<()> {
+        for event in self.events.drain(..) {
+            sender.send(LoopEvent::Input(event)).unwrap();
+        }
+        self.sender = Some(sender);
+        Ok(())
+    }
+
+    fn draw(&mut self, clear: bool, changes: &[Change]) -> Result<()> {
+        if clear {
+            for cell in self.cells.iter_mut() {
+                *cell = Cell::default();
+            }
+        }
+        let columns = self.size.columns as usize;
+        for change in changes {
+            let ix = change.location.row_ix as usize * columns + change.location.column_ix as usize;
+            self.cells[ix] = change.cell.clone();
+            if graphemes::grapheme_width(&change.cell.symbol, 0, 1) == 2 {
+                self.cells[ix + 1] = Cell::continuation(change.cell.style);
+            }
+        }
+        Ok(())
+    }
+
+    fn set_cursor(&mut self, location: Location) -> Result<()> {
+        self.cursor = location;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+}
@@ -1,13 +1,34 @@
 use crate::CoreData;
 
+mod bar_manager;
+mod command_bar;
 mod performance_bar;
 mod status_bar;
 
+pub use bar_manager::BarManager;
+pub use command_bar::CommandBar;
 pub use performance_bar::PerformanceBar;
 pub use status_bar::StatusBar;
 
+/// The edge of the terminal a bar is docked to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Dock {
+    Top,
+    Bottom,
+}
+
 /// Bars render status information
 pub trait Bar {
+    /// Gets the name of the bar, used to refer to it when toggling it.
+    fn name(&self) -> &'static str;
+
+    /// Gets the priority of the bar. The smaller the priority number, the
+    ///   further towards the top of the application the bar is rendered.
+    fn priority(&self) -> u8;
+
+    /// Gets the edge of the terminal the bar is docked to.
+    fn dock(&self) -> Dock;
+
     /// Renders the bar
     fn render(&self, core_data: &CoreData) -> String;
 }
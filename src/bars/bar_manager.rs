@@ -0,0 +1,195 @@
+use crate::CoreData;
+use crate::editor::Dimensions;
+use crate::layout::Rect;
+use super::{Bar, Dock};
+
+/// A bar, and whether it is currently shown.
+struct BarEntry {
+    bar: Box<dyn Bar>,
+    visible: bool,
+}
+
+/// Arranges bars along the top and bottom edges of the terminal, leaving the
+///   remaining area for editors.
+pub struct BarManager {
+    entries: Vec<BarEntry>,
+}
+
+impl BarManager {
+    pub fn new() -> BarManager {
+        BarManager { entries: Vec::new() }
+    }
+
+    /// Adds a bar. Bars are shown when added.
+    ///
+    /// # Arguments
+    ///
+    /// * `bar` - The bar to add.
+    pub fn add(&mut self, bar: Box<dyn Bar>) {
+        self.entries.push(BarEntry { bar, visible: true });
+        // Keep bars in priority order, so that they can be laid out in turn
+        self.entries.sort_by_key(|entry| entry.bar.priority());
+    }
+
+    /// Shows or hides a bar.
+    ///
+    /// Returns false if there is no bar with the name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the bar.
+    /// * `visible` - Whether the bar should be shown.
+    pub fn set_visible(&mut self, name: &str, visible: bool) -> bool {
+        match self.entries.iter_mut().find(|entry| entry.bar.name() == name) {
+            Some(entry) => {
+                entry.visible = visible;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Whether a bar is shown. Returns false if there is no bar with the name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the bar.
+    #[cfg(test)]
+    pub fn is_visible(&self, name: &str) -> bool {
+        self.entries.iter().any(|entry| entry.bar.name() == name && entry.visible)
+    }
+
+    /// Gets the text of a bar, if it is visible.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the bar.
+    /// * `core_data` - The information the bar displays.
+    pub fn render(&self, name: &str, core_data: &CoreData) -> Option<String> {
+        self.entries
+            .iter()
+            .find(|entry| entry.bar.name() == name && entry.visible)
+            .map(|entry| entry.bar.render(core_data))
+    }
+
+    /// Gets the row on which each visible bar is rendered.
+    ///
+    /// Bars with a smaller priority number are placed further towards the top
+    ///   of the terminal, within the edge they are docked to. If the terminal
+    ///   is too short for every bar, the bars furthest from the editors are
+    ///   kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `screen_size` - The size of the terminal.
+    pub fn arrange(&self, screen_size: &Dimensions) -> Vec<(&dyn Bar, u16)> {
+        let (top, bottom) = self.visible_bars();
+        let mut result = Vec::new();
+        let mut rows_left = screen_size.rows;
+
+        for (row_ix, bar) in top.iter().enumerate() {
+            if rows_left == 0 {
+                break;
+            }
+            result.push((*bar, row_ix as u16));
+            rows_left -= 1;
+        }
+        let bottom_rows = (bottom.len() as u16).min(rows_left);
+        let first_bottom_row = screen_size.rows - bottom_rows;
+        for (row_ix, bar) in bottom.iter().skip(bottom.len() - bottom_rows as usize).enumerate() {
+            result.push((*bar, first_bottom_row + row_ix as u16));
+        }
+        result
+    }
+
+    /// Gets the row on which a bar is rendered, if it is visible.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the bar.
+    /// * `screen_size` - The size of the terminal.
+    pub fn row_of(&self, name: &str, screen_size: &Dimensions) -> Option<u16> {
+        self.arrange(screen_size)
+            .into_iter()
+            .find(|(bar, _)| bar.name() == name)
+            .map(|(_, row_ix)| row_ix)
+    }
+
+    /// Gets the area of the terminal left for editors once the visible bars
+    ///   have been placed.
+    ///
+    /// # Arguments
+    ///
+    /// * `screen_size` - The size of the terminal.
+    pub fn editor_area(&self, screen_size: &Dimensions) -> Rect {
+        let (top, bottom) = self.visible_bars();
+        let top_rows = (top.len() as u16).min(screen_size.rows);
+        let bottom_rows = (bottom.len() as u16).min(screen_size.rows - top_rows);
+        Rect::new(0, top_rows, screen_size.columns, screen_size.rows - top_rows - bottom_rows)
+    }
+
+    /// Gets the visible bars docked to the top and bottom, in priority order.
+    fn visible_bars(&self) -> (Vec<&dyn Bar>, Vec<&dyn Bar>) {
+        let visible = self.entries.iter().filter(|entry| entry.visible);
+        let top = visible.clone()
+            .filter(|entry| entry.bar.dock() == Dock::Top)
+            .map(|entry| entry.bar.as_ref())
+            .collect();
+        let bottom = visible
+            .filter(|entry| entry.bar.dock() == Dock::Bottom)
+            .map(|entry| entry.bar.as_ref())
+            .collect();
+        (top, bottom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bars::{PerformanceBar, StatusBar};
+
+    /// Gets the names and rows of the bars arranged on a screen.
+    fn arranged(manager: &BarManager, screen_size: Dimensions) -> Vec<(&'static str, u16)> {
+        manager
+            .arrange(&screen_size)
+            .into_iter()
+            .map(|(bar, row_ix)| (bar.name(), row_ix))
+            .collect()
+    }
+
+    /// Bars are docked by priority, and the editor area shrinks to match.
+    #[test]
+    fn docks_bars_by_priority() {
+        let mut manager = BarManager::new();
+        manager.add(Box::new(PerformanceBar::new(2, Dock::Bottom)));
+        manager.add(Box::new(StatusBar::new(1, Dock::Bottom)));
+
+        assert_eq!(arranged(&manager, Dimensions::new(80, 30)), vec![("status", 28), ("performance", 29)]);
+        assert_eq!(manager.editor_area(&Dimensions::new(80, 30)), Rect::new(0, 0, 80, 28));
+    }
+
+    /// Bars can be docked to the top of the screen.
+    #[test]
+    fn docks_bars_at_top() {
+        let mut manager = BarManager::new();
+        manager.add(Box::new(StatusBar::new(1, Dock::Bottom)));
+        manager.add(Box::new(PerformanceBar::new(2, Dock::Top)));
+
+        assert_eq!(arranged(&manager, Dimensions::new(80, 24)), vec![("performance", 0), ("status", 23)]);
+        assert_eq!(manager.editor_area(&Dimensions::new(80, 24)), Rect::new(0, 1, 80, 22));
+    }
+
+    /// Hidden bars give their rows back to the editors.
+    #[test]
+    fn hidden_bars_are_not_arranged() {
+        let mut manager = BarManager::new();
+        manager.add(Box::new(StatusBar::new(1, Dock::Bottom)));
+        manager.add(Box::new(PerformanceBar::new(2, Dock::Bottom)));
+
+        assert!(manager.set_visible("performance", false));
+        assert!(!manager.is_visible("performance"));
+        assert_eq!(arranged(&manager, Dimensions::new(80, 24)), vec![("status", 23)]);
+        assert_eq!(manager.editor_area(&Dimensions::new(80, 24)), Rect::new(0, 0, 80, 23));
+        assert!(!manager.set_visible("missing", false));
+    }
+}
@@ -0,0 +1,40 @@
+use crate::CoreData;
+use super::{Bar, Dock};
+
+/// Displays the command line while a command or search is being typed.
+pub struct CommandBar {
+    /// Bar priority. Used to determine the order in which bars are rendered
+    ///   within the application. The smaller the priority number, the further
+    ///   towards the top of the application the bar is rendered.
+    priority: u8,
+
+    /// The edge of the terminal the bar is docked to.
+    dock: Dock,
+}
+
+impl Bar for CommandBar {
+    fn name(&self) -> &'static str {
+        "command"
+    }
+
+    fn priority(&self) -> u8 {
+        self.priority
+    }
+
+    fn dock(&self) -> Dock {
+        self.dock
+    }
+
+    fn render(&self, core_data: &CoreData) -> String {
+        core_data.command_line().unwrap_or_default().to_string()
+    }
+}
+
+impl CommandBar {
+    pub fn new(priority: u8, dock: Dock) -> CommandBar {
+        CommandBar {
+            priority,
+            dock,
+        }
+    }
+}
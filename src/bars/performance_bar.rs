@@ -1,5 +1,5 @@
 use crate::CoreData;
-use super::Bar;
+use super::{Bar, Dock};
 
 /// Displays performance statistics.
 pub struct PerformanceBar {
@@ -7,21 +7,38 @@ pub struct PerformanceBar {
     ///   within the application. The smaller the priority number, the further
     ///   towards the top of the application the bar is rendered.
     priority: u8,
+
+    /// The edge of the terminal the bar is docked to.
+    dock: Dock,
 }
 
 impl Bar for PerformanceBar {
+    fn name(&self) -> &'static str {
+        "performance"
+    }
+
+    fn priority(&self) -> u8 {
+        self.priority
+    }
+
+    fn dock(&self) -> Dock {
+        self.dock
+    }
+
     /// Renders the performance bar
     fn render(&self, core_data: &CoreData) -> String {
-        format!("[Performance] Uptime (secs): {} Frames: {} FPS: {}",
-        core_data.uptime(), core_data.frames(), core_data.fps())
+        format!("[Performance] Uptime (secs): {} Frames: {} FPS: {} Idle: {:.1}% Render (ms): {:.2}",
+        core_data.uptime(), core_data.frames(), core_data.fps(),
+        core_data.idle_percent(), core_data.render_time().as_secs_f64() * 1000.0)
     }
 }
 
 impl PerformanceBar {
-    pub fn new(priority: u8) -> PerformanceBar
+    pub fn new(priority: u8, dock: Dock) -> PerformanceBar
     {
         PerformanceBar {
             priority,
+            dock,
         }
     }
 }
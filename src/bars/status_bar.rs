@@ -1,29 +1,59 @@
 use crate::CoreData;
-use super::Bar;
+use super::{Bar, Dock};
 
 pub struct StatusBar {
     /// Bar priority. Used to determine the order in which bars are rendered
     ///   within the application. The smaller the priority number, the further
     ///   towards the top of the application the bar is rendered.
     priority: u8,
+
+    /// The edge of the terminal the bar is docked to.
+    dock: Dock,
 }
 
 impl Bar for StatusBar {
+    fn name(&self) -> &'static str {
+        "status"
+    }
+
+    fn priority(&self) -> u8 {
+        self.priority
+    }
+
+    fn dock(&self) -> Dock {
+        self.dock
+    }
+
     fn render(&self, core_data: &CoreData) -> String {
-        format!(
-            "[Status] File path: FILE_PATH_HERE [Dimensions]: {}, {} [Cursor]: {}, {}",
+        let mut status = format!(
+            "[Status] [{}] File path: {}{} [{}] [Dimensions]: {}, {} [Cursor]: {}, {}",
+            core_data.mode().name(),
+            core_data.file_path().unwrap_or("[No Name]"),
+            if core_data.modified() { " [+]" } else { "" },
+            core_data.line_ending(),
             core_data.dimensions().columns,
             core_data.dimensions().rows, 
             core_data.cursor_location().column_ix,
             core_data.cursor_location().row_ix
-        )
+        );
+        match core_data.search_matches() {
+            Some((Some(ix), count)) => status.push_str(&format!(" [Search]: match {} of {}", ix + 1, count)),
+            Some((None, 1)) => status.push_str(" [Search]: 1 match"),
+            Some((None, count)) => status.push_str(&format!(" [Search]: {} matches", count)),
+            None => {}
+        }
+        if let Some(message) = core_data.message() {
+            status.push_str(&format!(" [Message]: {}", message));
+        }
+        status
     }
 }
 
 impl StatusBar {
-    pub fn new(priority: u8) -> StatusBar {
+    pub fn new(priority: u8, dock: Dock) -> StatusBar {
         StatusBar {
             priority,
+            dock,
         }
     }
 }
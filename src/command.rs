@@ -0,0 +1,470 @@
+use std::fs;
+use std::ops::Range;
+use std::path::Path;
+
+/// What a command's arguments can be completed from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Completion {
+    /// The arguments aren't completed.
+    None,
+
+    /// Paths on the file system.
+    Path,
+
+    /// A fixed list of words, such as the names of settings.
+    Words(&'static [&'static str]),
+}
+
+/// The arguments given to a command.
+#[derive(Debug, Default, PartialEq)]
+pub struct Args {
+    /// Whether the command name was followed by `!`, which makes commands
+    ///   skip their usual safety checks.
+    pub bang: bool,
+
+    /// The arguments, split on unescaped whitespace.
+    pub values: Vec<String>,
+}
+
+impl Args {
+    /// Gets an argument.
+    ///
+    /// # Arguments
+    ///
+    /// * `ix` - The position of the argument.
+    pub fn get(&self, ix: usize) -> Option<&str> {
+        self.values.get(ix).map(String::as_str)
+    }
+}
+
+/// A named command that can be run from the command line or bound to a key.
+pub struct Command<T> {
+    /// The name the command is run by.
+    pub name: &'static str,
+
+    /// Other names the command can be run by, usually abbreviations.
+    pub aliases: &'static [&'static str],
+
+    /// A one-line usage summary, such as `:w [file]`.
+    pub usage: &'static str,
+
+    /// What the command does.
+    pub help: &'static str,
+
+    /// The fewest arguments the command accepts.
+    pub min_args: usize,
+
+    /// The most arguments the command accepts.
+    pub max_args: usize,
+
+    /// What the command's arguments can be completed from.
+    pub completion: Completion,
+
+    /// Runs the command, returning an error message if it fails.
+    pub action: fn(&mut T, &Args) -> Result<(), String>,
+}
+
+/// The commands available to run on a target, such as the program.
+pub struct Registry<T> {
+    commands: Vec<Command<T>>,
+}
+
+impl<T> Registry<T> {
+    pub fn new() -> Registry<T> {
+        Registry { commands: Vec::new() }
+    }
+
+    /// Adds a command.
+    ///
+    /// # Arguments
+    ///
+    /// * `command` - The command to add.
+    pub fn register(&mut self, command: Command<T>) {
+        self.commands.push(command);
+    }
+
+    /// Gets every command, in the order they were registered.
+    pub fn commands(&self) -> &[Command<T>] {
+        &self.commands
+    }
+
+    /// Finds a command by its name or one of its aliases.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name to look up.
+    pub fn find(&self, name: &str) -> Option<&Command<T>> {
+        self.commands
+            .iter()
+            .find(|command| command.name == name || command.aliases.contains(&name))
+    }
+
+    /// Parses a command line into a command and its arguments.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The command line, without the leading `:`.
+    pub fn parse(&self, line: &str) -> Result<(&Command<T>, Args), String> {
+        let line = line.trim_start();
+        let name_len = line
+            .find(|c: char| !c.is_alphanumeric() && c != '_')
+            .unwrap_or(line.len());
+        let (name, rest) = line.split_at(name_len);
+        if name.is_empty() {
+            return Err("No command given".to_string());
+        }
+        let command = self.find(name).ok_or_else(|| format!("Not a command: {}", name))?;
+
+        let (bang, rest) = match rest.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, rest),
+        };
+        if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+            return Err(format!("Trailing characters: {}", rest));
+        }
+        let values = split_args(rest);
+        if values.len() < command.min_args || values.len() > command.max_args {
+            return Err(format!("Usage: {}", command.usage));
+        }
+        Ok((command, Args { bang, values }))
+    }
+
+    /// Parses and runs a command line.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - What the command acts on.
+    /// * `line` - The command line, without the leading `:`.
+    pub fn run(&self, target: &mut T, line: &str) -> Result<(), String> {
+        let (command, args) = self.parse(line)?;
+        (command.action)(target, &args)
+    }
+
+    /// Lists the ways the last word of a partly typed command line could be
+    ///   completed.
+    ///
+    /// Returns the byte index at which the last word starts, and the words it
+    ///   could be replaced with.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The command line typed so far, without the leading `:`.
+    pub fn complete(&self, line: &str) -> (usize, Vec<String>) {
+        let word_start = last_word_start(line);
+        let word = &line[word_start..];
+        let before = line[..word_start].trim();
+
+        if before.is_empty() {
+            let candidates = self.commands
+                .iter()
+                .map(|command| command.name)
+                .filter(|name| name.starts_with(word))
+                .map(str::to_string)
+                .collect();
+            return (word_start, candidates);
+        }
+
+        let name = before.trim_end_matches('!');
+        let candidates = match self.find(name).map(|command| command.completion) {
+            Some(Completion::Path) => complete_path(word),
+            Some(Completion::Words(words)) => words
+                .iter()
+                .filter(|candidate| candidate.starts_with(word))
+                .map(|candidate| candidate.to_string())
+                .collect(),
+            _ => Vec::new(),
+        };
+        (word_start, candidates)
+    }
+}
+
+/// The line an address counts from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Line {
+    /// The line holding the cursor, written `.`.
+    Current,
+
+    /// The last line of the document, written `$`.
+    Last,
+
+    /// A line number, counting from one.
+    Number(usize),
+}
+
+/// A line written before a command, such as `12`, `.` or `$-2`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Address {
+    pub line: Line,
+
+    /// The number of lines to move on from the line, written as `+` and `-`
+    ///   followed by an optional count.
+    pub offset: isize,
+}
+
+impl Address {
+    /// Works out the line the address refers to, counting from zero. Lines
+    ///   before the first line resolve to the first line.
+    ///
+    /// # Arguments
+    ///
+    /// * `current_line` - The line holding the cursor.
+    /// * `line_count` - The number of lines in the document.
+    pub fn resolve(&self, current_line: usize, line_count: usize) -> usize {
+        let line_ix = match self.line {
+            Line::Current => current_line as isize,
+            Line::Last => line_count as isize - 1,
+            Line::Number(number) => number as isize - 1,
+        };
+        (line_ix + self.offset).max(0) as usize
+    }
+}
+
+/// The lines a command applies to, written before its name, such as `%`,
+///   `3,7` or `.,$`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineRange {
+    pub start: Address,
+    pub end: Address,
+}
+
+impl LineRange {
+    /// Works out the lines of a document the range covers, counting from
+    ///   zero. A range written backwards is turned around.
+    ///
+    /// Returns an error if the range runs past the end of the document.
+    ///
+    /// # Arguments
+    ///
+    /// * `current_line` - The line holding the cursor.
+    /// * `line_count` - The number of lines in the document.
+    pub fn resolve(&self, current_line: usize, line_count: usize) -> Result<Range<usize>, String> {
+        let start = self.start.resolve(current_line, line_count);
+        let end = self.end.resolve(current_line, line_count);
+        let (start, end) = (start.min(end), start.max(end));
+        if end >= line_count {
+            return Err("Invalid range".to_string());
+        }
+        Ok(start..end + 1)
+    }
+}
+
+/// Splits the range of lines off the start of a command line.
+///
+/// Returns the range, if there is one, and the rest of the line.
+///
+/// # Arguments
+///
+/// * `line` - The command line, without the leading `:`.
+pub fn parse_range(line: &str) -> Result<(Option<LineRange>, &str), String> {
+    let line = line.trim_start();
+    if let Some(rest) = line.strip_prefix('%') {
+        let range = LineRange {
+            start: Address { line: Line::Number(1), offset: 0 },
+            end: Address { line: Line::Last, offset: 0 },
+        };
+        return Ok((Some(range), rest));
+    }
+
+    let (start, rest) = parse_address(line)?;
+    match rest.strip_prefix(',') {
+        Some(rest) => {
+            let start = start.unwrap_or(Address { line: Line::Current, offset: 0 });
+            match parse_address(rest)? {
+                (Some(end), rest) => Ok((Some(LineRange { start, end }), rest)),
+                (None, _) => Err("Invalid range".to_string()),
+            }
+        }
+        None => Ok((start.map(|start| LineRange { start, end: start }), rest)),
+    }
+}
+
+/// Splits an address off the start of a command line, if it starts with
+///   one.
+fn parse_address(text: &str) -> Result<(Option<Address>, &str), String> {
+    let (line, mut rest) = match text.chars().next() {
+        Some('.') => (Some(Line::Current), &text[1..]),
+        Some('$') => (Some(Line::Last), &text[1..]),
+        Some(c) if c.is_ascii_digit() => {
+            let (number, rest) = split_number(text);
+            (Some(Line::Number(number.parse().map_err(|_| "Invalid range".to_string())?)), rest)
+        }
+        _ => (None, text),
+    };
+
+    let mut offset = 0;
+    let mut has_offset = false;
+    while let Some(sign) = rest.chars().next().filter(|c| *c == '+' || *c == '-') {
+        let (count, after) = split_number(&rest[1..]);
+        let count: isize = match count {
+            "" => 1,
+            count => count.parse().map_err(|_| "Invalid range".to_string())?,
+        };
+        offset += if sign == '+' { count } else { -count };
+        has_offset = true;
+        rest = after;
+    }
+
+    // An offset on its own counts from the current line
+    let line = match (line, has_offset) {
+        (Some(line), _) => line,
+        (None, true) => Line::Current,
+        (None, false) => return Ok((None, text)),
+    };
+    Ok((Some(Address { line, offset }), rest))
+}
+
+/// Splits the leading digits off some text.
+fn split_number(text: &str) -> (&str, &str) {
+    let len = text.find(|c: char| !c.is_ascii_digit()).unwrap_or(text.len());
+    text.split_at(len)
+}
+
+/// Splits arguments on whitespace, treating a backslash as escaping the
+///   character after it, so that paths may contain spaces.
+fn split_args(text: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut current = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.extend(chars.next()),
+            c if c.is_whitespace() => {
+                if !current.is_empty() {
+                    values.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        values.push(current);
+    }
+    values
+}
+
+/// Gets the byte index at which the last word of a line starts, skipping over
+///   escaped whitespace.
+fn last_word_start(line: &str) -> usize {
+    let mut start = 0;
+    let mut escaped = false;
+    for (ix, c) in line.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c.is_whitespace() {
+            start = ix + c.len_utf8();
+        }
+    }
+    start
+}
+
+/// Lists the paths that a partly typed path could be completed to.
+///
+/// Directories end with a separator, so that completion can continue into
+///   them. Spaces are escaped, so that the result is a single argument.
+fn complete_path(word: &str) -> Vec<String> {
+    let word = word.replace("\\ ", " ");
+    let (dir, prefix) = match word.rfind('/') {
+        Some(ix) => (&word[..ix + 1], &word[ix + 1..]),
+        None => ("", word.as_str()),
+    };
+    let entries = match fs::read_dir(if dir.is_empty() { Path::new(".") } else { Path::new(dir) }) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut candidates: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            // Hidden files are only offered once a dot has been typed
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            let suffix = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{}{}{}", dir, name, suffix).replace(' ', "\\ "))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A registry of commands that append to a log.
+    fn registry() -> Registry<Vec<String>> {
+        let mut registry = Registry::new();
+        registry.register(Command {
+            name: "write",
+            aliases: &["w"],
+            usage: ":write [file]",
+            help: "Writes the log",
+            min_args: 0,
+            max_args: 1,
+            completion: Completion::Path,
+            action: |log: &mut Vec<String>, args: &Args| {
+                log.push(format!("write {:?} {}", args.get(0), args.bang));
+                Ok(())
+            },
+        });
+        registry.register(Command {
+            name: "set",
+            aliases: &[],
+            usage: ":set option",
+            help: "Changes a setting",
+            min_args: 1,
+            max_args: 1,
+            completion: Completion::Words(&["tabwidth", "scrolloff"]),
+            action: |_: &mut Vec<String>, _: &Args| Err("failed".to_string()),
+        });
+        registry
+    }
+
+    /// Commands are found by name or alias, and receive their arguments.
+    #[test]
+    fn runs_commands() {
+        let registry = registry();
+        let mut log = Vec::new();
+
+        registry.run(&mut log, "w my\\ file.txt").unwrap();
+        registry.run(&mut log, "write!").unwrap();
+        assert_eq!(log, vec!["write Some(\"my file.txt\") false", "write None true"]);
+
+        assert_eq!(registry.run(&mut log, "set tabwidth"), Err("failed".to_string()));
+        assert_eq!(registry.run(&mut log, "set"), Err("Usage: :set option".to_string()));
+        assert_eq!(registry.run(&mut log, "nope"), Err("Not a command: nope".to_string()));
+        assert_eq!(registry.run(&mut log, "w?"), Err("Trailing characters: ?".to_string()));
+    }
+
+    /// Ranges of lines are split off the start of command lines, and resolve
+    ///   against the cursor and the length of the document.
+    #[test]
+    fn parses_ranges() {
+        let (range, rest) = parse_range("%s/a/b/").unwrap();
+        assert_eq!(rest, "s/a/b/");
+        assert_eq!(range.unwrap().resolve(3, 10), Ok(0..10));
+
+        let (range, rest) = parse_range(".,.+2 s").unwrap();
+        assert_eq!(rest, " s");
+        assert_eq!(range.unwrap().resolve(3, 10), Ok(3..6));
+
+        let (range, _) = parse_range("$-1,4").unwrap();
+        assert_eq!(range.unwrap().resolve(0, 10), Ok(3..9));
+        assert_eq!(parse_range("12").unwrap().0.unwrap().resolve(0, 10), Err("Invalid range".to_string()));
+        assert_eq!(parse_range("write").unwrap(), (None, "write"));
+        assert!(parse_range("3,").is_err());
+    }
+
+    /// Command names and arguments are completed.
+    #[test]
+    fn completes_commands_and_arguments() {
+        let registry = registry();
+
+        assert_eq!(registry.complete("wr"), (0, vec!["write".to_string()]));
+        assert_eq!(registry.complete("set s"), (4, vec!["scrolloff".to_string()]));
+        assert_eq!(registry.complete("set x"), (4, Vec::<String>::new()));
+    }
+}
@@ -0,0 +1,322 @@
+/// The most entries kept in the command history.
+const MAX_HISTORY: usize = 100;
+
+/// The text being typed on the command line, with its own cursor, history
+///   and completion state.
+pub struct CommandLine {
+    /// The text typed so far, without the leading `:`.
+    text: String,
+
+    /// The cursor position, as a char index into the text.
+    cursor: usize,
+
+    /// Previously run command lines, oldest first.
+    history: Vec<String>,
+
+    /// While browsing the history, the entry being shown.
+    history_ix: Option<usize>,
+
+    /// While browsing the history, the text that was typed before browsing
+    ///   started. Only entries starting with it are shown.
+    history_prefix: String,
+
+    /// While cycling through completions, the candidates and the one shown.
+    completion: Option<CompletionState>,
+}
+
+/// The completions being cycled through.
+struct CompletionState {
+    /// The byte index at which the completed word starts.
+    word_start: usize,
+    /// The word as it was typed, restored after the last candidate.
+    original: String,
+    candidates: Vec<String>,
+    /// The candidate being shown, or `None` for the original word.
+    ix: Option<usize>,
+}
+
+impl CommandLine {
+    pub fn new() -> CommandLine {
+        CommandLine {
+            text: String::new(),
+            cursor: 0,
+            history: Vec::new(),
+            history_ix: None,
+            history_prefix: String::new(),
+            completion: None,
+        }
+    }
+
+    /// Gets the text typed so far.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Gets the cursor position, as a char index into the text.
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    /// Clears the text, ready for a new command.
+    pub fn clear(&mut self) {
+        self.set_text(String::new());
+    }
+
+    /// Takes the typed text to be run, adding it to the history.
+    pub fn submit(&mut self) -> String {
+        let text = std::mem::take(&mut self.text);
+        self.clear();
+        if !text.trim().is_empty() {
+            // Keep one copy of each command, at its most recent position
+            self.history.retain(|entry| *entry != text);
+            self.history.push(text.clone());
+            if self.history.len() > MAX_HISTORY {
+                self.history.remove(0);
+            }
+        }
+        text
+    }
+
+    /// Inserts a character at the cursor.
+    ///
+    /// # Arguments
+    ///
+    /// * `c` - The character to insert.
+    pub fn insert(&mut self, c: char) {
+        self.stop_browsing();
+        let byte_ix = self.byte_ix(self.cursor);
+        self.text.insert(byte_ix, c);
+        self.cursor += 1;
+    }
+
+    /// Deletes the character before the cursor.
+    pub fn delete_backward(&mut self) {
+        if self.cursor > 0 {
+            self.stop_browsing();
+            self.cursor -= 1;
+            let byte_ix = self.byte_ix(self.cursor);
+            self.text.remove(byte_ix);
+        }
+    }
+
+    /// Deletes the character under the cursor.
+    pub fn delete_forward(&mut self) {
+        if self.cursor < self.text.chars().count() {
+            self.stop_browsing();
+            let byte_ix = self.byte_ix(self.cursor);
+            self.text.remove(byte_ix);
+        }
+    }
+
+    /// Deletes the word before the cursor, and any whitespace after it.
+    pub fn delete_word_backward(&mut self) {
+        self.stop_browsing();
+        let chars: Vec<char> = self.text.chars().collect();
+        let mut start = self.cursor;
+        while start > 0 && chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !chars[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        let range = self.byte_ix(start)..self.byte_ix(self.cursor);
+        self.text.replace_range(range, "");
+        self.cursor = start;
+    }
+
+    /// Deletes everything before the cursor.
+    pub fn delete_to_start(&mut self) {
+        self.stop_browsing();
+        let byte_ix = self.byte_ix(self.cursor);
+        self.text.replace_range(..byte_ix, "");
+        self.cursor = 0;
+    }
+
+    pub fn move_left(&mut self) {
+        self.cursor = self.cursor.saturating_sub(1);
+    }
+
+    pub fn move_right(&mut self) {
+        self.cursor = (self.cursor + 1).min(self.text.chars().count());
+    }
+
+    pub fn move_to_start(&mut self) {
+        self.cursor = 0;
+    }
+
+    pub fn move_to_end(&mut self) {
+        self.cursor = self.text.chars().count();
+    }
+
+    /// Shows the previous history entry that starts with the text typed
+    ///   before browsing started.
+    pub fn history_previous(&mut self) {
+        self.completion = None;
+        if self.history_ix.is_none() {
+            self.history_prefix = self.text.clone();
+        }
+        let end = self.history_ix.unwrap_or(self.history.len());
+        let found = self.history[..end]
+            .iter()
+            .rposition(|entry| entry.starts_with(&self.history_prefix));
+        if let Some(ix) = found {
+            self.history_ix = Some(ix);
+            self.show(self.history[ix].clone());
+        }
+    }
+
+    /// Shows the next history entry that starts with the text typed before
+    ///   browsing started, or that text itself after the newest entry.
+    pub fn history_next(&mut self) {
+        self.completion = None;
+        let start = match self.history_ix {
+            Some(ix) => ix + 1,
+            None => return,
+        };
+        let found = self.history[start..]
+            .iter()
+            .position(|entry| entry.starts_with(&self.history_prefix));
+        match found {
+            Some(offset) => {
+                self.history_ix = Some(start + offset);
+                self.show(self.history[start + offset].clone());
+            }
+            None => {
+                self.history_ix = None;
+                self.show(self.history_prefix.clone());
+            }
+        }
+    }
+
+    /// Completes the word before the cursor, or shows the next completion if
+    ///   completion is under way. After the last candidate, the word is
+    ///   restored as it was typed.
+    ///
+    /// # Arguments
+    ///
+    /// * `complete` - Lists the completions of a line, returning the byte index
+    ///   at which the completed word starts and the candidates.
+    pub fn complete<F>(&mut self, complete: F)
+    where
+        F: FnOnce(&str) -> (usize, Vec<String>),
+    {
+        if self.completion.is_none() {
+            let cursor_byte = self.byte_ix(self.cursor);
+            let (word_start, candidates) = complete(&self.text[..cursor_byte]);
+            self.text.truncate(cursor_byte);
+            self.completion = Some(CompletionState {
+                word_start,
+                original: self.text[word_start..].to_string(),
+                candidates,
+                ix: None,
+            });
+        }
+
+        let state = self.completion.as_mut().unwrap();
+        if state.candidates.is_empty() {
+            return;
+        }
+        state.ix = match state.ix {
+            None => Some(0),
+            Some(ix) if ix + 1 < state.candidates.len() => Some(ix + 1),
+            Some(_) if state.candidates.len() == 1 => Some(0),
+            Some(_) => None,
+        };
+        let word = match state.ix {
+            Some(ix) => state.candidates[ix].clone(),
+            None => state.original.clone(),
+        };
+        let word_start = state.word_start;
+        self.text.replace_range(word_start.., &word);
+        self.cursor = self.text.chars().count();
+    }
+
+    /// Replaces the text, keeping the history browsing state.
+    fn show(&mut self, text: String) {
+        self.text = text;
+        self.cursor = self.text.chars().count();
+    }
+
+    /// Replaces the text, ending any history browsing or completion.
+    fn set_text(&mut self, text: String) {
+        self.stop_browsing();
+        self.show(text);
+    }
+
+    /// Ends any history browsing or completion, keeping the text shown.
+    fn stop_browsing(&mut self) {
+        self.history_ix = None;
+        self.completion = None;
+    }
+
+    /// Gets the byte index of a char index into the text.
+    fn byte_ix(&self, char_ix: usize) -> usize {
+        self.text.char_indices().nth(char_ix).map_or(self.text.len(), |(ix, _)| ix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Types text into a command line.
+    fn type_text(command_line: &mut CommandLine, text: &str) {
+        for c in text.chars() {
+            command_line.insert(c);
+        }
+    }
+
+    /// Text can be edited anywhere on the line.
+    #[test]
+    fn edits_text() {
+        let mut command_line = CommandLine::new();
+        type_text(&mut command_line, "wrte foo");
+        command_line.move_to_start();
+        command_line.move_right();
+        command_line.move_right();
+        command_line.insert('i');
+        assert_eq!(command_line.text(), "write foo");
+
+        command_line.move_to_end();
+        command_line.delete_word_backward();
+        assert_eq!(command_line.text(), "write ");
+        command_line.delete_backward();
+        assert_eq!(command_line.text(), "write");
+    }
+
+    /// History is browsed by prefix, returning to the typed text at the end.
+    #[test]
+    fn browses_history() {
+        let mut command_line = CommandLine::new();
+        for command in ["w a", "set x", "w b"] {
+            type_text(&mut command_line, command);
+            command_line.submit();
+        }
+
+        type_text(&mut command_line, "w");
+        command_line.history_previous();
+        assert_eq!(command_line.text(), "w b");
+        command_line.history_previous();
+        assert_eq!(command_line.text(), "w a");
+        command_line.history_previous();
+        assert_eq!(command_line.text(), "w a");
+        command_line.history_next();
+        command_line.history_next();
+        assert_eq!(command_line.text(), "w");
+    }
+
+    /// Completion cycles through the candidates, then back to the typed word.
+    #[test]
+    fn cycles_completions() {
+        let mut command_line = CommandLine::new();
+        type_text(&mut command_line, "set s");
+        let candidates = |_: &str| (4, vec!["scrolloff".to_string(), "statusbar".to_string()]);
+
+        command_line.complete(candidates);
+        assert_eq!(command_line.text(), "set scrolloff");
+        command_line.complete(candidates);
+        assert_eq!(command_line.text(), "set statusbar");
+        command_line.complete(candidates);
+        assert_eq!(command_line.text(), "set s");
+    }
+}
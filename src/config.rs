@@ -0,0 +1,77 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Gets the directory holding the user's configuration files, following the
+///   XDG base directory conventions.
+pub fn config_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("stated"))
+}
+
+/// Notices when a file changes on disk, by comparing its modification time
+///   and size with when it was last checked.
+pub struct FileWatch {
+    path: PathBuf,
+    /// The modification time and size of the file when last checked, or
+    ///   None if it didn't exist.
+    stamp: Option<(SystemTime, u64)>,
+}
+
+impl FileWatch {
+    /// Starts watching a file, which need not exist yet.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file.
+    pub fn new(path: &Path) -> FileWatch {
+        FileWatch { path: path.to_path_buf(), stamp: stamp(path) }
+    }
+
+    /// Gets the path of the watched file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Checks whether the file has been changed, created or removed since it
+    ///   was last checked.
+    pub fn changed(&mut self) -> bool {
+        let stamp = stamp(&self.path);
+        let changed = stamp != self.stamp;
+        self.stamp = stamp;
+        changed
+    }
+}
+
+/// Gets the modification time and size of a file.
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::ScratchPath;
+
+    /// Changes are noticed once each, including the file being removed.
+    #[test]
+    fn notices_changes() {
+        let path = ScratchPath::new("config-watch.toml");
+        let mut watch = FileWatch::new(&path);
+        assert!(!watch.changed());
+
+        fs::write(&path, "tabwidth = 2\n").unwrap();
+        assert!(watch.changed());
+        assert!(!watch.changed());
+
+        fs::write(&path, "tabwidth = 8\nscrolloff = 1\n").unwrap();
+        assert!(watch.changed());
+
+        fs::remove_file(&path).unwrap();
+        assert!(watch.changed());
+    }
+}
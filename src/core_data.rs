@@ -1,30 +1,87 @@
-use std::time::{Duration, SystemTime};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use crate::document::LineEnding;
 use crate::editor::{Dimensions,Location};
+use crate::mode::Mode;
 
 pub struct CoreData {
-    start_time: SystemTime,
+    start_time: Instant,
     running_for_secs: u64,
     frames: u64,
+    /// The time spent waiting for something to happen.
+    idle_time: Duration,
+    /// The time taken to draw the most recent frame.
+    render_time: Duration,
     dimensions: Dimensions,
     cursor_location: Location,
+    file_path: Option<String>,
+    modified: bool,
+    message: Option<String>,
+    mode: Mode,
+    line_ending: String,
+    command_line: Option<String>,
+    /// While search matches are highlighted, the match under the cursor (if
+    ///   any) and the number of matches.
+    search_matches: Option<(Option<usize>, usize)>,
 }
 
 impl CoreData {
     pub fn new() -> CoreData {
         CoreData {
-            start_time: SystemTime::now(),
+            start_time: Instant::now(),
             running_for_secs: 0,
             frames: 0,
+            idle_time: Duration::ZERO,
+            render_time: Duration::ZERO,
             dimensions: Dimensions::default(),
-            cursor_location: Location::default()
+            cursor_location: Location::default(),
+            file_path: None,
+            modified: false,
+            message: None,
+            mode: Mode::Normal,
+            line_ending: String::new(),
+            command_line: None,
+            search_matches: None,
         }
     }
 
     pub fn tick(&mut self) {
-        let now = SystemTime::now();
-        let time: Duration = now.duration_since(self.start_time).unwrap();
-        self.running_for_secs = time.as_secs();
+        self.running_for_secs = self.start_time.elapsed().as_secs();
+    }
+
+    /// Counts a frame that was redrawn on the terminal.
+    ///
+    /// # Arguments
+    ///
+    /// * `render_time` - How long the frame took to draw.
+    pub fn record_frame(&mut self, render_time: Duration) {
         self.frames += 1;
+        self.render_time = render_time;
+    }
+
+    /// Adds to the time spent waiting for something to happen.
+    ///
+    /// # Arguments
+    ///
+    /// * `idle_time` - How long the program was waiting.
+    pub fn record_idle(&mut self, idle_time: Duration) {
+        self.idle_time += idle_time;
+    }
+
+    /// Gets the percentage of the uptime spent waiting for something to
+    ///   happen.
+    pub fn idle_percent(&self) -> f64 {
+        let uptime = self.start_time.elapsed().as_secs_f64();
+        if uptime > 0.0 {
+            (self.idle_time.as_secs_f64() / uptime * 100.0).min(100.0)
+        } else {
+            0.0
+        }
+    }
+
+    /// Gets the time taken to draw the most recent frame.
+    pub fn render_time(&self) -> Duration {
+        self.render_time
     }
 
     pub fn frames(&self) -> u64 {
@@ -36,15 +93,11 @@ impl CoreData {
     }
 
     pub fn fps(&self) -> u64 {
-        if self.running_for_secs == 0 {
-            return 0;
-        } else {
-            return self.frames / self.running_for_secs;
-        }
+        self.frames.checked_div(self.running_for_secs).unwrap_or(0)
     }
 
     pub fn dimensions(&self) -> &Dimensions {
-        return &self.dimensions;
+        &self.dimensions
     }
 
     pub fn update_dimensions(&mut self, dimensions: &Dimensions) {
@@ -52,10 +105,78 @@ impl CoreData {
     }
 
     pub fn cursor_location(&self) -> &Location {
-        return &self.cursor_location;
+        &self.cursor_location
     }
 
     pub fn update_cursor_location(&mut self, location: &Location) {
         self.cursor_location = Location::from(location);
     }
-}
\ No newline at end of file
+
+    pub fn file_path(&self) -> Option<&str> {
+        self.file_path.as_deref()
+    }
+
+    pub fn update_file_path(&mut self, file_path: Option<&Path>) {
+        self.file_path = file_path.map(|p| p.display().to_string());
+    }
+
+    pub fn modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn update_modified(&mut self, modified: bool) {
+        self.modified = modified;
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn set_message(&mut self, message: String) {
+        self.message = Some(message);
+    }
+
+    pub fn clear_message(&mut self) {
+        self.message = None;
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    pub fn update_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    pub fn line_ending(&self) -> &str {
+        &self.line_ending
+    }
+
+    pub fn update_line_ending(&mut self, line_ending: LineEnding, mixed: bool) {
+        self.line_ending = if mixed {
+            format!("{} (mixed)", line_ending.name())
+        } else {
+            line_ending.name().to_string()
+        };
+    }
+
+    /// Gets the command line as shown, starting with the `:`, `/` or `?` it
+    ///   was opened with, if a command or search is being typed.
+    pub fn command_line(&self) -> Option<&str> {
+        self.command_line.as_deref()
+    }
+
+    pub fn update_command_line(&mut self, command_line: Option<&str>) {
+        self.command_line = command_line.map(str::to_string);
+    }
+
+    /// Gets the match of the search under the cursor, counting from zero,
+    ///   and the number of matches, while matches are highlighted.
+    pub fn search_matches(&self) -> Option<(Option<usize>, usize)> {
+        self.search_matches
+    }
+
+    pub fn update_search_matches(&mut self, search_matches: Option<(Option<usize>, usize)>) {
+        self.search_matches = search_matches;
+    }
+}
@@ -1,3 +1,18 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+pub mod filetype;
+mod history;
+mod line_ending;
+mod persistence;
+pub mod recovery;
+pub mod swap;
 mod text_document;
 
-pub use text_document::TextDocument;
\ No newline at end of file
+pub use line_ending::LineEnding;
+pub use text_document::TextDocument;
+
+/// A shared handle to an open document. Several editors may display (and
+///   edit) the same document, so documents are reference counted rather than
+///   borrowed.
+pub type DocumentHandle = Rc<RefCell<TextDocument>>;
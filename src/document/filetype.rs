@@ -0,0 +1,75 @@
+use std::path::Path;
+
+/// Works out the type of a file from its name, for choosing settings that
+///   apply to that type of file.
+///
+/// Returns None if the type isn't recognized.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file.
+pub fn detect(path: &Path) -> Option<&'static str> {
+    let name = path.file_name()?.to_str()?;
+    match name {
+        "Cargo.lock" => return Some("toml"),
+        ".bashrc" | ".bash_profile" | ".profile" | ".zshrc" => return Some("shell"),
+        _ => {}
+    }
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "rs" => Some("rust"),
+        "toml" => Some("toml"),
+        "md" | "markdown" => Some("markdown"),
+        "json" => Some("json"),
+        "sh" | "bash" | "zsh" => Some("shell"),
+        "txt" => Some("text"),
+        _ => None,
+    }
+}
+
+/// Works out the type of a script from the interpreter named on its first
+///   line, such as `#!/bin/sh` or `#!/usr/bin/env bash`.
+///
+/// Returns None if the line isn't a shebang, or the interpreter isn't
+///   recognized.
+///
+/// # Arguments
+///
+/// * `line` - The first line of the file.
+pub fn detect_shebang(line: &str) -> Option<&'static str> {
+    let mut words = line.strip_prefix("#!")?.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        // Options of env, such as `-S`, come before the program
+        program = words.find(|word| !word.starts_with('-'))?;
+    }
+    match program {
+        "sh" | "bash" | "zsh" | "ksh" | "dash" => Some("shell"),
+        "cargo" => Some("rust"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// File types are recognized by extension, or by well known names.
+    #[test]
+    fn detects_file_types() {
+        assert_eq!(detect(Path::new("src/main.rs")), Some("rust"));
+        assert_eq!(detect(Path::new("README.MD")), Some("markdown"));
+        assert_eq!(detect(Path::new("/home/me/.bashrc")), Some("shell"));
+        assert_eq!(detect(Path::new("Makefile")), None);
+    }
+
+    /// Scripts are recognized by the interpreter on their first line.
+    #[test]
+    fn detects_shebangs() {
+        assert_eq!(detect_shebang("#!/bin/sh"), Some("shell"));
+        assert_eq!(detect_shebang("#! /usr/bin/env bash"), Some("shell"));
+        assert_eq!(detect_shebang("#!/usr/bin/env -S cargo +nightly -Zscript"), Some("rust"));
+        assert_eq!(detect_shebang("#!/usr/bin/python3"), None);
+        assert_eq!(detect_shebang("# /bin/sh"), None);
+    }
+}
@@ -0,0 +1,166 @@
+use std::time::SystemTime;
+
+/// A single change to the text of a document.
+#[derive(Clone, Debug)]
+pub struct Edit {
+    /// The char index at which the change was made.
+    pub char_ix: usize,
+
+    /// The text that was removed.
+    pub removed: String,
+
+    /// The text that was inserted in its place.
+    pub inserted: String,
+
+    /// When the change was made.
+    pub timestamp: SystemTime,
+}
+
+/// A group of edits that are undone and redone together, such as all of the
+///   text typed during one visit to insert mode.
+#[derive(Clone, Debug)]
+pub struct Transaction {
+    /// Identifies the state of the document after this transaction.
+    pub id: u64,
+
+    /// The edits in the order they were made.
+    pub edits: Vec<Edit>,
+
+    /// The char index of the cursor before the transaction.
+    pub cursor_before: usize,
+
+    /// The char index of the cursor after the transaction.
+    pub cursor_after: usize,
+}
+
+impl Transaction {
+    /// Gets when the transaction started.
+    pub fn timestamp(&self) -> SystemTime {
+        self.edits.first().map_or(SystemTime::UNIX_EPOCH, |edit| edit.timestamp)
+    }
+}
+
+/// The undo and redo stacks of a document.
+pub struct History {
+    /// Transactions that can be undone, oldest first.
+    undo_stack: Vec<Transaction>,
+
+    /// Transactions that have been undone and can be redone, most recently
+    ///   undone last.
+    redo_stack: Vec<Transaction>,
+
+    /// The transaction that edits are currently being grouped into, if any.
+    open: Option<Transaction>,
+
+    /// The id to give the next transaction.
+    next_id: u64,
+}
+
+impl History {
+    pub fn new() -> History {
+        History {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            open: None,
+            next_id: 1,
+        }
+    }
+
+    /// Gets an id for the current state of the document. The id changes with
+    ///   every edit, undo and redo, and returns to an earlier value when the
+    ///   document returns to an earlier state.
+    pub fn state_id(&self) -> u64 {
+        match &self.open {
+            Some(transaction) if !transaction.edits.is_empty() => transaction.id,
+            _ => self.undo_stack.last().map_or(0, |transaction| transaction.id),
+        }
+    }
+
+    /// Starts grouping edits into a single transaction. Does nothing if a
+    ///   transaction is already open.
+    ///
+    /// # Arguments
+    ///
+    /// * `cursor` - The char index of the cursor before the transaction.
+    pub fn begin(&mut self, cursor: usize) {
+        if self.open.is_none() {
+            self.open = Some(self.new_transaction(cursor));
+        }
+    }
+
+    /// Stops grouping edits, making the open transaction undoable.
+    ///
+    /// # Arguments
+    ///
+    /// * `cursor` - The char index of the cursor after the transaction.
+    pub fn commit(&mut self, cursor: usize) {
+        if let Some(mut transaction) = self.open.take() {
+            if !transaction.edits.is_empty() {
+                transaction.cursor_after = cursor;
+                self.undo_stack.push(transaction);
+            }
+        }
+    }
+
+    /// Records an edit. Edits made outside of a transaction are undone on
+    ///   their own.
+    ///
+    /// # Arguments
+    ///
+    /// * `edit` - The edit that was made.
+    pub fn record(&mut self, edit: Edit) {
+        // Making a change abandons anything that was undone
+        self.redo_stack.clear();
+
+        let cursor_after = edit.char_ix + edit.inserted.chars().count();
+        match &mut self.open {
+            Some(transaction) => {
+                transaction.cursor_after = cursor_after;
+                transaction.edits.push(edit);
+            }
+            None => {
+                let mut transaction = self.new_transaction(edit.char_ix);
+                transaction.cursor_after = cursor_after;
+                transaction.edits.push(edit);
+                self.undo_stack.push(transaction);
+            }
+        }
+    }
+
+    /// Takes the most recent transaction off the undo stack so that it can be
+    ///   reverted. Any open transaction is committed first.
+    pub fn undo(&mut self) -> Option<&Transaction> {
+        self.commit_in_place();
+        let transaction = self.undo_stack.pop()?;
+        self.redo_stack.push(transaction);
+        self.redo_stack.last()
+    }
+
+    /// Takes the most recently undone transaction so that it can be reapplied.
+    pub fn redo(&mut self) -> Option<&Transaction> {
+        self.commit_in_place();
+        let transaction = self.redo_stack.pop()?;
+        self.undo_stack.push(transaction);
+        self.undo_stack.last()
+    }
+
+    /// Gets when the most recent undoable transaction started.
+    pub fn last_timestamp(&mut self) -> Option<SystemTime> {
+        self.commit_in_place();
+        self.undo_stack.last().map(Transaction::timestamp)
+    }
+
+    /// Commits any open transaction, leaving the cursor where its last edit
+    ///   left it.
+    fn commit_in_place(&mut self) {
+        let cursor = self.open.as_ref().map_or(0, |transaction| transaction.cursor_after);
+        self.commit(cursor);
+    }
+
+    /// Creates an empty transaction with a fresh id.
+    fn new_transaction(&mut self, cursor: usize) -> Transaction {
+        let id = self.next_id;
+        self.next_id += 1;
+        Transaction { id, edits: Vec::new(), cursor_before: cursor, cursor_after: cursor }
+    }
+}
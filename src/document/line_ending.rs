@@ -0,0 +1,128 @@
+/// A style of line break.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LineEnding {
+    /// A line feed (`\n`), as used on Unix-like systems.
+    Lf,
+
+    /// A carriage return followed by a line feed (`\r\n`), as used on Windows.
+    CrLf,
+
+    /// A lone carriage return (`\r`), as used on classic Mac OS.
+    Cr,
+}
+
+impl LineEnding {
+    /// Gets the default line ending for the platform.
+    pub fn platform_default() -> LineEnding {
+        if cfg!(windows) {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    /// Gets the text of the line break.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+
+    /// Gets the name of the line ending, as displayed to the user.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "LF",
+            LineEnding::CrLf => "CRLF",
+            LineEnding::Cr => "CR",
+        }
+    }
+
+    /// Parses a line ending from its name (case insensitive), as accepted from
+    ///   the user.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the line ending, such as `lf` or `crlf`.
+    pub fn from_name(name: &str) -> Option<LineEnding> {
+        match name.to_ascii_lowercase().as_str() {
+            "lf" | "unix" => Some(LineEnding::Lf),
+            "crlf" | "dos" => Some(LineEnding::CrLf),
+            "cr" | "mac" => Some(LineEnding::Cr),
+            _ => None,
+        }
+    }
+}
+
+/// The line endings found in some text.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineEndingSummary {
+    /// The most common line ending in the text, or the platform default if
+    ///   the text has no line breaks.
+    pub line_ending: LineEnding,
+
+    /// Whether the text contains more than one style of line ending.
+    pub mixed: bool,
+}
+
+/// Detects the line endings used in some text.
+///
+/// # Arguments
+///
+/// * `chars` - The chars of the text to examine.
+pub fn detect<I>(chars: I) -> LineEndingSummary
+where
+    I: Iterator<Item = char>,
+{
+    let (mut lf, mut crlf, mut cr) = (0, 0, 0);
+    let mut chars = chars.peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\n' => lf += 1,
+            '\r' if chars.peek() == Some(&'\n') => {
+                chars.next();
+                crlf += 1;
+            }
+            '\r' => cr += 1,
+            _ => {}
+        }
+    }
+
+    let line_ending = if lf + crlf + cr == 0 {
+        LineEnding::platform_default()
+    } else if crlf >= lf && crlf >= cr {
+        LineEnding::CrLf
+    } else if lf >= cr {
+        LineEnding::Lf
+    } else {
+        LineEnding::Cr
+    };
+    let styles_used = [lf, crlf, cr].iter().filter(|&&count| count > 0).count();
+
+    LineEndingSummary { line_ending, mixed: styles_used > 1 }
+}
+
+/// Rewrites every line break in some text to use the same line ending.
+///
+/// # Arguments
+///
+/// * `text` - The text to convert.
+/// * `line_ending` - The line ending to use.
+pub fn normalize(text: &str, line_ending: LineEnding) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' => {
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                result.push_str(line_ending.as_str());
+            }
+            '\n' => result.push_str(line_ending.as_str()),
+            c => result.push(c),
+        }
+    }
+    result
+}
@@ -0,0 +1,58 @@
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Writes a file atomically.
+///
+/// The content is written to a temporary file alongside the target, which is
+///   then renamed over the target. Readers therefore see either the old file
+///   or the new file, never a partially written one. If the target already
+///   exists its permissions are carried over to the new file.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file to write.
+/// * `write` - Writes the file content to the supplied writer.
+pub fn write_atomic<F>(path: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> io::Result<()>,
+{
+    let temp_path = temp_path_for(path);
+    let result = write_temp_file(path, &temp_path, write)
+        .and_then(|_| fs::rename(&temp_path, path));
+    if result.is_err() {
+        // Don't leave partial temp files lying around
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Writes the content to the temporary file and flushes it to disk.
+fn write_temp_file<F>(path: &Path, temp_path: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut dyn Write) -> io::Result<()>,
+{
+    let file = File::create(temp_path)?;
+    let mut writer = BufWriter::new(file);
+    write(&mut writer)?;
+    let file = writer.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+
+    // Keep the permissions of the file being replaced
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(temp_path, metadata.permissions())?;
+    }
+    Ok(())
+}
+
+/// Gets the temporary path used while writing a file.
+///
+/// The temporary file lives in the same directory as the target so that the
+///   final rename does not cross filesystems.
+fn temp_path_for(path: &Path) -> PathBuf {
+    let mut name = OsString::from(".");
+    name.push(path.file_name().unwrap_or_default());
+    name.push(format!(".{}.stated-tmp", std::process::id()));
+    path.with_file_name(name)
+}
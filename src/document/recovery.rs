@@ -0,0 +1,359 @@
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use super::{persistence, DocumentHandle};
+use super::swap::{self, SwapInfo, SwapStatus};
+
+/// What to do with a file that was found to have a swap file.
+#[derive(Debug, PartialEq)]
+pub enum Resolution {
+    /// Open the file as it is on disk.
+    Open,
+
+    /// Open the file, then replace its content with the recovered text.
+    Recover(String),
+
+    /// Don't open the file; stop instead.
+    Quit,
+}
+
+/// Checks a file that is about to be opened for a swap file, and asks the user
+///   what to do about any that is found.
+///
+/// Runs before the terminal is taken over, so asks on the normal screen. A
+///   swap file holding nothing new is removed without asking. A swap file
+///   that is discarded is removed.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file about to be opened.
+/// * `input` - Where to read the user's answers from.
+/// * `output` - Where to write questions to.
+pub fn resolve_swap<R, W>(path: &Path, input: &mut R, output: &mut W) -> io::Result<Resolution>
+where
+    R: BufRead,
+    W: Write,
+{
+    match swap::inspect(path)? {
+        SwapStatus::Absent => Ok(Resolution::Open),
+        SwapStatus::Unchanged(info) => {
+            fs::remove_file(&info.swap_path)?;
+            Ok(Resolution::Open)
+        }
+        SwapStatus::Locked(info) => {
+            writeln!(
+                output,
+                "stated: '{}' is being edited by another instance (pid {} on {})",
+                path.display(), info.pid, info.host
+            )?;
+            // Whether the owner is still running can't always be checked, such
+            //  as when it ran on another host, so its changes can be taken
+            //  over if the user knows it has gone
+            writeln!(output, "  If it is no longer running, its unsaved changes can be recovered.")?;
+            loop {
+                match ask(input, output, "(e)dit anyway, (r)ecover, (d)iff, (q)uit? ")?.as_deref() {
+                    Some("e") => return Ok(Resolution::Open),
+                    Some("r") => {
+                        fs::remove_file(&info.swap_path)?;
+                        return Ok(Resolution::Recover(info.content));
+                    }
+                    Some("d") => write_diff(path, &info, output)?,
+                    Some("q") | None => return Ok(Resolution::Quit),
+                    _ => {}
+                }
+            }
+        }
+        SwapStatus::Recoverable(info) => {
+            writeln!(
+                output,
+                "stated: found unsaved changes to '{}' in {}",
+                path.display(), info.swap_path.display()
+            )?;
+            if info.file_is_newer {
+                writeln!(output, "  The file has been changed since the swap file was written.")?;
+            }
+            loop {
+                match ask(input, output, "(r)ecover, (d)iff, (x) discard, (q)uit? ")?.as_deref() {
+                    Some("r") => return Ok(Resolution::Recover(info.content)),
+                    Some("d") => write_diff(path, &info, output)?,
+                    Some("x") => {
+                        fs::remove_file(&info.swap_path)?;
+                        return Ok(Resolution::Open);
+                    }
+                    Some("q") | None => return Ok(Resolution::Quit),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+/// Asks a question, returning the trimmed answer, or `None` if there is no
+///   more input.
+fn ask<R, W>(input: &mut R, output: &mut W, question: &str) -> io::Result<Option<String>>
+where
+    R: BufRead,
+    W: Write,
+{
+    write!(output, "{}", question)?;
+    output.flush()?;
+    let mut answer = String::new();
+    if input.read_line(&mut answer)? == 0 {
+        return Ok(None);
+    }
+    Ok(Some(answer.trim().to_string()))
+}
+
+/// Writes the differences between a file and the content of its swap file.
+fn write_diff<W>(path: &Path, info: &SwapInfo, output: &mut W) -> io::Result<()>
+where
+    W: Write,
+{
+    let file_content = fs::read_to_string(path).unwrap_or_default();
+    writeln!(output, "--- {}", path.display())?;
+    writeln!(output, "+++ {}", info.swap_path.display())?;
+    for line in diff_lines(&file_content, &info.content) {
+        writeln!(output, "{}", line)?;
+    }
+    Ok(())
+}
+
+/// The largest number of line pairs compared when diffing, beyond which the
+///   changed region is shown as wholly removed and re-added.
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/// Lists the lines that differ between two texts, each prefixed with `-` if
+///   it is only in the old text or `+` if it is only in the new text.
+///
+/// # Arguments
+///
+/// * `old` - The original text.
+/// * `new` - The changed text.
+pub fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+
+    // Only the region between the common prefix and suffix needs comparing
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let old = &old[prefix..old.len() - suffix];
+    let new = &new[prefix..new.len() - suffix];
+
+    let removed = |line: &&str| format!("-{}", line);
+    let added = |line: &&str| format!("+{}", line);
+    if old.len() * new.len() > MAX_DIFF_CELLS {
+        return old.iter().map(removed).chain(new.iter().map(added)).collect();
+    }
+
+    // Longest common subsequence lengths of every pair of suffixes
+    let mut lengths = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i][j] = if old[i] == new[j] {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut result = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            result.push(removed(&old[i]));
+            i += 1;
+        } else {
+            result.push(added(&new[j]));
+            j += 1;
+        }
+    }
+    result
+}
+
+/// The start of the name of every file that unsaved changes are dumped to.
+const DUMP_PREFIX: &str = "stated-recovery-";
+
+/// Gets the path of the file that unsaved changes are dumped to if the
+///   program crashes.
+pub fn emergency_dump_path() -> PathBuf {
+    std::env::temp_dir().join(format!("{}{}.txt", DUMP_PREFIX, std::process::id()))
+}
+
+/// Finds the files that earlier instances dumped unsaved changes to when
+///   they crashed or lost their terminal. They may have had nowhere to say so
+///   at the time.
+///
+/// # Arguments
+///
+/// * `dir` - The directory dumps are written to.
+pub fn find_emergency_dumps(dir: &Path) -> Vec<PathBuf> {
+    let own_name = format!("{}{}.txt", DUMP_PREFIX, std::process::id());
+    let mut dumps: Vec<PathBuf> = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.starts_with(DUMP_PREFIX) && name.ends_with(".txt") && name != own_name
+        })
+        .map(|entry| entry.path())
+        .collect();
+    dumps.sort();
+    dumps
+}
+
+/// Describes where unsaved changes from earlier instances were dumped, or
+///   returns None if there are no dumps.
+///
+/// # Arguments
+///
+/// * `dir` - The directory dumps are written to.
+pub fn describe_emergency_dumps(dir: &Path) -> Option<String> {
+    match find_emergency_dumps(dir).as_slice() {
+        [] => None,
+        [dump] => Some(format!("Unsaved changes from an earlier session are in {}", dump.display())),
+        dumps => Some(format!(
+            "Unsaved changes from {} earlier sessions are in {}",
+            dumps.len(), dir.join(format!("{}*.txt", DUMP_PREFIX)).display()
+        )),
+    }
+}
+
+/// Writes the content of every document with unsaved changes to a single
+///   file, each under a header naming the document.
+///
+/// Meant for use after a crash, so documents that can't be borrowed are
+///   skipped rather than panicking again. Nothing is written if there are no
+///   unsaved changes. Returns the number of documents written.
+///
+/// # Arguments
+///
+/// * `documents` - The open documents.
+/// * `path` - The path of the file to write.
+pub fn write_emergency_dump(documents: &[DocumentHandle], path: &Path) -> io::Result<usize> {
+    let dirty: Vec<_> = documents
+        .iter()
+        .filter_map(|document| document.try_borrow().ok())
+        .filter(|document| document.is_dirty())
+        .collect();
+    if dirty.is_empty() {
+        return Ok(0);
+    }
+
+    persistence::write_atomic(path, |w| {
+        for document in &dirty {
+            let name = document.path().map_or_else(
+                || "[No Name]".to_string(),
+                |path| path.display().to_string()
+            );
+            writeln!(w, "==== {} ====", name)?;
+            w.write_all(document.get_content().as_bytes())?;
+            writeln!(w)?;
+        }
+        Ok(())
+    })?;
+    Ok(dirty.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::TextDocument;
+    use crate::document::swap::SwapFile;
+    use crate::scratch::ScratchPath;
+    use ropey::Rope;
+
+    /// Diffs list only the lines that were removed or added.
+    #[test]
+    fn diffs_changed_lines() {
+        assert_eq!(diff_lines("a\nb\nc\n", "a\nB\nc\nd\n"), vec!["-b", "+B", "+d"]);
+        assert!(diff_lines("same", "same").is_empty());
+    }
+
+    /// Unsaved changes in a swap file can be recovered, or discarded.
+    #[test]
+    fn resolves_recoverable_swap() {
+        let dir = ScratchPath::new_dir("resolve-swap");
+        let path = dir.join("file.txt");
+        fs::write(&path, "saved\n").unwrap();
+        SwapFile::create(&path, &Rope::from_str("unsaved\n")).unwrap();
+
+        let mut output = Vec::new();
+        let mut input = io::Cursor::new("d\nr\n");
+        let resolution = resolve_swap(&path, &mut input, &mut output).unwrap();
+        assert_eq!(resolution, Resolution::Recover("unsaved\n".to_string()));
+        assert!(String::from_utf8(output).unwrap().contains("-saved\n+unsaved\n"));
+
+        let mut input = io::Cursor::new("x\n");
+        let resolution = resolve_swap(&path, &mut input, &mut Vec::new()).unwrap();
+        assert_eq!(resolution, Resolution::Open);
+        assert!(matches!(swap::inspect(&path).unwrap(), SwapStatus::Absent));
+    }
+
+    /// The changes in a swap file whose owner can't be checked can still be
+    ///   recovered, taking the swap file over.
+    #[test]
+    fn recovers_locked_swap() {
+        let dir = ScratchPath::new_dir("resolve-locked-swap");
+        let path = dir.join("file.txt");
+        fs::write(&path, "saved\n").unwrap();
+        let swap_path = swap::swap_paths(&path).remove(0);
+        SwapFile::create(&path, &Rope::from_str("unsaved\n")).unwrap();
+        let text = fs::read_to_string(&swap_path).unwrap();
+        let host_line = text.lines().find(|line| line.starts_with("host: ")).unwrap();
+        fs::write(&swap_path, text.replace(host_line, "host: elsewhere")).unwrap();
+
+        let mut output = Vec::new();
+        let mut input = io::Cursor::new("d\nr\n");
+        let resolution = resolve_swap(&path, &mut input, &mut output).unwrap();
+        assert_eq!(resolution, Resolution::Recover("unsaved\n".to_string()));
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("being edited by another instance"));
+        assert!(output.contains("-saved\n+unsaved\n"));
+        assert!(!swap_path.exists());
+    }
+
+    /// Dumps left by other instances are found and described.
+    #[test]
+    fn finds_emergency_dumps() {
+        let dir = ScratchPath::new_dir("find-dumps");
+        fs::write(dir.join(format!("stated-recovery-{}.txt", std::process::id())), "x").unwrap();
+        fs::write(dir.join("notes.txt"), "x").unwrap();
+        assert_eq!(describe_emergency_dumps(&dir), None);
+
+        let first = dir.join("stated-recovery-1.txt");
+        fs::write(&first, "x").unwrap();
+        assert_eq!(
+            describe_emergency_dumps(&dir),
+            Some(format!("Unsaved changes from an earlier session are in {}", first.display()))
+        );
+
+        let second = dir.join("stated-recovery-2.txt");
+        fs::write(&second, "x").unwrap();
+        assert_eq!(find_emergency_dumps(&dir), vec![first, second]);
+        assert!(describe_emergency_dumps(&dir).unwrap().starts_with("Unsaved changes from 2 earlier sessions"));
+    }
+
+    /// Only documents with unsaved changes are dumped.
+    #[test]
+    fn dumps_unsaved_documents() {
+        let path = ScratchPath::new("emergency-dump.txt");
+        let clean = TextDocument::new("clean").into_handle();
+        let dirty = TextDocument::new("dirty").into_handle();
+        dirty.borrow_mut().insert(5, "!");
+
+        assert_eq!(write_emergency_dump(std::slice::from_ref(&clean), &path).unwrap(), 0);
+        assert!(!path.exists());
+
+        assert_eq!(write_emergency_dump(&[clean, dirty], &path).unwrap(), 1);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "==== [No Name] ====\ndirty!\n");
+    }
+}
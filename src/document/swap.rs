@@ -0,0 +1,471 @@
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use ropey::Rope;
+
+use super::persistence;
+
+/// The first line of every swap file, identifying its format.
+const MAGIC: &str = "stated swap file 1";
+
+/// A swap file claimed by this process, holding a copy of a document's
+///   unsaved changes so that they survive a crash or a dropped connection.
+///
+/// The swap file also acts as a lock: while it exists, other instances are
+///   warned before they edit the same file.
+pub struct SwapFile {
+    path: PathBuf,
+}
+
+impl SwapFile {
+    /// Creates the swap file for a document.
+    ///
+    /// The swap file is kept next to the file being edited, or in the state
+    ///   directory if that directory can't be written to. A swap file left
+    ///   behind by an instance that is no longer running is replaced, but one
+    ///   owned by a running instance is left alone, and the swap file isn't
+    ///   created.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - The path of the file being edited.
+    /// * `content` - The current content of the document.
+    pub fn create(file_path: &Path, content: &Rope) -> io::Result<SwapFile> {
+        let mut last_error = io::Error::other("no location for swap file");
+        for path in swap_paths(file_path) {
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            let swap = SwapFile { path };
+            match swap.claim(file_path, content) {
+                Ok(()) => return Ok(swap),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => return Err(e),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Gets the path of the swap file.
+    #[cfg(test)]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Replaces the content held in the swap file.
+    ///
+    /// The swap file is checked first, so that a swap file another instance
+    ///   has taken over is never overwritten. A swap file that has been
+    ///   deleted is claimed again.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - The path of the file being edited.
+    /// * `content` - The current content of the document.
+    pub fn write(&self, file_path: &Path, content: &Rope) -> io::Result<()> {
+        match SwapInfo::read(&self.path) {
+            Ok(info) if info.is_owned() => {}
+            Ok(info) => return Err(taken_error(&info)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return self.claim(file_path, content),
+            Err(e) => return Err(e),
+        }
+        persistence::write_atomic(&self.path, |w| write_swap(w, file_path, content))
+    }
+
+    /// Creates the swap file, failing if it already exists, so that two
+    ///   instances can't both claim it. A swap file whose owner is no longer
+    ///   running is removed and claimed in its place.
+    ///
+    /// # Arguments
+    ///
+    /// * `file_path` - The path of the file being edited.
+    /// * `content` - The current content of the document.
+    fn claim(&self, file_path: &Path, content: &Rope) -> io::Result<()> {
+        let file = match create_new(&self.path) {
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+                let info = SwapInfo::read(&self.path)?;
+                if info.is_locked() {
+                    return Err(taken_error(&info));
+                }
+                fs::remove_file(&self.path)?;
+                create_new(&self.path)?
+            }
+            result => result?,
+        };
+        let mut writer = io::BufWriter::new(file);
+        write_swap(&mut writer, file_path, content)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()
+    }
+
+    /// Deletes the swap file, releasing the lock on the file being edited. A
+    ///   swap file another instance has taken over is left for it.
+    pub fn remove(self) -> io::Result<()> {
+        if SwapInfo::read(&self.path).is_ok_and(|info| !info.is_owned()) {
+            return Ok(());
+        }
+        match fs::remove_file(&self.path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Creates a file, failing if it already exists.
+fn create_new(path: &Path) -> io::Result<fs::File> {
+    fs::OpenOptions::new().write(true).create_new(true).open(path)
+}
+
+/// Writes the header and content of a swap file.
+///
+/// # Arguments
+///
+/// * `w` - Where to write the swap file.
+/// * `file_path` - The path of the file being edited.
+/// * `content` - The current content of the document.
+fn write_swap(w: &mut dyn Write, file_path: &Path, content: &Rope) -> io::Result<()> {
+    writeln!(w, "{}", MAGIC)?;
+    writeln!(w, "pid: {}", std::process::id())?;
+    writeln!(w, "host: {}", host_name())?;
+    writeln!(w, "path: {}", file_path.display())?;
+    writeln!(w)?;
+    content.write_to(w)
+}
+
+/// Gets the error for a swap file owned by another instance.
+fn taken_error(info: &SwapInfo) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("in use by another instance (pid {} on {})", info.pid, info.host),
+    )
+}
+
+/// The contents of a swap file found on disk.
+#[derive(Debug)]
+pub struct SwapInfo {
+    /// The path of the swap file.
+    pub swap_path: PathBuf,
+
+    /// The process that wrote the swap file.
+    pub pid: u32,
+
+    /// The host the writing process ran on.
+    pub host: String,
+
+    /// When the swap file was last written.
+    pub modified: SystemTime,
+
+    /// Whether the file being edited was changed after the swap file was
+    ///   last written.
+    pub file_is_newer: bool,
+
+    /// The content of the document when the swap file was last written.
+    pub content: String,
+}
+
+impl SwapInfo {
+    /// Reads a swap file.
+    ///
+    /// # Arguments
+    ///
+    /// * `swap_path` - The path of the swap file.
+    pub fn read(swap_path: &Path) -> io::Result<SwapInfo> {
+        let text = fs::read_to_string(swap_path)?;
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "not a swap file");
+
+        let (header, content) = text.split_once("\n\n").ok_or_else(invalid)?;
+        let mut lines = header.lines();
+        if lines.next() != Some(MAGIC) {
+            return Err(invalid());
+        }
+        let mut pid = None;
+        let mut host = String::new();
+        for line in lines {
+            match line.split_once(": ") {
+                Some(("pid", value)) => pid = value.parse().ok(),
+                Some(("host", value)) => host = value.to_string(),
+                _ => {}
+            }
+        }
+
+        Ok(SwapInfo {
+            swap_path: swap_path.to_path_buf(),
+            pid: pid.ok_or_else(invalid)?,
+            host,
+            modified: fs::metadata(swap_path)?.modified()?,
+            file_is_newer: false,
+            content: content.to_string(),
+        })
+    }
+
+    /// Whether this process owns the swap file.
+    pub fn is_owned(&self) -> bool {
+        self.pid == std::process::id() && self.host == host_name()
+    }
+
+    /// Whether another instance that is still running owns the swap file.
+    ///
+    /// Processes on other hosts can't be checked, so are assumed to still be
+    ///   running.
+    pub fn is_locked(&self) -> bool {
+        if self.host != host_name() {
+            return true;
+        }
+        self.pid != std::process::id() && process_is_running(self.pid)
+    }
+}
+
+/// What was found when looking for a swap file.
+#[derive(Debug)]
+pub enum SwapStatus {
+    /// There is no swap file.
+    Absent,
+
+    /// Another running instance is editing the file.
+    Locked(SwapInfo),
+
+    /// A swap file was left behind, but holds the same content as the file.
+    Unchanged(SwapInfo),
+
+    /// A swap file was left behind holding changes that were never saved.
+    Recoverable(SwapInfo),
+}
+
+/// Looks for a swap file for a file that is about to be opened.
+///
+/// # Arguments
+///
+/// * `file_path` - The path of the file.
+pub fn inspect(file_path: &Path) -> io::Result<SwapStatus> {
+    let swap_path = match swap_paths(file_path).into_iter().find(|path| path.is_file()) {
+        Some(path) => path,
+        None => return Ok(SwapStatus::Absent),
+    };
+    let mut info = SwapInfo::read(&swap_path)?;
+    if info.is_locked() {
+        return Ok(SwapStatus::Locked(info));
+    }
+
+    let file_content = match fs::read_to_string(file_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e),
+    };
+    if file_content == info.content {
+        return Ok(SwapStatus::Unchanged(info));
+    }
+    info.file_is_newer = fs::metadata(file_path)
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| modified > info.modified);
+    Ok(SwapStatus::Recoverable(info))
+}
+
+/// Gets the places a swap file for a file may be kept, in order of preference:
+///   next to the file, then in the state directory.
+///
+/// # Arguments
+///
+/// * `file_path` - The path of the file being edited.
+pub fn swap_paths(file_path: &Path) -> Vec<PathBuf> {
+    let mut name = std::ffi::OsString::from(".");
+    name.push(file_path.file_name().unwrap_or_default());
+    name.push(".stated-swp");
+    let mut paths = vec![file_path.with_file_name(name)];
+
+    if let Some(dir) = state_dir() {
+        // Name swap files in the state directory after the whole path, so that
+        //  files with the same name in different directories don't clash
+        let absolute = absolute_path(file_path);
+        let escaped = absolute.to_string_lossy().replace(std::path::MAIN_SEPARATOR, "%");
+        paths.push(dir.join("swap").join(format!("{}.stated-swp", escaped)));
+    }
+    paths
+}
+
+/// Gets the directory for state that should persist between runs, following
+///   the XDG base directory conventions.
+fn state_dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_STATE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local").join("state"),
+    };
+    Some(base.join("stated"))
+}
+
+/// Makes a path absolute, resolving its directory if it exists.
+fn absolute_path(path: &Path) -> PathBuf {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    match (fs::canonicalize(parent), path.file_name()) {
+        (Ok(parent), Some(name)) => parent.join(name),
+        _ => std::env::current_dir().map_or_else(|_| path.to_path_buf(), |dir| dir.join(path)),
+    }
+}
+
+/// Gets the name of the host this process is running on.
+fn host_name() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "localhost".to_string())
+}
+
+/// Whether a process is still running.
+#[cfg(unix)]
+fn process_is_running(pid: u32) -> bool {
+    use std::convert::TryFrom;
+
+    let pid = match libc::pid_t::try_from(pid) {
+        Ok(pid) if pid > 0 => pid,
+        _ => return false,
+    };
+    // Signal 0 checks that the process exists without disturbing it. A
+    //  process owned by another user can't be signalled, but does exist.
+    let exists = unsafe { libc::kill(pid, 0) } == 0
+        || io::Error::last_os_error().raw_os_error() != Some(libc::ESRCH);
+    exists && !is_zombie(pid)
+}
+
+/// Whether a process has exited but lingers as a zombie until it is reaped.
+#[cfg(target_os = "linux")]
+fn is_zombie(pid: libc::pid_t) -> bool {
+    let stat = match fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Ok(stat) => stat,
+        Err(_) => return false,
+    };
+    // The state follows the command name, which is in parentheses
+    let state = stat.rsplit_once(") ").and_then(|(_, rest)| rest.chars().next());
+    matches!(state, Some('Z') | Some('X'))
+}
+
+/// Whether a process has exited but lingers as a zombie. There's no portable
+///   way to tell, and a zombie is soon reaped, so processes are taken to be
+///   running.
+#[cfg(all(unix, not(target_os = "linux")))]
+fn is_zombie(_pid: libc::pid_t) -> bool {
+    false
+}
+
+/// Whether a process is still running. Without a portable way to check, the
+///   process is assumed to be running, so that the user is always warned.
+#[cfg(not(unix))]
+fn process_is_running(_pid: u32) -> bool {
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::ScratchPath;
+
+    /// A swap file written by this process round-trips, and doesn't lock the
+    ///   file against this process.
+    #[test]
+    fn writes_and_reads_swap_file() {
+        let dir = ScratchPath::new_dir("swap-roundtrip");
+        let path = dir.join("file.txt");
+        fs::write(&path, "saved").unwrap();
+        let swap = SwapFile::create(&path, &Rope::from_str("unsaved\n\nchanges")).unwrap();
+
+        let info = SwapInfo::read(swap.path()).unwrap();
+        assert_eq!(info.pid, std::process::id());
+        assert_eq!(info.content, "unsaved\n\nchanges");
+        assert!(!info.is_locked());
+
+        match inspect(&path).unwrap() {
+            SwapStatus::Recoverable(info) => assert_eq!(info.swap_path, swap.path()),
+            status => panic!("unexpected status {:?}", status),
+        }
+        swap.remove().unwrap();
+        assert!(matches!(inspect(&path).unwrap(), SwapStatus::Absent));
+    }
+
+    /// A swap file that matches the file holds nothing to recover.
+    #[test]
+    fn swap_matching_file_is_unchanged() {
+        let dir = ScratchPath::new_dir("swap-unchanged");
+        let path = dir.join("file.txt");
+        fs::write(&path, "same").unwrap();
+        let swap = SwapFile::create(&path, &Rope::from_str("same")).unwrap();
+
+        assert!(matches!(inspect(&path).unwrap(), SwapStatus::Unchanged(_)));
+        swap.remove().unwrap();
+    }
+
+    /// Gets the pid of a process that is certainly still running: the parent
+    ///   of this one.
+    fn running_pid() -> Option<u32> {
+        fs::read_to_string("/proc/self/stat")
+            .ok()
+            .and_then(|stat| stat.split_whitespace().nth(3).and_then(|pid| pid.parse().ok()))
+    }
+
+    /// Writes a swap file as if another process had written it.
+    fn write_foreign_swap(swap_path: &Path, pid: u32) {
+        fs::write(swap_path, format!("{}\npid: {}\nhost: {}\n\nx", MAGIC, pid, host_name())).unwrap();
+    }
+
+    /// A swap file owned by another running process locks the file.
+    #[test]
+    fn swap_of_running_process_is_locked() {
+        let dir = ScratchPath::new_dir("swap-locked");
+        let path = dir.join("file.txt");
+        let swap_path = &swap_paths(&path)[0];
+        let other_pid = match running_pid() {
+            Some(pid) => pid,
+            None => return,
+        };
+        write_foreign_swap(swap_path, other_pid);
+
+        assert!(matches!(inspect(&path).unwrap(), SwapStatus::Locked(_)));
+        fs::remove_file(swap_path).unwrap();
+    }
+
+    /// A swap file owned by another running process is never claimed or
+    ///   overwritten, while one left by a process that has gone is replaced.
+    #[test]
+    fn claims_only_abandoned_swap_files() {
+        let dir = ScratchPath::new_dir("swap-claim");
+        let path = dir.join("file.txt");
+        let swap_path = &swap_paths(&path)[0];
+        let other_pid = match running_pid() {
+            Some(pid) => pid,
+            None => return,
+        };
+        write_foreign_swap(swap_path, other_pid);
+        let error = SwapFile::create(&path, &Rope::from_str("mine")).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(SwapInfo::read(swap_path).unwrap().pid, other_pid);
+
+        write_foreign_swap(swap_path, u32::MAX);
+        let swap = SwapFile::create(&path, &Rope::from_str("mine")).unwrap();
+        assert_eq!(SwapInfo::read(swap_path).unwrap().content, "mine");
+        swap.remove().unwrap();
+    }
+
+    /// A swap file taken over by another instance is left alone by writes
+    ///   and on removal.
+    #[test]
+    fn leaves_taken_over_swap_file() {
+        let dir = ScratchPath::new_dir("swap-taken");
+        let path = dir.join("file.txt");
+        let swap = SwapFile::create(&path, &Rope::from_str("mine")).unwrap();
+        let swap_path = swap.path().to_path_buf();
+        let other_pid = match running_pid() {
+            Some(pid) => pid,
+            None => return swap.remove().unwrap(),
+        };
+        write_foreign_swap(&swap_path, other_pid);
+
+        assert!(swap.write(&path, &Rope::from_str("changed")).is_err());
+        assert_eq!(SwapInfo::read(&swap_path).unwrap().content, "x");
+        swap.remove().unwrap();
+        assert!(swap_path.exists());
+        fs::remove_file(swap_path).unwrap();
+    }
+}
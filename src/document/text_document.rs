@@ -1,13 +1,813 @@
-pub struct TextDocument<'a> {
-    content: &'a str
+use std::cell::RefCell;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use ropey::Rope;
+
+use super::DocumentHandle;
+use super::filetype;
+use super::history::{Edit, History};
+use super::line_ending::{self, LineEnding};
+use super::persistence;
+use super::swap::SwapFile;
+use crate::syntax::{Highlighter, Span};
+
+/// An owned, editable text document.
+///
+/// The content is stored in a rope, so inserting, deleting and looking up
+///   lines all run in logarithmic time regardless of the document size. All
+///   positions are expressed as char indices into the document.
+pub struct TextDocument {
+    /// The text of the document.
+    content: Rope,
+
+    /// The path of the file this document was loaded from, or will be saved
+    ///   to. Documents that have never been associated with a file have no
+    ///   path.
+    path: Option<PathBuf>,
+
+    /// The edits that can be undone and redone.
+    history: History,
+
+    /// The history state the document was in when it was last saved (or
+    ///   loaded). The document is dirty whenever its state differs from this.
+    saved_state: u64,
+
+    /// The line ending used for new line breaks. Detected when the document is
+    ///   loaded, so that saving writes back the style the file came with.
+    line_ending: LineEnding,
+
+    /// Whether the document contains more than one style of line ending.
+    mixed_line_endings: bool,
+
+    /// The swap file holding a copy of unsaved changes, once swapping has
+    ///   started.
+    swap: Option<SwapFile>,
+
+    /// The history state the document was in when the swap file was last
+    ///   written.
+    swap_state: u64,
+
+    /// Counts the changes made to the text, including undos and redos, so
+    ///   that anything worked out from the text can tell when it is stale.
+    version: u64,
+
+    /// Highlights the syntax of the document, once it has been asked for and
+    ///   if there's a grammar for its type of file.
+    syntax: Option<Highlighter>,
+}
+
+impl TextDocument {
+    /// Returns a new document containing the specified text.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The initial text of the document.
+    #[cfg(test)]
+    pub fn new(content: &str) -> TextDocument {
+        TextDocument::from_rope(Rope::from_str(content), None)
+    }
+
+    /// Returns a new, empty document.
+    pub fn empty() -> TextDocument {
+        TextDocument::from_rope(Rope::new(), None)
+    }
+
+    /// Returns a new document wrapping a rope, detecting its line endings.
+    fn from_rope(content: Rope, path: Option<PathBuf>) -> TextDocument {
+        let summary = line_ending::detect(content.chars());
+        TextDocument {
+            content,
+            path,
+            history: History::new(),
+            saved_state: 0,
+            line_ending: summary.line_ending,
+            mixed_line_endings: summary.mixed,
+            swap: None,
+            swap_state: 0,
+            version: 0,
+            syntax: None,
+        }
+    }
+
+    /// Opens a document from a file on disk.
+    ///
+    /// A file that does not exist yet opens as a new, empty document with its
+    ///   path recorded, so that it is created when first saved. Directories and
+    ///   files that cannot be read (or are not valid UTF-8) return an error.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file to open.
+    pub fn open(path: &Path) -> io::Result<TextDocument> {
+        if path.is_dir() {
+            return Err(io::Error::other("is a directory"));
+        }
+        let content = match File::open(path) {
+            Ok(file) => Rope::from_reader(BufReader::new(file))?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Rope::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(TextDocument::from_rope(content, Some(path.to_path_buf())))
+    }
+
+    /// Wraps this document in a handle that can be shared between editors.
+    pub fn into_handle(self) -> DocumentHandle {
+        Rc::new(RefCell::new(self))
+    }
+
+    /// Gets the path of the file associated with this document, if any.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Gets the type of the file associated with this document, if it is
+    ///   recognized from the file's name or, failing that, from a shebang on
+    ///   its first line.
+    pub fn filetype(&self) -> Option<&'static str> {
+        self.path()
+            .and_then(filetype::detect)
+            .or_else(|| filetype::detect_shebang(&self.line(0)))
+    }
+
+    /// Gets the highlighted spans of a range of lines, one list per line,
+    ///   using the grammar for the document's type of file.
+    ///
+    /// Lines are only highlighted again after they have been edited, or when
+    ///   an edit above them changes how they start, such as opening a
+    ///   comment. Documents without a grammar have no spans.
+    ///
+    /// # Arguments
+    ///
+    /// * `lines` - The zero-based indices of the lines. Clamped to the
+    ///   document bounds.
+    pub fn highlight(&mut self, lines: Range<usize>) -> Vec<Vec<Span>> {
+        let filetype = self.filetype();
+        if self.syntax.as_ref().map(Highlighter::filetype) != filetype {
+            self.syntax = filetype.and_then(Highlighter::new);
+        }
+        let end = lines.end.min(self.line_count());
+        let lines = lines.start.min(end)..end;
+        let content = &self.content;
+        match &mut self.syntax {
+            Some(syntax) => syntax.highlight(lines, |line_ix| line_text(content, line_ix)),
+            None => vec![Vec::new(); lines.len()],
+        }
+    }
+
+    /// Gets the line ending used for new line breaks in this document.
+    pub fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// Gets whether the document contains more than one style of line ending.
+    pub fn has_mixed_line_endings(&self) -> bool {
+        self.mixed_line_endings
+    }
+
+    /// Converts every line break in the document to a line ending, which is
+    ///   then used for new line breaks too.
+    ///
+    /// # Arguments
+    ///
+    /// * `line_ending` - The line ending to convert to.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        let content = self.get_content();
+        let converted = line_ending::normalize(&content, line_ending);
+        if converted != content {
+            self.replace(0..self.len_chars(), &converted);
+        }
+        self.line_ending = line_ending;
+        self.mixed_line_endings = false;
+    }
+
+    /// Gets whether the document has unsaved changes.
+    pub fn is_dirty(&self) -> bool {
+        self.history.state_id() != self.saved_state
+    }
+
+    /// Saves the document to the file it is associated with.
+    ///
+    /// Returns an error if the document has no path.
+    pub fn save(&mut self) -> io::Result<()> {
+        let path = match &self.path {
+            Some(path) => path.clone(),
+            None => return Err(io::Error::other("no file name")),
+        };
+        self.write_to(&path)
+    }
+
+    /// Saves the document to a new file, and associates the document with it.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file to save to.
+    pub fn save_as(&mut self, path: &Path) -> io::Result<()> {
+        self.write_to(path)?;
+        self.path = Some(path.to_path_buf());
+
+        // The swap file belongs next to the new file
+        if self.swap.is_some() {
+            self.stop_swap()?;
+            self.start_swap()?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the whole content of the document with text recovered from a
+    ///   swap file. The recovery is recorded as an edit, so it can be undone
+    ///   and the document is left with unsaved changes.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The recovered text.
+    pub fn recover(&mut self, content: &str) {
+        self.replace(0..self.len_chars(), content);
+        let summary = line_ending::detect(self.content.chars());
+        self.line_ending = summary.line_ending;
+        self.mixed_line_endings = summary.mixed;
+    }
+
+    /// Creates a swap file for the document, so that unsaved changes can be
+    ///   recovered after a crash. Does nothing for documents without a path,
+    ///   or that already have a swap file.
+    pub fn start_swap(&mut self) -> io::Result<()> {
+        if let (Some(path), None) = (&self.path, &self.swap) {
+            self.swap = Some(SwapFile::create(path, &self.content)?);
+            self.swap_state = self.history.state_id();
+        }
+        Ok(())
+    }
+
+    /// Writes the document to its swap file, if it has changed since the swap
+    ///   file was last written.
+    ///
+    /// Returns whether the swap file was written.
+    pub fn write_swap(&mut self) -> io::Result<bool> {
+        let (path, swap) = match (&self.path, &self.swap) {
+            (Some(path), Some(swap)) => (path, swap),
+            _ => return Ok(false),
+        };
+        let state = self.history.state_id();
+        if state == self.swap_state {
+            return Ok(false);
+        }
+        swap.write(path, &self.content)?;
+        self.swap_state = state;
+        Ok(true)
+    }
+
+    /// Deletes the swap file, if any. Called when the document is closed
+    ///   normally.
+    pub fn stop_swap(&mut self) -> io::Result<()> {
+        match self.swap.take() {
+            Some(swap) => swap.remove(),
+            None => Ok(()),
+        }
+    }
+
+    /// Gets the path of the document's swap file, if it has one.
+    #[cfg(test)]
+    pub fn swap_path(&self) -> Option<&Path> {
+        self.swap.as_ref().map(SwapFile::path)
+    }
+
+    /// Atomically writes the document to a file and marks it clean.
+    fn write_to(&mut self, path: &Path) -> io::Result<()> {
+        if path.is_dir() {
+            return Err(io::Error::other("is a directory"));
+        }
+        let content = &self.content;
+        persistence::write_atomic(path, |w| content.write_to(w))?;
+        self.saved_state = self.history.state_id();
+        Ok(())
+    }
+
+    /// Gets the full text of the document.
+    pub fn get_content(&self) -> String {
+        self.content.to_string()
+    }
+
+    /// Gets a number that changes whenever the text of the document changes.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Gets the number of chars in the document.
+    pub fn len_chars(&self) -> usize {
+        self.content.len_chars()
+    }
+
+    /// Gets the number of lines in the document.
+    ///
+    /// An empty document has a single, empty line.
+    pub fn line_count(&self) -> usize {
+        self.content.len_lines()
+    }
+
+    /// Gets the text of a line, without its line break.
+    ///
+    /// Returns an empty string if the line does not exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `line_ix` - The zero-based index of the line.
+    pub fn line(&self, line_ix: usize) -> String {
+        if line_ix >= self.line_count() {
+            return String::new();
+        }
+        line_text(&self.content, line_ix)
+    }
+
+    /// Gets the number of chars in a line, not counting its line break.
+    ///
+    /// # Arguments
+    ///
+    /// * `line_ix` - The zero-based index of the line.
+    pub fn line_len(&self, line_ix: usize) -> usize {
+        if line_ix >= self.line_count() {
+            return 0;
+        }
+        let line = self.content.line(line_ix);
+        let mut len = line.len_chars();
+        // Strip the line break (LF, CRLF or CR) from the end of the line
+        if len > 0 && line.char(len - 1) == '\n' {
+            len -= 1;
+        }
+        if len > 0 && line.char(len - 1) == '\r' {
+            len -= 1;
+        }
+        len
+    }
+
+    /// Gets the char index at which a line starts.
+    ///
+    /// Indices beyond the last line resolve to the end of the document.
+    ///
+    /// # Arguments
+    ///
+    /// * `line_ix` - The zero-based index of the line.
+    pub fn line_to_char(&self, line_ix: usize) -> usize {
+        if line_ix >= self.line_count() {
+            return self.len_chars();
+        }
+        self.content.line_to_char(line_ix)
+    }
+
+    /// Gets the index of the line containing a char.
+    ///
+    /// # Arguments
+    ///
+    /// * `char_ix` - The char index to look up.
+    pub fn char_to_line(&self, char_ix: usize) -> usize {
+        self.content.char_to_line(char_ix.min(self.len_chars()))
+    }
+
+    /// Inserts text into the document.
+    ///
+    /// # Arguments
+    ///
+    /// * `char_ix` - The char index at which to insert. Clamped to the end of
+    ///   the document.
+    /// * `text` - The text to insert.
+    pub fn insert(&mut self, char_ix: usize, text: &str) {
+        let char_ix = char_ix.min(self.len_chars());
+        self.replace(char_ix..char_ix, text);
+    }
+
+    /// Deletes a range of text from the document.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The char range to delete. Clamped to the document bounds.
+    pub fn delete(&mut self, range: Range<usize>) {
+        self.replace(range, "");
+    }
+
+    /// Replaces a range of text in the document.
+    ///
+    /// All edits funnel through here so that they are recorded in the undo
+    ///   history.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The char range to replace. Clamped to the document bounds.
+    /// * `text` - The replacement text.
+    pub fn replace(&mut self, range: Range<usize>, text: &str) {
+        let range = self.clamp_range(range);
+        if range.is_empty() && text.is_empty() {
+            return;
+        }
+        let edit = Edit {
+            char_ix: range.start,
+            removed: self.content.slice(range.clone()).to_string(),
+            inserted: text.to_string(),
+            timestamp: SystemTime::now(),
+        };
+        apply(&mut self.content, &mut self.syntax, range.start, range.len(), text);
+        self.history.record(edit);
+        self.version += 1;
+    }
+
+    /// Starts grouping edits so that they are undone in a single step.
+    ///
+    /// # Arguments
+    ///
+    /// * `cursor` - The char index of the cursor, restored when the group is
+    ///   undone.
+    pub fn begin_transaction(&mut self, cursor: usize) {
+        self.history.begin(cursor);
+    }
+
+    /// Stops grouping edits.
+    ///
+    /// # Arguments
+    ///
+    /// * `cursor` - The char index of the cursor, restored when the group is
+    ///   redone.
+    pub fn commit_transaction(&mut self, cursor: usize) {
+        self.history.commit(cursor);
+    }
+
+    /// Reverts the most recent transaction.
+    ///
+    /// Returns the char index the cursor was at before the transaction, or
+    ///   `None` if there is nothing to undo.
+    pub fn undo(&mut self) -> Option<usize> {
+        let transaction = self.history.undo()?;
+        for edit in transaction.edits.iter().rev() {
+            apply(&mut self.content, &mut self.syntax, edit.char_ix, edit.inserted.chars().count(), &edit.removed);
+        }
+        self.version += 1;
+        Some(transaction.cursor_before)
+    }
+
+    /// Reapplies the most recently undone transaction.
+    ///
+    /// Returns the char index the cursor was at after the transaction, or
+    ///   `None` if there is nothing to redo.
+    pub fn redo(&mut self) -> Option<usize> {
+        let transaction = self.history.redo()?;
+        for edit in transaction.edits.iter() {
+            apply(&mut self.content, &mut self.syntax, edit.char_ix, edit.removed.chars().count(), &edit.inserted);
+        }
+        self.version += 1;
+        Some(transaction.cursor_after)
+    }
+
+    /// Undoes every transaction made at or after a point in time, for example
+    ///   to return to the document as it was 30 seconds ago.
+    ///
+    /// Returns the char index of the cursor before the earliest transaction
+    ///   undone, or `None` if nothing was undone.
+    ///
+    /// # Arguments
+    ///
+    /// * `time` - The point in time to return to.
+    pub fn undo_to(&mut self, time: SystemTime) -> Option<usize> {
+        let mut cursor = None;
+        while self.history.last_timestamp().is_some_and(|timestamp| timestamp >= time) {
+            cursor = self.undo();
+        }
+        cursor
+    }
+
+    /// Clamps a char range so that it lies within the document.
+    fn clamp_range(&self, range: Range<usize>) -> Range<usize> {
+        let len = self.len_chars();
+        let end = range.end.min(len);
+        let start = range.start.min(end);
+        start..end
+    }
+}
+
+/// Replaces a range of chars in a rope, without recording any history,
+///   marking the lines it touches to be highlighted again.
+fn apply(content: &mut Rope, syntax: &mut Option<Highlighter>, char_ix: usize, remove_len: usize, text: &str) {
+    let line_count = content.len_lines();
+    // Starting from the char before catches a line break joining a CR at the
+    //  end of the line above
+    let first_line = content.char_to_line(char_ix.saturating_sub(1));
+    content.remove(char_ix..char_ix + remove_len);
+    content.insert(char_ix, text);
+    if let Some(syntax) = syntax {
+        let last_line = content.char_to_line(char_ix + text.chars().count());
+        let replaced_end = last_line + 1 + line_count - content.len_lines();
+        syntax.edit(first_line..replaced_end, last_line + 1 - first_line);
+    }
+}
+
+/// Gets the text of a line in a rope, without its line break.
+fn line_text(content: &Rope, line_ix: usize) -> String {
+    let line = content.line(line_ix);
+    let mut len = line.len_chars();
+    if len > 0 && line.char(len - 1) == '\n' {
+        len -= 1;
+    }
+    if len > 0 && line.char(len - 1) == '\r' {
+        len -= 1;
+    }
+    line.slice(..len).to_string()
 }
 
-impl<'a> TextDocument<'a> {
-    pub fn new(content: &'a str) -> TextDocument<'a> {
-        TextDocument { content: content }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scratch::ScratchPath;
+
+    /// An empty document has one empty line.
+    #[test]
+    fn empty_document_has_one_line() {
+        let document = TextDocument::empty();
+
+        assert_eq!(document.line_count(), 1);
+        assert_eq!(document.line(0), "");
+    }
+
+    /// Lines are returned without their line breaks.
+    #[test]
+    fn line_excludes_line_break() {
+        let document = TextDocument::new("Hello\r\nWorld!\n");
+
+        assert_eq!(document.line(0), "Hello");
+        assert_eq!(document.line_len(0), 5);
+        assert_eq!(document.line(1), "World!");
+        assert_eq!(document.line(2), "");
+    }
+
+    /// Inserting text places it at the requested char index.
+    #[test]
+    fn insert_text() {
+        let mut document = TextDocument::new("Hllo");
+        document.insert(1, "e");
+
+        assert_eq!(document.get_content(), "Hello");
+    }
+
+    /// Deleting a range that spans a line break joins the lines.
+    #[test]
+    fn delete_range_joins_lines() {
+        let mut document = TextDocument::new("Hello\nWorld");
+        document.delete(5..6);
+
+        assert_eq!(document.line_count(), 1);
+        assert_eq!(document.get_content(), "HelloWorld");
+    }
+
+    /// Replacing a range swaps in the new text.
+    #[test]
+    fn replace_range() {
+        let mut document = TextDocument::new("Hello World");
+        document.replace(6..11, "Rope");
+
+        assert_eq!(document.get_content(), "Hello Rope");
+    }
+
+    /// Out of range edits are clamped rather than panicking.
+    #[test]
+    fn edits_clamped_to_document() {
+        let mut document = TextDocument::new("abc");
+        document.insert(10, "d");
+        document.delete(2..100);
+
+        assert_eq!(document.get_content(), "ab");
+    }
+
+    /// Opening a file that does not exist yields an empty document that
+    ///  remembers its path.
+    #[test]
+    fn open_missing_file_as_new_document() {
+        let path = ScratchPath::new("open-missing-file.txt");
+        let document = TextDocument::open(&path).unwrap();
+
+        assert_eq!(document.get_content(), "");
+        assert_eq!(document.path(), Some(&*path));
+    }
+
+    /// Opening a directory is an error rather than a panic.
+    #[test]
+    fn open_directory_fails() {
+        assert!(TextDocument::open(&std::env::temp_dir()).is_err());
+    }
+
+    /// Editing marks a document dirty and saving marks it clean again.
+    #[test]
+    fn save_as_writes_file_and_clears_dirty_flag() {
+        let path = ScratchPath::new("save-as.txt");
+        let mut document = TextDocument::new("Hello");
+        document.insert(5, "!");
+        assert!(document.is_dirty());
+
+        document.save_as(&path).unwrap();
+
+        assert!(!document.is_dirty());
+        assert_eq!(document.path(), Some(&*path));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "Hello!");
+    }
+
+    /// Saving keeps the permissions of the file being overwritten.
+    #[cfg(unix)]
+    #[test]
+    fn save_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = ScratchPath::new("save-permissions.sh");
+        std::fs::write(&path, "echo").unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o750)).unwrap();
+
+        let mut document = TextDocument::open(&path).unwrap();
+        document.insert(4, " hi");
+        document.save().unwrap();
+
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o750);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "echo hi");
     }
 
-    pub fn get_content(&self) -> &'a str {
-        self.content
+    /// A document without a path can't be saved.
+    #[test]
+    fn save_without_path_fails() {
+        assert!(TextDocument::new("text").save().is_err());
     }
-}
\ No newline at end of file
+
+    /// The dominant line ending is detected, along with whether styles are
+    ///  mixed.
+    #[test]
+    fn detects_line_endings() {
+        let document = TextDocument::new("a\r\nb\r\nc");
+        assert_eq!(document.line_ending(), LineEnding::CrLf);
+        assert!(!document.has_mixed_line_endings());
+
+        let document = TextDocument::new("a\rb\rc\nd");
+        assert_eq!(document.line_ending(), LineEnding::Cr);
+        assert!(document.has_mixed_line_endings());
+    }
+
+    /// Every line ending style splits lines.
+    #[test]
+    fn splits_lines_for_every_line_ending() {
+        let document = TextDocument::new("a\nb\r\nc\rd");
+
+        assert_eq!(document.line_count(), 4);
+        assert_eq!(document.line(1), "b");
+        assert_eq!(document.line(2), "c");
+    }
+
+    /// Converting line endings rewrites every line break.
+    #[test]
+    fn converts_line_endings() {
+        let mut document = TextDocument::new("a\nb\r\nc");
+        document.set_line_ending(LineEnding::CrLf);
+
+        assert_eq!(document.get_content(), "a\r\nb\r\nc");
+        assert!(!document.has_mixed_line_endings());
+        assert!(document.is_dirty());
+    }
+
+    /// Saving writes back the line endings the file was loaded with.
+    #[test]
+    fn save_preserves_line_endings() {
+        let path = ScratchPath::new("save-line-endings.txt");
+        std::fs::write(&path, "one\r\ntwo\r\n").unwrap();
+
+        let mut document = TextDocument::open(&path).unwrap();
+        let end = document.len_chars();
+        document.insert(end, &format!("three{}", document.line_ending().as_str()));
+        document.save().unwrap();
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "one\r\ntwo\r\nthree\r\n");
+    }
+
+    /// Undo and redo revert and reapply edits, returning cursor positions.
+    #[test]
+    fn undo_and_redo_edits() {
+        let mut document = TextDocument::new("Hello");
+        document.insert(5, " World");
+        document.delete(0..1);
+
+        assert_eq!(document.undo(), Some(0));
+        assert_eq!(document.get_content(), "Hello World");
+        assert_eq!(document.undo(), Some(5));
+        assert_eq!(document.get_content(), "Hello");
+        assert!(!document.is_dirty());
+        assert_eq!(document.undo(), None);
+
+        assert_eq!(document.redo(), Some(11));
+        assert_eq!(document.get_content(), "Hello World");
+        assert!(document.is_dirty());
+    }
+
+    /// Edits made inside a transaction are undone together.
+    #[test]
+    fn undo_transaction_as_one_step() {
+        let mut document = TextDocument::new("");
+        document.begin_transaction(0);
+        document.insert(0, "a");
+        document.insert(1, "b");
+        document.insert(2, "c");
+        document.commit_transaction(3);
+
+        assert_eq!(document.undo(), Some(0));
+        assert_eq!(document.get_content(), "");
+        assert_eq!(document.redo(), Some(3));
+        assert_eq!(document.get_content(), "abc");
+    }
+
+    /// Making a new edit discards anything that could have been redone.
+    #[test]
+    fn edit_clears_redo() {
+        let mut document = TextDocument::new("a");
+        document.insert(1, "b");
+        document.undo();
+        document.insert(1, "c");
+
+        assert_eq!(document.redo(), None);
+        assert_eq!(document.get_content(), "ac");
+    }
+
+    /// Undoing to a point in time reverts only the edits made since then.
+    #[test]
+    fn undo_to_time() {
+        let mut document = TextDocument::new("");
+        document.insert(0, "old");
+        let cutoff = SystemTime::now();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        document.insert(3, " new");
+        document.insert(7, " newer");
+
+        assert_eq!(document.undo_to(cutoff), Some(3));
+        assert_eq!(document.get_content(), "old");
+    }
+
+    /// Line and char indices map onto each other.
+    #[test]
+    fn maps_lines_and_chars() {
+        let document = TextDocument::new("ab\ncd\nef");
+
+        assert_eq!(document.line_to_char(1), 3);
+        assert_eq!(document.char_to_line(4), 1);
+        assert_eq!(document.line_to_char(10), 8);
+    }
+
+    /// Highlighting follows edits, undos and redos, using the grammar picked
+    ///   by a shebang when the document has no path.
+    #[test]
+    fn highlights_through_edits() {
+        let mut document = TextDocument::new("#!/bin/sh\necho $HOME\nexit 1\n");
+        assert_eq!(document.filetype(), Some("shell"));
+        let groups = |document: &mut TextDocument| -> Vec<Vec<&'static str>> {
+            document.highlight(1..10).iter().map(|spans| spans.iter().map(|span| span.group).collect()).collect()
+        };
+        assert_eq!(groups(&mut document), vec![vec!["syntax.variable"], vec!["syntax.keyword", "syntax.number"], vec![]]);
+
+        // Opening a string carries it onto the lines below
+        document.insert(document.line_to_char(1), "x='\n");
+        assert_eq!(groups(&mut document), vec![vec!["syntax.string"], vec!["syntax.string"], vec!["syntax.string"], vec![]]);
+
+        document.undo();
+        assert_eq!(groups(&mut document), vec![vec!["syntax.variable"], vec!["syntax.keyword", "syntax.number"], vec![]]);
+        document.redo();
+        assert_eq!(groups(&mut document)[2], vec!["syntax.string"]);
+
+        // Without a grammar there is nothing to highlight
+        document.replace(0..document.line_len(0), "plain");
+        assert_eq!(document.highlight(0..2), vec![Vec::new(), Vec::new()]);
+    }
+
+    /// Swap files follow the document's unsaved changes and are removed when
+    ///   swapping stops.
+    #[test]
+    fn swap_file_tracks_changes() {
+        let dir = ScratchPath::new_dir("document-swap");
+        let path = dir.join("file.txt");
+        std::fs::write(&path, "Hello").unwrap();
+        let mut document = TextDocument::open(&path).unwrap();
+        document.start_swap().unwrap();
+        let swap_path = document.swap_path().unwrap().to_path_buf();
+
+        assert!(!document.write_swap().unwrap());
+        document.insert(5, "!");
+        assert!(document.write_swap().unwrap());
+        assert!(std::fs::read_to_string(&swap_path).unwrap().ends_with("\n\nHello!"));
+
+        document.stop_swap().unwrap();
+        assert!(!swap_path.exists());
+    }
+
+    /// Recovered text replaces the content as an undoable edit.
+    #[test]
+    fn recover_replaces_content() {
+        let mut document = TextDocument::new("old\n");
+        document.recover("new\r\n");
+
+        assert_eq!(document.get_content(), "new\r\n");
+        assert!(document.is_dirty());
+        assert_eq!(document.line_ending(), LineEnding::CrLf);
+        document.undo();
+        assert_eq!(document.get_content(), "old\n");
+    }
+}
@@ -1,40 +1,108 @@
 mod dimensions;
+pub mod graphemes;
+mod gutter;
 mod location;
+mod position;
+mod wrap;
 
 pub use dimensions::Dimensions;
+pub use gutter::LineNumbers;
 pub use location::Location;
+pub use position::Position;
+pub use wrap::{ScreenLine, Wrap};
 
-use crate::document::TextDocument;
+use std::ops::Range;
+use std::time::{Duration, SystemTime};
+
+use crate::document::DocumentHandle;
 
 /// An editor hosts a single open document. The program itself may have many
 ///   open editors. Each editor is given a different portion of the screen into
 ///   which it can render its content.
-pub struct Editor<'a> {
-    /// The dimensions allocated to this editor to use to display its contents.
+pub struct Editor {
+    /// The dimensions allocated to this editor to use to display its contents,
+    ///   including the gutter.
     pub dimensions: Dimensions,
     
-    /// The location of the cursor in this editor.
+    /// The location of the cursor on screen, relative to this editor. Columns
+    ///   count from the left of the gutter.
+    ///
+    /// Derived from `position` and the scrolling; it is updated whenever
+    ///   either of those change.
     pub cursor_location: Location,
 
-    /// The amount of scrolling (columns and rows) applied to the editor.
-    pub scroll_amount: Location,
+    /// The position of the cursor in the document.
+    pub position: Position,
+
+    /// The display column the cursor tries to return to when moving between
+    ///   lines.
+    ///
+    /// Moving through a short line clamps the cursor's column, but the
+    ///   cursor returns to this column when it reaches a longer line again.
+    pub desired_column: usize,
+
+    /// Whether the cursor may sit one past the last char of a line, as it
+    ///   can while inserting text.
+    pub cursor_past_end: bool,
+
+    /// The number of display columns the editor is scrolled sideways by,
+    ///   while lines aren't wrapped.
+    pub scroll_column: u16,
+
+    /// The zero-based index of the first document line shown.
+    pub top_line: usize,
+
+    /// The first segment shown of the top line, when it is wrapped.
+    top_segment: usize,
+
+    /// The document currently being displayed in this editor.
+    pub content: Option<DocumentHandle>,
+
+    /// The version of the document the cursor was last kept within, so that
+    ///   changes made through other editors can be noticed.
+    seen_version: u64,
+
+    /// The minimum number of lines to keep visible above and below the cursor
+    ///   when scrolling vertically.
+    pub scroll_off: u16,
+
+    /// The number of columns between tab stops.
+    pub tab_width: usize,
+
+    /// How line numbers are shown in the gutter left of the text.
+    line_numbers: LineNumbers,
+
+    /// How lines longer than the editor is wide are shown.
+    wrap: Wrap,
 
-    /// The content of the document currently being displayed in this editor.
-    pub content: Option<&'a str>
+    /// The text shown at the start of each screen line that carries on a
+    ///   wrapped line.
+    wrap_indicator: String,
 }
 
-impl<'a> Editor<'a> {
+impl Editor {
     /// Returns a new Editor.
     /// 
     /// # Arguments
     /// 
     /// * `dimensions` - The dimensions of this editor.
-    pub fn new(dimensions: Dimensions) -> Editor<'a> {
+    pub fn new(dimensions: Dimensions) -> Editor {
         Editor {
-            dimensions: dimensions,
+            dimensions,
             cursor_location: Location::default(),
-            scroll_amount: Location::default(),
-            content: None
+            position: Position::default(),
+            desired_column: 0,
+            cursor_past_end: false,
+            scroll_column: 0,
+            top_line: 0,
+            top_segment: 0,
+            content: None,
+            seen_version: 0,
+            scroll_off: 0,
+            tab_width: 4,
+            line_numbers: LineNumbers::Off,
+            wrap: Wrap::Off,
+            wrap_indicator: String::new(),
         }
     }
 
@@ -43,44 +111,236 @@ impl<'a> Editor<'a> {
     /// # Arguments
     /// 
     /// * `self` - The editor into which to set content.
-    /// * `document` - A document containing the contents to load.
-    pub fn set_content(&mut self, document: &'a TextDocument) {
-        self.content = Some(document.get_content());
+    /// * `document` - A handle to the document to display.
+    pub fn set_content(&mut self, document: DocumentHandle) {
+        self.seen_version = document.borrow().version();
+        self.content = Some(document);
     }
 
-    /// Gets the content to render in this editor.
+    /// Gets how line numbers are shown in the gutter.
+    pub fn line_numbers(&self) -> LineNumbers {
+        self.line_numbers
+    }
+
+    /// Changes how line numbers are shown in the gutter, scrolling if the
+    ///   narrower or wider text leaves the cursor out of view.
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - The editor whose gutter to change.
+    /// * `line_numbers` - How line numbers are shown.
+    pub fn set_line_numbers(&mut self, line_numbers: LineNumbers) {
+        self.line_numbers = line_numbers;
+        self.scroll_to_cursor();
+    }
+
+    /// Gets the number of columns taken by the gutter. The gutter is left
+    ///   out if it wouldn't leave a column for the text.
+    pub fn gutter_width(&self) -> u16 {
+        let width = self.line_numbers.width(self.line_count());
+        if width < self.dimensions.columns {
+            width
+        } else {
+            0
+        }
+    }
+
+    /// Gets the dimensions left for the text, right of the gutter.
+    pub fn text_dimensions(&self) -> Dimensions {
+        Dimensions::new(self.dimensions.columns - self.gutter_width(), self.dimensions.rows)
+    }
+
+    /// Gets how long lines are shown.
+    pub fn wrap(&self) -> Wrap {
+        self.wrap
+    }
+
+    /// Gets the text shown at the start of each screen line that carries on
+    ///   a wrapped line.
+    pub fn wrap_indicator(&self) -> &str {
+        &self.wrap_indicator
+    }
+
+    /// Changes how long lines are shown, scrolling to keep the cursor in
+    ///   view.
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - The editor whose wrapping to change.
+    /// * `wrap` - How long lines are shown.
+    /// * `indicator` - The text shown at the start of each screen line that
+    ///   carries on a wrapped line.
+    pub fn set_wrap(&mut self, wrap: Wrap, indicator: &str) {
+        self.wrap = wrap;
+        self.wrap_indicator = indicator.to_string();
+        if wrap != Wrap::Off {
+            // Wrapped lines are never scrolled sideways
+            self.scroll_column = 0;
+        }
+        self.scroll_to_cursor();
+    }
+
+    /// Gets the line numbers to render in the gutter, one for each line of
+    ///   render content, padded to the width of the gutter. Screen lines that
+    ///   carry on a wrapped line have no number.
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - The editor for which to get the gutter.
+    pub fn get_gutter_content(&self) -> Vec<String> {
+        let width = self.gutter_width() as usize;
+        if width == 0 {
+            return Vec::new();
+        }
+        self.screen_lines()
+            .iter()
+            .map(|(screen_line, _)| match screen_line.segment_ix {
+                0 => {
+                    let label = self.line_numbers.label(screen_line.line_ix, self.position.line_ix);
+                    format!("{:>1$} ", label, width - 1)
+                }
+                _ => " ".repeat(width),
+            })
+            .collect()
+    }
+
+    /// Gets the content to render in this editor, right of the gutter.
     /// 
     /// # Arguments
     /// 
     /// * `self` - The editor for which to get render content.
-    pub fn get_render_content(&self) -> Vec<&'a str> {
-        let mut result: Vec<&str> = Vec::new();
-        if let Some(content) = self.content {
-            let lines = content.split("\r\n");
-            
-            // Iterate available lines
-            let mut ix = 0;
-            for line in lines {
-                // Only render this line if there is enough space in viewport
-                if ix < self.dimensions.rows {
-                    // Dimensions dictate how many columns are visible
-                    let cols = self.dimensions.columns as usize;
-                    // Determine what part of the line should be rendered
-                    let start = self.scroll_amount.column_ix as usize;
-                    let mut end = start + cols;
-                    if end > line.len() {
-                        end = line.len();
-                    }
-                    result.push(&line[start..end]);
-                } else {
-                    break;
-                }
-                ix += 1;
+    pub fn get_render_content(&self) -> Vec<String> {
+        let mut result: Vec<String> = Vec::new();
+        // Dimensions dictate how many columns are visible
+        let cols = self.text_dimensions().columns as usize;
+        let indicator_width = self.indicator_width();
+        for (screen_line, segment) in self.screen_lines() {
+            let line = self.line_text(screen_line.line_ix);
+            if self.wrap == Wrap::Off {
+                // Determine what part of the line should be rendered
+                let start = self.scroll_column as usize;
+                result.push(graphemes::render_line(&line, start, cols, self.tab_width));
+                continue;
+            }
+            let start = graphemes::display_column(&line, segment.start, self.tab_width);
+            let end = graphemes::display_column(&line, segment.end, self.tab_width);
+            let mut row = String::new();
+            if screen_line.segment_ix > 0 {
+                row.push_str(&graphemes::render_line(&self.wrap_indicator, 0, indicator_width, self.tab_width));
             }
+            row.push_str(&graphemes::render_line(&line, start, end - start, self.tab_width));
+            result.push(row);
         }
         result
     }
 
+    /// Gets the document lines shown in the editor, in whole or in part.
+    #[cfg(test)]
+    pub fn visible_lines(&self) -> Range<usize> {
+        let first_line = self.top_line;
+        let last_line = self.screen_lines().last().map_or(first_line, |(screen_line, _)| screen_line.line_ix + 1);
+        first_line..last_line.max(first_line)
+    }
+
+    /// Gets the rows of the editor that show the line holding the cursor.
+    pub fn cursor_rows(&self) -> Range<u16> {
+        let rows: Vec<usize> = self.screen_lines()
+            .iter()
+            .enumerate()
+            .filter(|(_, (screen_line, _))| screen_line.line_ix == self.position.line_ix)
+            .map(|(row_ix, _)| row_ix)
+            .collect();
+        match (rows.first(), rows.last()) {
+            (Some(first), Some(last)) => *first as u16..*last as u16 + 1,
+            _ => 0..0,
+        }
+    }
+
+    /// Gets the columns of the editor in which part of a line is drawn,
+    ///   counting from the left of the gutter. When the line is wrapped, only
+    ///   the screen line on which the part starts is considered.
+    ///
+    /// Returns None if that part of the line is scrolled out of view, or is
+    ///   empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - The editor displaying the line.
+    /// * `line_ix` - The document line.
+    /// * `chars` - The range of chars within the line.
+    #[cfg(test)]
+    pub fn span_columns(&self, line_ix: usize, chars: Range<usize>) -> Option<Range<u16>> {
+        let line = self.line_text(line_ix);
+        let segments = self.segments(line_ix);
+        let segment_ix = segments.iter().rposition(|segment| segment.start <= chars.start).unwrap_or(0);
+        let screen_line = ScreenLine::new(line_ix, segment_ix);
+        self.segment_columns(&line, screen_line, segments[segment_ix].clone(), chars)
+    }
+
+    /// Gets where ranges of the document are drawn in the editor, as a row
+    ///   and a span of columns for each screen line each range is shown on.
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - The editor displaying the document.
+    /// * `ranges` - Char ranges of the document, in document order.
+    pub fn char_spans(&self, ranges: &[Range<usize>]) -> Vec<(u16, Range<u16>)> {
+        let document = match &self.content {
+            Some(document) => document.borrow(),
+            None => return Vec::new(),
+        };
+        let mut spans = Vec::new();
+        for (row_ix, (screen_line, segment)) in self.screen_lines().into_iter().enumerate() {
+            let line_start = document.line_to_char(screen_line.line_ix);
+            let row_chars = line_start + segment.start..line_start + segment.end;
+            let first = ranges.partition_point(|range| range.end <= row_chars.start);
+            let line = document.line(screen_line.line_ix);
+            // A range may run over several screen lines
+            for range in ranges[first..].iter().take_while(|range| range.start < row_chars.end) {
+                let chars = range.start.saturating_sub(line_start)..range.end - line_start;
+                if let Some(columns) = self.segment_columns(&line, screen_line, segment.clone(), chars) {
+                    spans.push((row_ix as u16, columns));
+                }
+            }
+        }
+        spans
+    }
+
+    /// Gets where the document's highlighted syntax is drawn in the editor, as
+    ///   a row, a span of columns and a highlight group for each visible part
+    ///   of a span.
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - The editor displaying the document.
+    pub fn syntax_spans(&self) -> Vec<(u16, Range<u16>, &'static str)> {
+        let document = match &self.content {
+            Some(document) => document,
+            None => return Vec::new(),
+        };
+        let screen_lines = self.screen_lines();
+        let lines = match (screen_lines.first(), screen_lines.last()) {
+            (Some((first, _)), Some((last, _))) => first.line_ix..last.line_ix + 1,
+            _ => return Vec::new(),
+        };
+        let highlights = document.borrow_mut().highlight(lines.clone());
+
+        let mut spans = Vec::new();
+        for (row_ix, (screen_line, segment)) in screen_lines.into_iter().enumerate() {
+            let line_spans = &highlights[screen_line.line_ix - lines.start];
+            if line_spans.is_empty() {
+                continue;
+            }
+            let line = self.line_text(screen_line.line_ix);
+            for span in line_spans.iter().filter(|span| span.chars.end > segment.start && span.chars.start < segment.end) {
+                if let Some(columns) = self.segment_columns(&line, screen_line, segment.clone(), span.chars.clone()) {
+                    spans.push((row_ix as u16, columns, span.group));
+                }
+            }
+        }
+        spans
+    }
+
     /// Resizes the render area for an editor.
     ///
     /// # Arguments
@@ -89,11 +349,21 @@ impl<'a> Editor<'a> {
     /// * `dimensions` - The dimensions describing the updated render area.
     pub fn resize(&mut self, dimensions: Dimensions) {
         self.dimensions = dimensions;
+        self.scroll_to_cursor();
     }
 
-    pub fn scroll_to(&mut self, column_ix: u16, row_ix: u16) {
-        self.scroll_amount.column_ix = column_ix;
-        self.scroll_amount.row_ix = row_ix;
+    /// Scrolls the editor to an absolute position.
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - The editor to scroll.
+    /// * `column_ix` - The first display column to show.
+    /// * `line_ix` - The first document line to show, from its start.
+    pub fn scroll_to(&mut self, column_ix: u16, line_ix: usize) {
+        self.scroll_column = column_ix;
+        self.top_line = line_ix;
+        self.top_segment = 0;
+        self.update_cursor_location();
     }
 
     /// Moves the cursor to the left a specified number of columns.
@@ -103,39 +373,31 @@ impl<'a> Editor<'a> {
     /// * `self` - The editor in which to move the cursor.
     /// * `num_columns` - The number of columns to move the cursor left.
     pub fn move_cursor_left(&mut self, num_columns: u16) {
-        if num_columns > self.cursor_location.column_ix {
-            // Ensure cursor remains within editor bounds.
-            self.cursor_location.column_ix = 0;
-        } else {
-            self.cursor_location.column_ix -= num_columns;
+        let line = self.line_text(self.position.line_ix);
+        let mut column_ix = self.position.column_ix;
+        for _ in 0..num_columns {
+            column_ix = graphemes::prev_boundary(&line, column_ix);
         }
+        self.set_cursor_position(self.position.line_ix, column_ix);
     }
 
     /// Moves the cursor to the right a specified number of columns.
     ///
+    /// The cursor stops at the end of the line, scrolling the editor if the
+    ///   line is wider than the editor.
+    ///
     /// # Arguments
     ///
     /// * `self` - The editor in which to move the cursor.
     /// * `num_columns` - The number of columns to move the cursor right.
     pub fn move_cursor_right(&mut self, num_columns: u16) {
-        if self.cursor_location.column_ix + num_columns > self.dimensions.columns - 1 {
-            // Check if scroll position needs to be updated
-            if let Some(content) = self.content {
-                let mut lines = content.split("\r\n");
-                let cur_row = self.cursor_location.row_ix as usize;
-                if let Some(line) = &lines.nth(cur_row) {
-                    let end = (self.scroll_amount.column_ix + self.dimensions.columns) as usize;
-                    if line.len() > end {
-                        // Increment scroll position
-                        self.scroll_amount.column_ix += 1;
-                    }
-                }
-            }
-            // Ensure cursor remains within editor bounds.
-            self.cursor_location.column_ix = self.dimensions.columns - 1;
-        } else {
-            self.cursor_location.column_ix += num_columns;
+        let line = self.line_text(self.position.line_ix);
+        let mut column_ix = self.position.column_ix;
+        for _ in 0..num_columns {
+            column_ix = graphemes::next_boundary(&line, column_ix);
         }
+        let column_ix = column_ix.min(self.max_column(self.position.line_ix));
+        self.set_cursor_position(self.position.line_ix, column_ix);
     }
 
     /// Moves the cursor up a specified number of rows.
@@ -145,12 +407,7 @@ impl<'a> Editor<'a> {
     /// * `self` - The editor in which to move the cursor.
     /// * `num_rows` - The number of rows to move the cursor up.
     pub fn move_cursor_up(&mut self, num_rows: u16) {
-        if num_rows > self.cursor_location.row_ix {
-            // Ensure cursor remains within editor bounds.
-            self.cursor_location.row_ix = 0;
-        } else {
-            self.cursor_location.row_ix -= num_rows;
-        }
+        self.move_cursor_to_line(self.position.line_ix.saturating_sub(num_rows as usize));
     }
 
     /// Moves the cursor down a specified number of rows.
@@ -160,18 +417,642 @@ impl<'a> Editor<'a> {
     /// * `self` - The editor in which to move the cursor.
     /// * `num_rows` - The number of rows to move the cursor down.
     pub fn move_cursor_down(&mut self, num_rows: u16) {
-        if self.cursor_location.row_ix + num_rows > self.dimensions.rows {
-            // Ensure cursor remains within editor bounds.
-            self.cursor_location.row_ix = self.dimensions.rows - 1;
+        self.move_cursor_to_line(self.position.line_ix + num_rows as usize);
+    }
+
+    /// Moves the cursor up a number of screen lines, which are segments of
+    ///   wrapped lines, keeping to the desired column within them.
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - The editor in which to move the cursor.
+    /// * `num_rows` - The number of screen lines to move the cursor up.
+    pub fn move_cursor_up_screen_lines(&mut self, num_rows: u16) {
+        if self.wrap == Wrap::Off {
+            return self.move_cursor_up(num_rows);
+        }
+        let target = self.walk_back(self.cursor_screen_line(), num_rows as usize);
+        self.move_cursor_to_screen_line(target);
+    }
+
+    /// Moves the cursor down a number of screen lines, which are segments of
+    ///   wrapped lines, keeping to the desired column within them.
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - The editor in which to move the cursor.
+    /// * `num_rows` - The number of screen lines to move the cursor down.
+    pub fn move_cursor_down_screen_lines(&mut self, num_rows: u16) {
+        if self.wrap == Wrap::Off {
+            return self.move_cursor_down(num_rows);
+        }
+        let target = self.walk_forward(self.cursor_screen_line(), num_rows as usize);
+        self.move_cursor_to_screen_line(target);
+    }
+
+    /// Scrolls down by a page, moving the cursor by the same amount.
+    pub fn page_down(&mut self) {
+        self.scroll_lines_down(self.dimensions.rows as usize);
+    }
+
+    /// Scrolls up by a page, moving the cursor by the same amount.
+    pub fn page_up(&mut self) {
+        self.scroll_lines_up(self.dimensions.rows as usize);
+    }
+
+    /// Scrolls down by half a page, moving the cursor by the same amount.
+    pub fn half_page_down(&mut self) {
+        self.scroll_lines_down((self.dimensions.rows as usize / 2).max(1));
+    }
+
+    /// Scrolls up by half a page, moving the cursor by the same amount.
+    pub fn half_page_up(&mut self) {
+        self.scroll_lines_up((self.dimensions.rows as usize / 2).max(1));
+    }
+
+    /// Moves the cursor to the first line of the document.
+    pub fn go_to_top(&mut self) {
+        self.move_cursor_to_line(0);
+    }
+
+    /// Moves the cursor to the last line of the document.
+    pub fn go_to_bottom(&mut self) {
+        let last_line = self.line_count().saturating_sub(1);
+        self.move_cursor_to_line(last_line);
+    }
+
+    /// Moves the cursor to a line of the document, keeping to the desired
+    ///   column.
+    ///
+    /// # Arguments
+    ///
+    /// * `line_ix` - The zero-based index of the line. Clamped to the last
+    ///   line.
+    pub fn go_to_line(&mut self, line_ix: usize) {
+        self.move_cursor_to_line(line_ix);
+    }
+
+    /// Scrolls the editor so that the cursor's screen line is in the middle
+    ///   of the viewport, as far as the ends of the document allow.
+    pub fn center_cursor(&mut self) {
+        let top = self.walk_back(self.cursor_screen_line(), self.dimensions.rows as usize / 2);
+        self.set_top(top.min(self.max_top()));
+        self.scroll_to_cursor();
+    }
+
+    /// Scrolls the viewport and the cursor down a number of screen lines.
+    fn scroll_lines_down(&mut self, num_lines: usize) {
+        let top = self.walk_forward(self.top(), num_lines).min(self.max_top());
+        self.set_top(top);
+        self.move_cursor_down_screen_lines(num_lines.min(u16::MAX as usize) as u16);
+    }
+
+    /// Scrolls the viewport and the cursor up a number of screen lines.
+    fn scroll_lines_up(&mut self, num_lines: usize) {
+        let top = self.walk_back(self.top(), num_lines);
+        self.set_top(top);
+        self.move_cursor_up_screen_lines(num_lines.min(u16::MAX as usize) as u16);
+    }
+
+    /// Moves the cursor onto a screen line, as close to the desired column,
+    ///   counted from the start of the screen line, as its length allows.
+    fn move_cursor_to_screen_line(&mut self, target: ScreenLine) {
+        let line = self.line_text(self.position.line_ix);
+        let segments = self.segments(self.position.line_ix);
+        let current = &segments[self.cursor_screen_line().segment_ix];
+        let offset = self.desired_column.saturating_sub(graphemes::display_column(&line, current.start, self.tab_width));
+
+        let line = self.line_text(target.line_ix);
+        let segments = self.segments(target.line_ix);
+        let segment = &segments[target.segment_ix];
+        let start = graphemes::display_column(&line, segment.start, self.tab_width);
+        let mut column_ix = graphemes::char_ix_at_column(&line, start + offset, self.tab_width);
+        if target.segment_ix + 1 < segments.len() {
+            // Stay on the screen line, rather than the start of the next
+            column_ix = column_ix.min(graphemes::prev_boundary(&line, segment.end));
         } else {
-            self.cursor_location.row_ix += num_rows;
+            column_ix = column_ix.min(self.max_column(target.line_ix));
+        }
+        self.position = Position::new(target.line_ix, column_ix);
+        self.desired_column = start + offset;
+        self.scroll_to_cursor();
+    }
+
+    /// Moves the cursor to a document line, keeping to the desired column as
+    ///   closely as the length of the line allows.
+    fn move_cursor_to_line(&mut self, line_ix: usize) {
+        let line_ix = line_ix.min(self.line_count().saturating_sub(1));
+        let line = self.line_text(line_ix);
+        let column_ix = graphemes::char_ix_at_column(&line, self.desired_column, self.tab_width);
+        self.position.line_ix = line_ix;
+        self.position.column_ix = column_ix.min(self.max_column(line_ix));
+        self.scroll_to_cursor();
+    }
+
+    /// Keeps the cursor within the current line, for example after leaving
+    ///   insert mode.
+    pub fn clamp_cursor(&mut self) {
+        let max_column = self.max_column(self.position.line_ix);
+        if self.position.column_ix > max_column {
+            self.set_cursor_position(self.position.line_ix, max_column);
         }
     }
+
+    /// Keeps the cursor and scrolling within the document once it has
+    ///   changed, such as through another editor showing the same document.
+    pub fn fit_to_document(&mut self) {
+        let version = match &self.content {
+            Some(document) => document.borrow().version(),
+            None => return,
+        };
+        if version == self.seen_version {
+            return;
+        }
+        self.seen_version = version;
+
+        let last_line = self.line_count().saturating_sub(1);
+        self.top_line = self.top_line.min(last_line);
+        let line_ix = self.position.line_ix.min(last_line);
+        let line = self.line_text(line_ix);
+        let column_ix = self.position.column_ix.min(self.max_column(line_ix));
+        self.position = Position::new(line_ix, graphemes::grapheme_start(&line, column_ix));
+        self.scroll_to_cursor();
+    }
+
+    /// Gets the furthest column the cursor can move to on a line. Outside of
+    ///   insert mode this is the start of the last grapheme on the line.
+    fn max_column(&self, line_ix: usize) -> usize {
+        let line_len = self.line_len(line_ix);
+        if self.cursor_past_end {
+            line_len
+        } else {
+            graphemes::prev_boundary(&self.line_text(line_ix), line_len)
+        }
+    }
+
+    /// Gets the display column of the cursor, and the width of the grapheme
+    ///   under it.
+    fn cursor_display_column(&self) -> (usize, usize) {
+        let line = self.line_text(self.position.line_ix);
+        let column = graphemes::display_column(&line, self.position.column_ix, self.tab_width);
+        let width = graphemes::display_column(
+            &line, graphemes::next_boundary(&line, self.position.column_ix), self.tab_width
+        ) - column;
+        (column, width.max(1))
+    }
+
+    /// Scrolls the editor, if needed, so that the cursor is visible.
+    fn scroll_to_cursor(&mut self) {
+        self.scroll_vertically_to(self.cursor_screen_line());
+        let (column, width) = self.cursor_display_column();
+        self.scroll_horizontally_to(column, width);
+        self.update_cursor_location();
+    }
+
+    /// Scrolls vertically, if needed, so that a screen line is visible and
+    ///   at least `scroll_off` screen lines from the top and bottom of the
+    ///   viewport.
+    fn scroll_vertically_to(&mut self, target: ScreenLine) {
+        let rows = self.dimensions.rows.max(1) as usize;
+        // The margin can't be more than half the viewport, or it couldn't be met
+        let margin = (self.scroll_off as usize).min((rows - 1) / 2);
+        let mut top = self.top();
+
+        if target < top || self.distance(top, target, rows) < margin {
+            top = self.walk_back(target, margin);
+        } else if self.distance(top, target, rows) + margin >= rows {
+            top = self.walk_back(target, rows - 1 - margin);
+        }
+
+        // Don't scroll the end of the document further up than it needs to be
+        self.set_top(top.min(self.max_top()));
+    }
+
+    /// Scrolls horizontally, if needed, so that a span of display columns is
+    ///   visible right of the gutter. Wrapped lines are never scrolled.
+    fn scroll_horizontally_to(&mut self, column_ix: usize, width: usize) {
+        if self.wrap != Wrap::Off {
+            self.scroll_column = 0;
+            return;
+        }
+        let columns = self.text_dimensions().columns.max(1) as usize;
+        let mut left = self.scroll_column as usize;
+
+        if column_ix < left {
+            left = column_ix;
+        } else if column_ix + width > left + columns {
+            left = (column_ix + width).saturating_sub(columns).min(column_ix);
+        }
+        self.scroll_column = left as u16;
+    }
+
+    /// Derives the on-screen cursor location from the cursor's position in the
+    ///   document and the amount the editor is scrolled.
+    fn update_cursor_location(&mut self) {
+        let cursor = self.cursor_screen_line();
+        let top = self.top();
+        let row_ix = if cursor < top { 0 } else { self.distance(top, cursor, self.dimensions.rows as usize) };
+        let (column, _) = self.cursor_display_column();
+        let column_ix = if self.wrap == Wrap::Off {
+            column.saturating_sub(self.scroll_column as usize)
+        } else {
+            let line = self.line_text(self.position.line_ix);
+            let segment = &self.segments(self.position.line_ix)[cursor.segment_ix];
+            let indent = if cursor.segment_ix > 0 { self.indicator_width() } else { 0 };
+            let column_ix = indent + column - graphemes::display_column(&line, segment.start, self.tab_width);
+            // A cursor after the end of a full screen line stays at its edge
+            column_ix.min((self.text_dimensions().columns as usize).saturating_sub(1))
+        };
+        self.cursor_location.row_ix = row_ix as u16;
+        self.cursor_location.column_ix = self.gutter_width() + column_ix as u16;
+    }
+
+    /// Gets the number of columns the wrap indicator takes, leaving at least
+    ///   one column for text.
+    fn indicator_width(&self) -> usize {
+        let columns = self.text_dimensions().columns as usize;
+        graphemes::line_width(&self.wrap_indicator, self.tab_width).min(columns.saturating_sub(1))
+    }
+
+    /// Gets the char ranges of a line shown on each of its screen lines.
+    fn segments(&self, line_ix: usize) -> Vec<Range<usize>> {
+        if self.wrap == Wrap::Off {
+            return std::iter::once(0..self.line_len(line_ix)).collect();
+        }
+        let columns = self.text_dimensions().columns as usize;
+        let line = self.line_text(line_ix);
+        wrap::wrap_line(&line, self.wrap, columns, self.indicator_width(), self.tab_width)
+    }
+
+    /// Gets the number of screen lines a line is shown on.
+    fn segment_count(&self, line_ix: usize) -> usize {
+        match self.wrap {
+            Wrap::Off => 1,
+            _ => self.segments(line_ix).len(),
+        }
+    }
+
+    /// Gets the screen lines shown in the editor, from the top, along with
+    ///   the chars of the line each shows.
+    fn screen_lines(&self) -> Vec<(ScreenLine, Range<usize>)> {
+        let rows = self.dimensions.rows as usize;
+        let line_count = self.line_count();
+        let top = self.top();
+        let mut screen_lines = Vec::new();
+        let mut line_ix = top.line_ix;
+        while screen_lines.len() < rows && line_ix < line_count {
+            let skip = if line_ix == top.line_ix { top.segment_ix } else { 0 };
+            for (segment_ix, segment) in self.segments(line_ix).into_iter().enumerate().skip(skip) {
+                if screen_lines.len() == rows {
+                    break;
+                }
+                screen_lines.push((ScreenLine::new(line_ix, segment_ix), segment));
+            }
+            line_ix += 1;
+        }
+        screen_lines
+    }
+
+    /// Gets the columns of the editor in which chars of a screen line are
+    ///   drawn, counting from the left of the gutter.
+    fn segment_columns(&self, line: &str, screen_line: ScreenLine, segment: Range<usize>, chars: Range<usize>) -> Option<Range<u16>> {
+        let chars = chars.start.max(segment.start)..chars.end.min(segment.end);
+        let (left, indent) = match self.wrap {
+            Wrap::Off => (self.scroll_column as usize, 0),
+            _ if screen_line.segment_ix > 0 => (graphemes::display_column(line, segment.start, self.tab_width), self.indicator_width()),
+            _ => (graphemes::display_column(line, segment.start, self.tab_width), 0),
+        };
+        let columns = (self.text_dimensions().columns as usize).saturating_sub(indent);
+        let start = graphemes::display_column(line, chars.start, self.tab_width).max(left);
+        let end = graphemes::display_column(line, chars.end, self.tab_width).min(left + columns);
+        if start >= end {
+            return None;
+        }
+        let offset = self.gutter_width() + indent as u16;
+        Some(offset + (start - left) as u16..offset + (end - left) as u16)
+    }
+
+    /// Gets the screen line holding the cursor.
+    fn cursor_screen_line(&self) -> ScreenLine {
+        let segments = self.segments(self.position.line_ix);
+        let segment_ix = segments.iter().rposition(|segment| segment.start <= self.position.column_ix).unwrap_or(0);
+        ScreenLine::new(self.position.line_ix, segment_ix)
+    }
+
+    /// Gets the first screen line shown.
+    fn top(&self) -> ScreenLine {
+        let line_ix = self.top_line;
+        let segment_ix = match self.top_segment {
+            0 => 0,
+            segment_ix => segment_ix.min(self.segment_count(line_ix) - 1),
+        };
+        ScreenLine::new(line_ix, segment_ix)
+    }
+
+    /// Scrolls so that a screen line is shown first.
+    fn set_top(&mut self, top: ScreenLine) {
+        self.top_line = top.line_ix;
+        self.top_segment = top.segment_ix;
+    }
+
+    /// Gets the screen line a number of screen lines before another,
+    ///   stopping at the first.
+    fn walk_back(&self, screen_line: ScreenLine, num_rows: usize) -> ScreenLine {
+        if self.wrap == Wrap::Off {
+            return ScreenLine::new(screen_line.line_ix.saturating_sub(num_rows), 0);
+        }
+        let mut screen_line = screen_line;
+        for _ in 0..num_rows {
+            screen_line = match screen_line {
+                ScreenLine { line_ix: 0, segment_ix: 0 } => break,
+                ScreenLine { line_ix, segment_ix: 0 } => ScreenLine::new(line_ix - 1, self.segment_count(line_ix - 1) - 1),
+                ScreenLine { line_ix, segment_ix } => ScreenLine::new(line_ix, segment_ix - 1),
+            };
+        }
+        screen_line
+    }
+
+    /// Gets the screen line a number of screen lines after another, stopping
+    ///   at the last.
+    fn walk_forward(&self, screen_line: ScreenLine, num_rows: usize) -> ScreenLine {
+        let last_line = self.line_count().saturating_sub(1);
+        if self.wrap == Wrap::Off {
+            return ScreenLine::new((screen_line.line_ix + num_rows).min(last_line), 0);
+        }
+        let mut screen_line = screen_line;
+        let mut segment_count = self.segment_count(screen_line.line_ix);
+        for _ in 0..num_rows {
+            if screen_line.segment_ix + 1 < segment_count {
+                screen_line.segment_ix += 1;
+            } else if screen_line.line_ix < last_line {
+                screen_line = ScreenLine::new(screen_line.line_ix + 1, 0);
+                segment_count = self.segment_count(screen_line.line_ix);
+            } else {
+                break;
+            }
+        }
+        screen_line
+    }
+
+    /// Counts the screen lines from one screen line down to another, up to a
+    ///   limit.
+    fn distance(&self, from: ScreenLine, to: ScreenLine, limit: usize) -> usize {
+        if self.wrap == Wrap::Off {
+            return to.line_ix.saturating_sub(from.line_ix).min(limit);
+        }
+        let mut count = 0;
+        let mut screen_line = from;
+        while screen_line < to && count < limit {
+            let next = self.walk_forward(screen_line, 1);
+            // Stop at the end of the document, should `to` be past it
+            if next == screen_line {
+                break;
+            }
+            screen_line = next;
+            count += 1;
+        }
+        count
+    }
+
+    /// Gets the furthest the editor can scroll down while still filling the
+    ///   viewport.
+    fn max_top(&self) -> ScreenLine {
+        let last_line = self.line_count().saturating_sub(1);
+        let last = ScreenLine::new(last_line, self.segment_count(last_line) - 1);
+        self.walk_back(last, (self.dimensions.rows as usize).saturating_sub(1))
+    }
+
+    /// Gets the number of lines in the document.
+    fn line_count(&self) -> usize {
+        self.content
+            .as_ref()
+            .map_or(0, |document| document.borrow().line_count())
+    }
+
+    /// Gets the position in the document (line and column) under the cursor.
+    pub fn cursor_position(&self) -> (usize, usize) {
+        (self.position.line_ix, self.position.column_ix)
+    }
+
+    /// Gets the text of a line in the document, without its line break.
+    fn line_text(&self, line_ix: usize) -> String {
+        self.content
+            .as_ref()
+            .map_or_else(String::new, |document| document.borrow().line(line_ix))
+    }
+
+    /// Gets the length of a line in the document, in chars.
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - The editor displaying the document.
+    /// * `line_ix` - The document line to measure.
+    pub fn line_len(&self, line_ix: usize) -> usize {
+        self.content
+            .as_ref()
+            .map_or(0, |document| document.borrow().line_len(line_ix))
+    }
+
+    /// Moves the cursor to a position in the document, scrolling the editor if
+    ///   needed so that the cursor remains visible.
+    ///
+    /// The position is clamped to the document and snapped to the start of a
+    ///   grapheme. Its display column becomes the column the cursor returns to
+    ///   when later moving between lines.
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - The editor in which to move the cursor.
+    /// * `line_ix` - The document line on which to place the cursor.
+    /// * `column_ix` - The column within the line on which to place the cursor.
+    pub fn set_cursor_position(&mut self, line_ix: usize, column_ix: usize) {
+        let line_ix = line_ix.min(self.line_count().saturating_sub(1));
+        let line = self.line_text(line_ix);
+        let column_ix = graphemes::grapheme_start(&line, column_ix);
+        self.position = Position::new(line_ix, column_ix);
+        self.desired_column = graphemes::display_column(&line, column_ix, self.tab_width);
+        self.scroll_to_cursor();
+    }
+
+    /// Inserts text into the document at the cursor, leaving the cursor after
+    ///   the inserted text.
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - The editor into which to insert text.
+    /// * `text` - The text to insert.
+    pub fn insert_text(&mut self, text: &str) {
+        let char_ix = match self.cursor_char_ix() {
+            Some(char_ix) => char_ix,
+            None => return,
+        };
+        if let Some(document) = &self.content {
+            document.borrow_mut().insert(char_ix, text);
+        }
+        self.set_cursor_char_ix(char_ix + text.chars().count());
+    }
+
+    /// Splits the current line at the cursor, moving the cursor to the start
+    ///   of the new line. The line break matches the document's line ending.
+    pub fn insert_newline(&mut self) {
+        let line_ending = match &self.content {
+            Some(document) => document.borrow().line_ending(),
+            None => return,
+        };
+        self.insert_text(line_ending.as_str());
+    }
+
+    /// Deletes the grapheme before the cursor. At the start of a line this
+    ///   joins the line onto the end of the previous line.
+    pub fn delete_backward(&mut self) {
+        let Position { line_ix, column_ix } = self.position;
+        let range = match &self.content {
+            Some(document) => {
+                let document = document.borrow();
+                let line_start = document.line_to_char(line_ix);
+                let char_ix = line_start + column_ix;
+                if column_ix > 0 {
+                    let line = document.line(line_ix);
+                    line_start + graphemes::prev_boundary(&line, column_ix)..char_ix
+                } else if line_ix > 0 {
+                    // Remove the whole line break, which may be more than one char
+                    let prev_end = document.line_to_char(line_ix - 1) + document.line_len(line_ix - 1);
+                    prev_end..char_ix
+                } else {
+                    return;
+                }
+            }
+            None => return,
+        };
+        let start = range.start;
+        if let Some(document) = &self.content {
+            document.borrow_mut().delete(range);
+        }
+        self.set_cursor_char_ix(start);
+    }
+
+    /// Deletes the grapheme under the cursor. At the end of a line this joins
+    ///   the next line onto the current line.
+    pub fn delete_forward(&mut self) {
+        let Position { line_ix, column_ix } = self.position;
+        let range = match &self.content {
+            Some(document) => {
+                let document = document.borrow();
+                let line_start = document.line_to_char(line_ix);
+                let char_ix = line_start + column_ix;
+                if column_ix < document.line_len(line_ix) {
+                    let line = document.line(line_ix);
+                    char_ix..line_start + graphemes::next_boundary(&line, column_ix)
+                } else if line_ix + 1 < document.line_count() {
+                    char_ix..document.line_to_char(line_ix + 1)
+                } else {
+                    return;
+                }
+            }
+            None => return,
+        };
+        let start = range.start;
+        if let Some(document) = &self.content {
+            document.borrow_mut().delete(range);
+        }
+        self.set_cursor_char_ix(start);
+    }
+
+    /// Starts grouping edits made in this editor into a single undo step, for
+    ///   example while in insert mode.
+    pub fn begin_edit_group(&mut self) {
+        if let Some(char_ix) = self.cursor_char_ix() {
+            if let Some(document) = &self.content {
+                document.borrow_mut().begin_transaction(char_ix);
+            }
+        }
+    }
+
+    /// Stops grouping edits into a single undo step.
+    pub fn end_edit_group(&mut self) {
+        if let Some(char_ix) = self.cursor_char_ix() {
+            if let Some(document) = &self.content {
+                document.borrow_mut().commit_transaction(char_ix);
+            }
+        }
+    }
+
+    /// Undoes the most recent change to the document, restoring the cursor to
+    ///   where it was before the change.
+    ///
+    /// Returns false if there was nothing to undo.
+    pub fn undo(&mut self) -> bool {
+        let cursor = self.content.as_ref().and_then(|document| document.borrow_mut().undo());
+        self.restore_cursor(cursor)
+    }
+
+    /// Redoes the most recently undone change to the document, restoring the
+    ///   cursor to where it was after the change.
+    ///
+    /// Returns false if there was nothing to redo.
+    pub fn redo(&mut self) -> bool {
+        let cursor = self.content.as_ref().and_then(|document| document.borrow_mut().redo());
+        self.restore_cursor(cursor)
+    }
+
+    /// Undoes every change made within a recent period of time.
+    ///
+    /// Returns false if there was nothing to undo.
+    ///
+    /// # Arguments
+    ///
+    /// * `self` - The editor in which to undo changes.
+    /// * `period` - How far back in time to go. Periods reaching back before
+    ///   the epoch undo everything.
+    pub fn undo_period(&mut self, period: Duration) -> bool {
+        let time = SystemTime::now().checked_sub(period).unwrap_or(SystemTime::UNIX_EPOCH);
+        let cursor = self.content.as_ref().and_then(|document| document.borrow_mut().undo_to(time));
+        self.restore_cursor(cursor)
+    }
+
+    /// Moves the cursor to a char index restored from the undo history.
+    fn restore_cursor(&mut self, cursor: Option<usize>) -> bool {
+        match cursor {
+            Some(char_ix) => {
+                self.set_cursor_char_ix(char_ix);
+                self.clamp_cursor();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Gets the char index in the document under the cursor.
+    pub fn cursor_char_ix(&self) -> Option<usize> {
+        let Position { line_ix, column_ix } = self.position;
+        self.content
+            .as_ref()
+            .map(|document| document.borrow().line_to_char(line_ix) + column_ix)
+    }
+
+    /// Moves the cursor to a char index in the document.
+    pub fn set_cursor_char_ix(&mut self, char_ix: usize) {
+        let position = match &self.content {
+            Some(document) => {
+                let document = document.borrow();
+                let line_ix = document.char_to_line(char_ix);
+                (line_ix, char_ix - document.line_to_char(line_ix))
+            }
+            None => return,
+        };
+        self.set_cursor_position(position.0, position.1);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::document::TextDocument;
+
+    /// Builds a document filled with text of the specified width and height.
+    fn block_of_text(columns: usize, rows: usize) -> String {
+        vec!["x".repeat(columns); rows].join("\n")
+    }
 
     /// Tests that the editor moves the cursor to the left.
     /// 
@@ -179,6 +1060,7 @@ mod tests {
     #[test]
     fn moves_cursor_left_within_bounds() {
         let mut editor = Editor::new(Dimensions::new(80, 24));
+        editor.set_content(TextDocument::new(&block_of_text(100, 30)).into_handle());
 
         // Move the cursor 10 columns right, then 1 column to the left
         editor.move_cursor_right(10);
@@ -194,6 +1076,7 @@ mod tests {
     #[test]
     fn moves_cursor_right_within_bounds() {
         let mut editor = Editor::new(Dimensions::new(80, 24));
+        editor.set_content(TextDocument::new(&block_of_text(100, 30)).into_handle());
 
         // Move cursor one column to the right
         editor.move_cursor_right(1);
@@ -208,6 +1091,7 @@ mod tests {
     #[test]
     fn moves_cursor_up_within_bounds() {
         let mut editor = Editor::new(Dimensions::new(80,24));
+        editor.set_content(TextDocument::new(&block_of_text(100, 30)).into_handle());
 
         // Move the cursor down 3 rows then up 1 row
         editor.move_cursor_down(3);
@@ -223,6 +1107,7 @@ mod tests {
     #[test]
     fn moves_cursor_down_within_bounds() {
         let mut editor = Editor::new(Dimensions::new(80,24));
+        editor.set_content(TextDocument::new(&block_of_text(100, 30)).into_handle());
 
         // Move the cursor down 1 row
         editor.move_cursor_down(1);
@@ -246,6 +1131,7 @@ mod tests {
     #[test]
     fn constrains_cursor_within_editor_right_side() {
         let mut editor = Editor::new(Dimensions::new(80, 24));
+        editor.set_content(TextDocument::new(&block_of_text(100, 30)).into_handle());
 
         // Attempt to move the cursor right (should constrain to editor width)
         editor.move_cursor_right(100);
@@ -268,6 +1154,7 @@ mod tests {
     #[test]
     fn constrains_cursor_within_editor_bottom() {
         let mut editor = Editor::new(Dimensions::new(80, 24));
+        editor.set_content(TextDocument::new(&block_of_text(100, 30)).into_handle());
 
         // Attempt to move the cursor down (should constrain to editor height)
         editor.move_cursor_down(30);
@@ -287,8 +1174,8 @@ mod tests {
     #[test]
     fn get_render_content() {
         let mut editor = Editor::new(Dimensions::new(80, 24));
-        let document = TextDocument::new("Hello\r\nWorld!");
-        editor.set_content(&document);
+        let document = TextDocument::new("Hello\r\nWorld!").into_handle();
+        editor.set_content(document);
 
         assert_eq!(editor.get_render_content(), vec!["Hello", "World!"]);
     }
@@ -298,8 +1185,8 @@ mod tests {
     #[test]
     fn get_render_content_when_too_tall_to_fit() {
         let mut editor = Editor::new(Dimensions::new(10, 1));
-        let document = TextDocument::new("First\r\nSecond");
-        editor.set_content(&document);
+        let document = TextDocument::new("First\r\nSecond").into_handle();
+        editor.set_content(document);
 
         assert_eq!(editor.get_render_content(), vec!["First"]);
     }
@@ -309,8 +1196,8 @@ mod tests {
     #[test]
     fn get_render_content_when_too_wide_to_fit() {
         let mut editor = Editor::new(Dimensions::new(4, 1));
-        let document = TextDocument::new("First");
-        editor.set_content(&document);
+        let document = TextDocument::new("First").into_handle();
+        editor.set_content(document);
 
         assert_eq!(editor.get_render_content(), vec!["Firs"]);
     }
@@ -320,8 +1207,8 @@ mod tests {
     #[test]
     fn get_render_content_when_scrolled_width() {
         let mut editor = Editor::new(Dimensions::new(4, 1));
-        let document = TextDocument::new("First");
-        editor.set_content(&document);
+        let document = TextDocument::new("First").into_handle();
+        editor.set_content(document);
 
         editor.scroll_to(1, 0);
         assert_eq!(editor.get_render_content(), vec!["irst"]);
@@ -332,8 +1219,8 @@ mod tests {
     #[test]
     fn get_render_content_when_scrolled_beyond_end_of_content() {
         let mut editor = Editor::new(Dimensions::new(4, 1));
-        let document = TextDocument::new("First");
-        editor.set_content(&document);
+        let document = TextDocument::new("First").into_handle();
+        editor.set_content(document);
 
         editor.scroll_to(2, 0);
         assert_eq!(editor.get_render_content(), vec!["rst"]);
@@ -344,8 +1231,8 @@ mod tests {
     #[test]
     fn auto_scroll_when_cursor_moved_too_far_right() {
         let mut editor = Editor::new(Dimensions::new(4, 1));
-        let document = TextDocument::new("First");
-        editor.set_content(&document);
+        let document = TextDocument::new("First").into_handle();
+        editor.set_content(document);
 
         // Move cursor 4 columns to the right (should force scroll)
         editor.move_cursor_right(4);
@@ -360,11 +1247,472 @@ mod tests {
     #[test]
     fn auto_scroll_when_cursor_moved_too_far_right_many_times() {
         let mut editor = Editor::new(Dimensions::new(4, 1));
-        let document = TextDocument::new("First");
-        editor.set_content(&document);
+        let document = TextDocument::new("First").into_handle();
+        editor.set_content(document);
 
         // Attempt to move cursor right 10 times (should force scroll after 4)
         editor.move_cursor_right(10);
         assert_eq!(editor.get_render_content(), vec!["irst"]);
     }
-}
\ No newline at end of file
+
+    /// Typing inserts text at the cursor and advances the cursor.
+    #[test]
+    fn insert_text_at_cursor() {
+        let mut editor = Editor::new(Dimensions::new(80, 24));
+        let document = TextDocument::new("Hllo").into_handle();
+        editor.set_content(document.clone());
+
+        editor.move_cursor_right(1);
+        editor.insert_text("e");
+
+        assert_eq!(document.borrow().get_content(), "Hello");
+        assert_eq!(editor.cursor_location.column_ix, 2);
+    }
+
+    /// Inserting a newline splits the line and moves the cursor down.
+    #[test]
+    fn insert_newline_splits_line() {
+        let mut editor = Editor::new(Dimensions::new(80, 24));
+        let document = TextDocument::new("HelloWorld").into_handle();
+        editor.set_content(document.clone());
+
+        editor.move_cursor_right(5);
+        editor.insert_newline();
+
+        assert_eq!(editor.get_render_content(), vec!["Hello", "World"]);
+        assert_eq!(editor.cursor_position(), (1, 0));
+    }
+
+    /// Backspace at the start of a line joins it onto the previous line,
+    ///  including CRLF line breaks.
+    #[test]
+    fn delete_backward_joins_lines() {
+        let mut editor = Editor::new(Dimensions::new(80, 24));
+        let document = TextDocument::new("Hello\r\nWorld").into_handle();
+        editor.set_content(document.clone());
+
+        editor.move_cursor_down(1);
+        editor.delete_backward();
+
+        assert_eq!(document.borrow().get_content(), "HelloWorld");
+        assert_eq!(editor.cursor_position(), (0, 5));
+    }
+
+    /// Delete at the end of a line joins the next line onto it.
+    #[test]
+    fn delete_forward_joins_lines() {
+        let mut editor = Editor::new(Dimensions::new(80, 24));
+        let document = TextDocument::new("Hello\nWorld").into_handle();
+        editor.set_content(document.clone());
+        editor.cursor_past_end = true;
+
+        editor.move_cursor_right(5);
+        editor.delete_forward();
+        editor.delete_forward();
+
+        assert_eq!(document.borrow().get_content(), "Helloorld");
+        assert_eq!(editor.cursor_position(), (0, 5));
+    }
+
+    /// Typing past the right edge scrolls the editor to keep the cursor visible.
+    #[test]
+    fn insert_text_scrolls_horizontally() {
+        let mut editor = Editor::new(Dimensions::new(4, 1));
+        let document = TextDocument::new("").into_handle();
+        editor.set_content(document);
+
+        editor.insert_text("Hello");
+
+        assert_eq!(editor.scroll_column, 2);
+        assert_eq!(editor.cursor_location.column_ix, 3);
+    }
+
+    /// Builds a document with the specified number of numbered lines.
+    fn numbered_lines(num_lines: usize) -> String {
+        (0..num_lines).map(|ix| ix.to_string()).collect::<Vec<String>>().join("\n")
+    }
+
+    /// Tests that the editor scrolls down when the cursor moves below the
+    ///  bottom of the viewport.
+    #[test]
+    fn auto_scroll_when_cursor_moved_below_viewport() {
+        let mut editor = Editor::new(Dimensions::new(10, 3));
+        editor.set_content(TextDocument::new(&numbered_lines(10)).into_handle());
+
+        editor.move_cursor_down(4);
+
+        assert_eq!(editor.top_line, 2);
+        assert_eq!(editor.cursor_location.row_ix, 2);
+        assert_eq!(editor.get_render_content(), vec!["2", "3", "4"]);
+    }
+
+    /// Tests that the cursor stops at the last line of the document.
+    #[test]
+    fn cursor_constrained_to_last_line() {
+        let mut editor = Editor::new(Dimensions::new(10, 3));
+        editor.set_content(TextDocument::new(&numbered_lines(5)).into_handle());
+
+        editor.move_cursor_down(100);
+
+        assert_eq!(editor.cursor_position(), (4, 0));
+        assert_eq!(editor.top_line, 2);
+    }
+
+    /// Tests that documents with more lines than a u16 can count scroll to
+    ///   their later lines.
+    #[test]
+    fn scrolls_past_u16_lines() {
+        let mut editor = Editor::new(Dimensions::new(10, 3));
+        editor.set_content(TextDocument::new(&numbered_lines(70_000)).into_handle());
+
+        editor.go_to_bottom();
+
+        assert_eq!(editor.top_line, 69_997);
+        assert_eq!(editor.get_render_content(), vec!["69997", "69998", "69999"]);
+    }
+
+    /// Tests that the scroll-off margin keeps lines visible around the cursor.
+    #[test]
+    fn auto_scroll_respects_scroll_off() {
+        let mut editor = Editor::new(Dimensions::new(10, 5));
+        editor.set_content(TextDocument::new(&numbered_lines(20)).into_handle());
+        editor.scroll_off = 1;
+
+        editor.move_cursor_down(4);
+        assert_eq!(editor.top_line, 1);
+        assert_eq!(editor.cursor_location.row_ix, 3);
+
+        editor.move_cursor_up(3);
+        assert_eq!(editor.top_line, 0);
+        assert_eq!(editor.cursor_location.row_ix, 1);
+    }
+
+    /// Tests paging down and back up through a document.
+    #[test]
+    fn page_down_and_up() {
+        let mut editor = Editor::new(Dimensions::new(10, 4));
+        editor.set_content(TextDocument::new(&numbered_lines(10)).into_handle());
+
+        editor.page_down();
+        assert_eq!(editor.top_line, 4);
+        assert_eq!(editor.cursor_position(), (4, 0));
+
+        editor.half_page_up();
+        assert_eq!(editor.top_line, 2);
+        assert_eq!(editor.cursor_position(), (2, 0));
+
+        editor.page_up();
+        assert_eq!(editor.top_line, 0);
+        assert_eq!(editor.cursor_position(), (0, 0));
+    }
+
+    /// Tests jumping to the bottom and top of a document.
+    #[test]
+    fn go_to_bottom_and_top() {
+        let mut editor = Editor::new(Dimensions::new(10, 4));
+        editor.set_content(TextDocument::new(&numbered_lines(10)).into_handle());
+
+        editor.go_to_bottom();
+        assert_eq!(editor.cursor_position(), (9, 0));
+        assert_eq!(editor.get_render_content(), vec!["6", "7", "8", "9"]);
+
+        editor.go_to_top();
+        assert_eq!(editor.cursor_position(), (0, 0));
+        assert_eq!(editor.top_line, 0);
+    }
+
+    /// Tests that moving right stops at the last char of a short line.
+    #[test]
+    fn cursor_constrained_to_line_length() {
+        let mut editor = Editor::new(Dimensions::new(80, 24));
+        editor.set_content(TextDocument::new("abc").into_handle());
+
+        editor.move_cursor_right(10);
+        assert_eq!(editor.cursor_position(), (0, 2));
+
+        // In insert mode the cursor may sit after the last char
+        editor.cursor_past_end = true;
+        editor.move_cursor_right(10);
+        assert_eq!(editor.cursor_position(), (0, 3));
+    }
+
+    /// Tests that moving through a short line remembers the original column.
+    #[test]
+    fn vertical_movement_keeps_desired_column() {
+        let mut editor = Editor::new(Dimensions::new(80, 24));
+        editor.set_content(TextDocument::new("abcdef\nab\nabcdef").into_handle());
+
+        editor.move_cursor_right(4);
+        editor.move_cursor_down(1);
+        assert_eq!(editor.cursor_position(), (1, 1));
+        assert_eq!(editor.cursor_location.column_ix, 1);
+
+        editor.move_cursor_down(1);
+        assert_eq!(editor.cursor_position(), (2, 4));
+    }
+
+    /// Tests that the screen cursor is the document position less the scroll.
+    #[test]
+    fn cursor_location_derived_from_position_and_scroll() {
+        let mut editor = Editor::new(Dimensions::new(4, 2));
+        editor.set_content(TextDocument::new(&block_of_text(10, 10)).into_handle());
+
+        editor.set_cursor_position(5, 6);
+
+        assert_eq!(editor.top_line, 4);
+        assert_eq!(editor.scroll_column, 3);
+        assert_eq!(editor.cursor_location.row_ix, 1);
+        assert_eq!(editor.cursor_location.column_ix, 3);
+    }
+
+    /// Tests that rendering multi-byte and wide characters neither panics nor
+    ///  overflows the editor width.
+    #[test]
+    fn get_render_content_with_wide_characters() {
+        let mut editor = Editor::new(Dimensions::new(5, 1));
+        editor.set_content(TextDocument::new("日本語です").into_handle());
+
+        assert_eq!(editor.get_render_content(), vec!["日本 "]);
+
+        editor.scroll_to(1, 0);
+        assert_eq!(editor.get_render_content(), vec![" 本語"]);
+    }
+
+    /// Tests that centering scrolls the cursor's line to the middle of the
+    ///  viewport, but not past the ends of the document.
+    #[test]
+    fn center_cursor_scrolls_to_middle() {
+        let mut editor = Editor::new(Dimensions::new(10, 4));
+        editor.set_content(TextDocument::new(&numbered_lines(20)).into_handle());
+
+        editor.set_cursor_position(10, 0);
+        editor.center_cursor();
+        assert_eq!(editor.top_line, 8);
+        assert_eq!(editor.cursor_location.row_ix, 2);
+
+        editor.set_cursor_position(19, 0);
+        editor.center_cursor();
+        assert_eq!(editor.top_line, 16);
+    }
+
+    /// Tests that spans of a line map to the columns they are drawn in,
+    ///  allowing for tabs and horizontal scrolling.
+    #[test]
+    fn span_columns_allow_for_scrolling() {
+        let mut editor = Editor::new(Dimensions::new(6, 2));
+        editor.set_content(TextDocument::new("\tab cd\nxyz").into_handle());
+
+        assert_eq!(editor.span_columns(0, 1..3), Some(4..6));
+        editor.scroll_to(5, 0);
+        assert_eq!(editor.span_columns(0, 1..3), Some(0..1));
+        assert_eq!(editor.span_columns(0, 0..1), None);
+        assert_eq!(editor.visible_lines(), 0..2);
+    }
+
+    /// Tests that the gutter adapts to the line count, numbers lines as set,
+    ///  and narrows the text so that the cursor scrolls into view sooner.
+    #[test]
+    fn gutter_narrows_text() {
+        let text: String = (0..1000).map(|_| "abcdefgh\n").collect();
+        let mut editor = Editor::new(Dimensions::new(10, 3));
+        editor.set_content(TextDocument::new("abcdefgh\nb\nc").into_handle());
+        editor.set_line_numbers(LineNumbers::Absolute);
+        assert_eq!(editor.gutter_width(), 4);
+        assert_eq!(editor.text_dimensions(), Dimensions::new(6, 3));
+        assert_eq!(editor.get_gutter_content(), vec!["  1 ", "  2 ", "  3 "]);
+
+        editor.move_cursor_right(7);
+        assert_eq!(editor.scroll_column, 2);
+        assert_eq!(editor.cursor_location.column_ix, 9);
+        assert_eq!(editor.get_render_content()[0], "cdefgh");
+        assert_eq!(editor.span_columns(0, 7..8), Some(9..10));
+
+        editor.set_line_numbers(LineNumbers::Hybrid);
+        editor.move_cursor_down(1);
+        assert_eq!(editor.get_gutter_content(), vec!["  1 ", "  2 ", "  1 "]);
+        editor.set_line_numbers(LineNumbers::Relative);
+        assert_eq!(editor.get_gutter_content(), vec!["  1 ", "  0 ", "  1 "]);
+
+        // Each digit past three widens the gutter
+        editor.set_content(TextDocument::new(&text).into_handle());
+        editor.set_cursor_position(0, 7);
+        assert_eq!(editor.gutter_width(), 5);
+        assert_eq!(editor.scroll_column, 3);
+
+        // A gutter that wouldn't leave room for text is left out
+        editor.resize(Dimensions::new(5, 3));
+        assert_eq!(editor.gutter_width(), 0);
+    }
+
+    /// Tests that wrapped lines are drawn over several screen lines, with the
+    ///  wrap indicator and line numbers, and that the cursor is placed on the
+    ///  screen line holding it.
+    #[test]
+    fn wraps_long_lines() {
+        let mut editor = Editor::new(Dimensions::new(12, 5));
+        editor.set_content(TextDocument::new("one two three four\nfive").into_handle());
+        editor.set_line_numbers(LineNumbers::Absolute);
+        editor.set_wrap(Wrap::Word, "> ");
+
+        assert_eq!(editor.get_render_content(), vec!["one two ", "> three ", "> four", "five"]);
+        assert_eq!(editor.get_gutter_content(), vec!["  1 ", "    ", "    ", "  2 "]);
+        assert_eq!(editor.cursor_rows(), 0..3);
+
+        editor.set_cursor_position(0, 9);
+        assert_eq!(editor.cursor_location, Location::new(7, 1));
+        assert_eq!(editor.span_columns(0, 8..11), Some(6..9));
+        assert_eq!(
+            editor.char_spans(&[2..10, 15..20]),
+            vec![(0, 6..12), (1, 6..8), (2, 7..10), (3, 4..5)]
+        );
+
+        editor.set_wrap(Wrap::Char, "");
+        assert_eq!(editor.get_render_content(), vec!["one two ", "three fo", "ur", "five"]);
+    }
+
+    /// Tests that counting screen lines towards a line past the end of the
+    ///  document stops at the end, rather than walking to the limit.
+    #[test]
+    fn distance_stops_at_end_of_document() {
+        let mut editor = Editor::new(Dimensions::new(4, 5));
+        editor.set_content(TextDocument::new("abcdefghij\nxy").into_handle());
+        editor.set_wrap(Wrap::Char, "");
+
+        let past_end = ScreenLine::new(9, 0);
+        assert_eq!(editor.distance(ScreenLine::new(0, 0), past_end, usize::MAX), 3);
+        assert_eq!(editor.distance(ScreenLine::new(0, 0), past_end, 2), 2);
+    }
+
+    /// Tests that gj and gk move between the screen lines of wrapped lines,
+    ///  keeping to the column within them.
+    #[test]
+    fn moves_by_screen_line() {
+        let mut editor = Editor::new(Dimensions::new(4, 5));
+        editor.set_content(TextDocument::new("abcdefghij\nxy").into_handle());
+        editor.set_wrap(Wrap::Char, "");
+        editor.set_cursor_position(0, 1);
+
+        editor.move_cursor_down_screen_lines(1);
+        assert_eq!(editor.cursor_position(), (0, 5));
+        editor.move_cursor_down_screen_lines(1);
+        assert_eq!(editor.cursor_position(), (0, 9));
+        editor.move_cursor_down_screen_lines(1);
+        assert_eq!(editor.cursor_position(), (1, 1));
+        editor.move_cursor_up_screen_lines(2);
+        assert_eq!(editor.cursor_position(), (0, 5));
+        editor.move_cursor_down_screen_lines(1);
+        assert_eq!(editor.cursor_position(), (0, 9));
+
+        // Moving by line keeps to the column in the whole line
+        editor.move_cursor_up(1);
+        assert_eq!(editor.cursor_position(), (0, 9));
+        editor.move_cursor_down(1);
+        assert_eq!(editor.cursor_position(), (1, 1));
+    }
+
+    /// Tests that scrolling counts screen lines, so that a line taking
+    ///  several rows scrolls into view whole.
+    #[test]
+    fn scrolls_by_screen_line() {
+        let text = "a\nb\n0123456789ab\nc\nd";
+        let mut editor = Editor::new(Dimensions::new(4, 3));
+        editor.set_content(TextDocument::new(text).into_handle());
+        editor.set_wrap(Wrap::Char, "");
+
+        editor.set_cursor_position(2, 9);
+        assert_eq!(editor.get_render_content(), vec!["0123", "4567", "89ab"]);
+        assert_eq!(editor.cursor_location, Location::new(1, 2));
+
+        editor.move_cursor_down(1);
+        assert_eq!(editor.get_render_content(), vec!["4567", "89ab", "c"]);
+        editor.go_to_bottom();
+        assert_eq!(editor.get_render_content(), vec!["89ab", "c", "d"]);
+
+        editor.set_cursor_position(0, 0);
+        editor.page_down();
+        assert_eq!(editor.get_render_content(), vec!["4567", "89ab", "c"]);
+        assert_eq!(editor.cursor_position(), (2, 4));
+
+        editor.center_cursor();
+        assert_eq!(editor.get_render_content(), vec!["0123", "4567", "89ab"]);
+    }
+
+    /// Tests that the cursor moves over whole graphemes and lands on display
+    ///  columns.
+    #[test]
+    fn cursor_moves_by_grapheme() {
+        let mut editor = Editor::new(Dimensions::new(80, 24));
+        editor.set_content(TextDocument::new("日e\u{301}x\ty").into_handle());
+
+        editor.move_cursor_right(1);
+        assert_eq!(editor.cursor_position(), (0, 1));
+        assert_eq!(editor.cursor_location.column_ix, 2);
+
+        editor.move_cursor_right(1);
+        assert_eq!(editor.cursor_position(), (0, 3));
+        assert_eq!(editor.cursor_location.column_ix, 3);
+
+        // Tabs expand to the next tab stop
+        editor.move_cursor_right(2);
+        assert_eq!(editor.cursor_position(), (0, 5));
+        assert_eq!(editor.cursor_location.column_ix, 8);
+
+        editor.move_cursor_left(3);
+        assert_eq!(editor.cursor_position(), (0, 1));
+    }
+
+    /// Tests that deleting removes a whole grapheme cluster.
+    #[test]
+    fn delete_backward_removes_grapheme() {
+        let mut editor = Editor::new(Dimensions::new(80, 24));
+        let document = TextDocument::new("ae\u{301}").into_handle();
+        editor.set_content(document.clone());
+        editor.cursor_past_end = true;
+
+        editor.move_cursor_right(2);
+        editor.delete_backward();
+
+        assert_eq!(document.borrow().get_content(), "a");
+    }
+
+    /// Tests that undo reverts an insert mode session and restores the cursor.
+    #[test]
+    fn undo_edit_group_restores_cursor() {
+        let mut editor = Editor::new(Dimensions::new(80, 24));
+        let document = TextDocument::new("Hello").into_handle();
+        editor.set_content(document.clone());
+        editor.cursor_past_end = true;
+
+        editor.move_cursor_right(5);
+        editor.begin_edit_group();
+        editor.insert_text(" ");
+        editor.insert_newline();
+        editor.insert_text("World");
+        editor.end_edit_group();
+        editor.cursor_past_end = false;
+
+        assert!(editor.undo());
+        assert_eq!(document.borrow().get_content(), "Hello");
+        assert_eq!(editor.cursor_position(), (0, 4));
+
+        assert!(editor.redo());
+        assert_eq!(document.borrow().get_content(), "Hello \nWorld");
+        assert_eq!(editor.cursor_position(), (1, 4));
+        assert!(!editor.redo());
+    }
+
+    /// Tests that undoing over a period longer than the clock has run undoes
+    ///   everything rather than failing.
+    #[test]
+    fn undo_period_before_epoch_undoes_everything() {
+        let mut editor = Editor::new(Dimensions::new(80, 24));
+        let document = TextDocument::new("").into_handle();
+        editor.set_content(document.clone());
+        editor.cursor_past_end = true;
+        editor.insert_text("a");
+        editor.insert_text("b");
+
+        assert!(editor.undo_period(Duration::MAX));
+        assert_eq!(document.borrow().get_content(), "");
+    }
+}
@@ -1,3 +1,4 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Dimensions {
     pub columns: u16,
     pub rows: u16,
@@ -13,8 +14,8 @@ impl Dimensions {
 
     pub fn new(columns: u16, rows: u16) -> Dimensions {
         Dimensions {
-            columns: columns,
-            rows: rows
+            columns,
+            rows
         }
     }
 
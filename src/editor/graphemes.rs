@@ -0,0 +1,221 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Gets the number of screen columns a grapheme cluster occupies.
+///
+/// Tabs expand to the next tab stop, so their width depends on the column at
+///   which they start. Other graphemes are one or two columns wide; zero-width
+///   graphemes (such as a stray combining mark) still take up a column so that
+///   the cursor can be placed on them.
+///
+/// # Arguments
+///
+/// * `grapheme` - The grapheme cluster to measure.
+/// * `display_column` - The screen column at which the grapheme starts.
+/// * `tab_width` - The number of columns between tab stops.
+pub fn grapheme_width(grapheme: &str, display_column: usize, tab_width: usize) -> usize {
+    if grapheme == "\t" {
+        let tab_width = tab_width.max(1);
+        return tab_width - display_column % tab_width;
+    }
+    if grapheme.chars().any(char::is_control) {
+        return 1;
+    }
+    grapheme.width().clamp(1, 2)
+}
+
+/// Gets the display width of a whole line.
+///
+/// # Arguments
+///
+/// * `line` - The text of the line.
+/// * `tab_width` - The number of columns between tab stops.
+pub fn line_width(line: &str, tab_width: usize) -> usize {
+    line.graphemes(true)
+        .fold(0, |column, grapheme| column + grapheme_width(grapheme, column, tab_width))
+}
+
+/// Gets the char index of the grapheme boundary after a char index.
+///
+/// # Arguments
+///
+/// * `line` - The text of the line.
+/// * `char_ix` - The char index to move forward from.
+pub fn next_boundary(line: &str, char_ix: usize) -> usize {
+    let mut boundary = 0;
+    for grapheme in line.graphemes(true) {
+        boundary += grapheme.chars().count();
+        if boundary > char_ix {
+            return boundary;
+        }
+    }
+    boundary
+}
+
+/// Gets the char index of the grapheme boundary before a char index.
+///
+/// # Arguments
+///
+/// * `line` - The text of the line.
+/// * `char_ix` - The char index to move back from.
+pub fn prev_boundary(line: &str, char_ix: usize) -> usize {
+    let mut boundary = 0;
+    for grapheme in line.graphemes(true) {
+        let next = boundary + grapheme.chars().count();
+        if next >= char_ix {
+            return boundary;
+        }
+        boundary = next;
+    }
+    boundary
+}
+
+/// Gets the char index at which the grapheme containing a char index starts.
+///
+/// Indices at or beyond the end of the line resolve to the end of the line.
+///
+/// # Arguments
+///
+/// * `line` - The text of the line.
+/// * `char_ix` - The char index to snap to a grapheme boundary.
+pub fn grapheme_start(line: &str, char_ix: usize) -> usize {
+    let mut boundary = 0;
+    for grapheme in line.graphemes(true) {
+        let next = boundary + grapheme.chars().count();
+        if next > char_ix {
+            return boundary;
+        }
+        boundary = next;
+    }
+    boundary
+}
+
+/// Gets the screen column at which the grapheme at a char index starts.
+///
+/// # Arguments
+///
+/// * `line` - The text of the line.
+/// * `char_ix` - The char index to locate.
+/// * `tab_width` - The number of columns between tab stops.
+pub fn display_column(line: &str, char_ix: usize, tab_width: usize) -> usize {
+    let mut column = 0;
+    let mut boundary = 0;
+    for grapheme in line.graphemes(true) {
+        if boundary >= char_ix {
+            break;
+        }
+        boundary += grapheme.chars().count();
+        column += grapheme_width(grapheme, column, tab_width);
+    }
+    column
+}
+
+/// Gets the char index of the grapheme that covers a screen column.
+///
+/// Columns beyond the end of the line resolve to the end of the line.
+///
+/// # Arguments
+///
+/// * `line` - The text of the line.
+/// * `display_column` - The screen column to locate.
+/// * `tab_width` - The number of columns between tab stops.
+pub fn char_ix_at_column(line: &str, display_column: usize, tab_width: usize) -> usize {
+    let mut column = 0;
+    let mut boundary = 0;
+    for grapheme in line.graphemes(true) {
+        column += grapheme_width(grapheme, column, tab_width);
+        if column > display_column {
+            return boundary;
+        }
+        boundary += grapheme.chars().count();
+    }
+    boundary
+}
+
+/// Renders the visible part of a line.
+///
+/// Tabs are expanded to spaces, and control characters are shown as `?`. Wide
+///   graphemes that are cut off by either edge of the visible area are
+///   replaced by spaces, so the result is never wider than `width`.
+///
+/// # Arguments
+///
+/// * `line` - The text of the line.
+/// * `first_column` - The first screen column to render.
+/// * `width` - The number of screen columns to render.
+/// * `tab_width` - The number of columns between tab stops.
+pub fn render_line(line: &str, first_column: usize, width: usize, tab_width: usize) -> String {
+    let last_column = first_column + width;
+    let mut result = String::new();
+    let mut column = 0;
+    for grapheme in line.graphemes(true) {
+        if column >= last_column {
+            break;
+        }
+        let grapheme_width = grapheme_width(grapheme, column, tab_width);
+        let start = column;
+        let end = column + grapheme_width;
+        column = end;
+
+        if end <= first_column {
+            continue;
+        }
+        if start < first_column || end > last_column || grapheme == "\t" {
+            // Only part of this grapheme is visible (or it's a tab), so pad
+            //  the visible columns with spaces
+            let visible = end.min(last_column) - start.max(first_column);
+            result.extend(std::iter::repeat_n(' ', visible));
+        } else if grapheme.chars().any(char::is_control) {
+            result.push('?');
+        } else {
+            result.push_str(grapheme);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wide graphemes take two columns and tabs run to the next tab stop.
+    #[test]
+    fn measures_grapheme_widths() {
+        assert_eq!(grapheme_width("a", 0, 4), 1);
+        assert_eq!(grapheme_width("日", 0, 4), 2);
+        assert_eq!(grapheme_width("e\u{301}", 0, 4), 1);
+        assert_eq!(grapheme_width("\t", 1, 4), 3);
+        assert_eq!(line_width("a\t日", 4), 6);
+    }
+
+    /// Boundaries step over whole grapheme clusters.
+    #[test]
+    fn steps_over_grapheme_clusters() {
+        let line = "ae\u{301}b";
+
+        assert_eq!(next_boundary(line, 1), 3);
+        assert_eq!(prev_boundary(line, 3), 1);
+        assert_eq!(next_boundary(line, 4), 4);
+        assert_eq!(prev_boundary(line, 0), 0);
+        assert_eq!(grapheme_start(line, 2), 1);
+        assert_eq!(grapheme_start(line, 9), 4);
+    }
+
+    /// Char indices and display columns map onto each other.
+    #[test]
+    fn maps_chars_and_columns() {
+        let line = "日本a";
+
+        assert_eq!(display_column(line, 2, 4), 4);
+        assert_eq!(char_ix_at_column(line, 3, 4), 1);
+        assert_eq!(char_ix_at_column(line, 10, 4), 3);
+    }
+
+    /// Rendering clips at display columns and pads cut-off wide graphemes.
+    #[test]
+    fn renders_clipped_line() {
+        assert_eq!(render_line("日本語", 1, 4, 4), " 本 ");
+        assert_eq!(render_line("a\tb", 0, 8, 4), "a   b");
+        assert_eq!(render_line("abc", 5, 4, 4), "");
+    }
+}
@@ -0,0 +1,80 @@
+/// The fewest digits the gutter makes room for, so that it doesn't change
+///   width as short documents grow.
+const MIN_DIGITS: usize = 3;
+
+/// How line numbers are shown in the gutter beside an editor's text.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineNumbers {
+    /// No gutter is shown.
+    #[default]
+    Off,
+
+    /// Each line shows its line number.
+    Absolute,
+
+    /// Each line shows how far it is from the cursor's line.
+    Relative,
+
+    /// Like `Relative`, but the cursor's line shows its line number.
+    Hybrid,
+}
+
+impl LineNumbers {
+    /// The names of the ways line numbers can be shown, as written with
+    ///   `:set`.
+    pub const NAMES: &'static [&'static str] = &["off", "absolute", "relative", "hybrid"];
+
+    /// Gets the name of the way line numbers are shown.
+    pub fn name(self) -> &'static str {
+        match self {
+            LineNumbers::Off => "off",
+            LineNumbers::Absolute => "absolute",
+            LineNumbers::Relative => "relative",
+            LineNumbers::Hybrid => "hybrid",
+        }
+    }
+
+    /// Gets a way of showing line numbers by its name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name, as written with `:set`.
+    pub fn from_name(name: &str) -> Option<LineNumbers> {
+        match name {
+            "off" => Some(LineNumbers::Off),
+            "absolute" => Some(LineNumbers::Absolute),
+            "relative" => Some(LineNumbers::Relative),
+            "hybrid" => Some(LineNumbers::Hybrid),
+            _ => None,
+        }
+    }
+
+    /// Gets the number shown beside a line.
+    ///
+    /// # Arguments
+    ///
+    /// * `line_ix` - The zero-based index of the line.
+    /// * `cursor_line_ix` - The line holding the cursor.
+    pub fn label(self, line_ix: usize, cursor_line_ix: usize) -> usize {
+        let distance = line_ix.abs_diff(cursor_line_ix);
+        match self {
+            LineNumbers::Relative => distance,
+            LineNumbers::Hybrid if distance > 0 => distance,
+            _ => line_ix + 1,
+        }
+    }
+
+    /// Gets the number of columns the gutter takes: enough for the number of
+    ///   the last line, and a space between the numbers and the text.
+    ///
+    /// # Arguments
+    ///
+    /// * `line_count` - The number of lines in the document.
+    pub fn width(self, line_count: usize) -> u16 {
+        if self == LineNumbers::Off {
+            return 0;
+        }
+        let digits = line_count.max(1).to_string().len().max(MIN_DIGITS);
+        digits as u16 + 1
+    }
+}
@@ -1,3 +1,4 @@
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Location {
     /// The index of the column described by this location.
     ///
@@ -16,7 +17,7 @@ impl Location {
     }
 
     pub fn new(column_ix: u16, row_ix: u16) -> Location {
-        Location { column_ix: column_ix, row_ix: row_ix }
+        Location { column_ix, row_ix }
     }
 
     pub fn from(location: &Location) -> Location {
@@ -0,0 +1,22 @@
+/// A position within a document.
+///
+/// Unlike a `Location`, which describes a cell on the screen, a position is
+///   independent of how the editor is scrolled.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Position {
+    /// The index of the line described by this position.
+    ///
+    /// Zero-based. The 0th line is the first line of the document.
+    pub line_ix: usize,
+
+    /// The char offset within the line described by this position.
+    ///
+    /// Zero-based. The 0th column is the start of the line.
+    pub column_ix: usize,
+}
+
+impl Position {
+    pub fn new(line_ix: usize, column_ix: usize) -> Position {
+        Position { line_ix, column_ix }
+    }
+}
@@ -0,0 +1,130 @@
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+use super::graphemes;
+
+/// How lines longer than the editor is wide are shown.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Wrap {
+    /// Long lines run past the edge, and the editor scrolls sideways to
+    ///   show them.
+    #[default]
+    Off,
+
+    /// Long lines carry on onto the next screen line at the edge.
+    Char,
+
+    /// Long lines carry on onto the next screen line after the last word
+    ///   that fits, or at the edge if a word doesn't fit on its own.
+    Word,
+}
+
+impl Wrap {
+    /// The names of the ways lines can be wrapped, as written with `:set`.
+    pub const NAMES: &'static [&'static str] = &["off", "char", "word"];
+
+    /// Gets the name of the way lines are wrapped.
+    pub fn name(self) -> &'static str {
+        match self {
+            Wrap::Off => "off",
+            Wrap::Char => "char",
+            Wrap::Word => "word",
+        }
+    }
+
+    /// Gets a way of wrapping lines by its name.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name, as written with `:set`.
+    pub fn from_name(name: &str) -> Option<Wrap> {
+        match name {
+            "off" => Some(Wrap::Off),
+            "char" => Some(Wrap::Char),
+            "word" => Some(Wrap::Word),
+            _ => None,
+        }
+    }
+}
+
+/// A row of an editor, showing one segment of a document line. Lines that
+///   aren't wrapped have a single segment.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ScreenLine {
+    /// The zero-based index of the document line.
+    pub line_ix: usize,
+
+    /// Which segment of the line, counting from zero.
+    pub segment_ix: usize,
+}
+
+impl ScreenLine {
+    pub fn new(line_ix: usize, segment_ix: usize) -> ScreenLine {
+        ScreenLine { line_ix, segment_ix }
+    }
+}
+
+/// Splits a line into the segments shown on each screen line, as char
+///   ranges. There is always at least one segment, and each holds at least
+///   one grapheme unless the line is empty.
+///
+/// # Arguments
+///
+/// * `line` - The text of the line.
+/// * `wrap` - How to wrap the line.
+/// * `width` - The number of columns text is shown in.
+/// * `indent` - The number of columns taken by the wrap indicator at the
+///   start of each segment after the first.
+/// * `tab_width` - The number of columns between tab stops.
+pub fn wrap_line(line: &str, wrap: Wrap, width: usize, indent: usize, tab_width: usize) -> Vec<Range<usize>> {
+    let mut segments = Vec::new();
+    // Char index and display column of the start of the segment being built
+    let mut start = (0, 0);
+    let mut available = width.max(1);
+    // Where the segment can end after a word, with the same pair of indices
+    let mut word_end: Option<(usize, usize)> = None;
+    let mut char_ix = 0;
+    let mut column = 0;
+
+    for grapheme in line.graphemes(true) {
+        let grapheme_width = graphemes::grapheme_width(grapheme, column, tab_width);
+        while wrap != Wrap::Off && column + grapheme_width - start.1 > available && char_ix > start.0 {
+            let end = match word_end.take() {
+                Some(end) if wrap == Wrap::Word => end,
+                _ => (char_ix, column),
+            };
+            segments.push(start.0..end.0);
+            start = end;
+            available = width.saturating_sub(indent).max(1);
+        }
+        char_ix += grapheme.chars().count();
+        column += grapheme_width;
+        if grapheme.chars().all(char::is_whitespace) {
+            word_end = Some((char_ix, column));
+        }
+    }
+    segments.push(start.0..char_ix);
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lines wrap at the edge, or after the last word that fits, leaving room
+    ///   for the wrap indicator on later segments.
+    #[test]
+    fn wraps_at_edge_or_words() {
+        assert_eq!(wrap_line("abcdefgh", Wrap::Off, 3, 0, 4), vec![0..8]);
+        assert_eq!(wrap_line("abcdefgh", Wrap::Char, 3, 0, 4), vec![0..3, 3..6, 6..8]);
+        assert_eq!(wrap_line("abcdefgh", Wrap::Char, 4, 1, 4), vec![0..4, 4..7, 7..8]);
+        assert_eq!(wrap_line("", Wrap::Char, 3, 0, 4), vec![0..0]);
+        assert_eq!(wrap_line("abc", Wrap::Char, 3, 0, 4), vec![0..3]);
+
+        assert_eq!(wrap_line("one two three", Wrap::Word, 9, 0, 4), vec![0..8, 8..13]);
+        assert_eq!(wrap_line("a verylongword", Wrap::Word, 6, 0, 4), vec![0..2, 2..8, 8..14]);
+
+        // Wide graphemes move whole onto the next segment
+        assert_eq!(wrap_line("a日本", Wrap::Char, 4, 0, 4), vec![0..2, 2..3]);
+    }
+}
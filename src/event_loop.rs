@@ -0,0 +1,186 @@
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::time::{Duration, Instant};
+use crossterm::event::Event;
+
+/// Something the program wakes up to handle.
+#[derive(Debug, PartialEq)]
+pub enum LoopEvent {
+    /// Input from the terminal.
+    Input(Event),
+
+    /// A timer came due.
+    Timer(TimerKind),
+
+    /// A background task finished, with a message to show.
+    Message(String),
+
+    /// Input can no longer be read, such as after the terminal has gone away,
+    ///   for the reason given.
+    InputLost(String),
+}
+
+/// The jobs that timers are set for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TimerKind {
+    /// Redraw the bars, so that the information they show stays current.
+    BarRefresh,
+
+    /// Copy unsaved changes to swap files.
+    SwapWrite,
+
+    /// Stop waiting for the rest of an ambiguous key sequence.
+    KeyTimeout,
+
+    /// Check whether the config file has changed.
+    ConfigCheck,
+}
+
+/// A pending timer.
+struct Timer {
+    kind: TimerKind,
+    due: Instant,
+    /// The time between firings of a repeating timer.
+    interval: Option<Duration>,
+}
+
+/// Waits for input, timers and messages from background tasks, so that the
+///   program sleeps while there is nothing to do.
+pub struct EventLoop {
+    sender: Sender<LoopEvent>,
+    receiver: Receiver<LoopEvent>,
+    timers: Vec<Timer>,
+}
+
+impl EventLoop {
+    pub fn new() -> EventLoop {
+        let (sender, receiver) = channel();
+        EventLoop { sender, receiver, timers: Vec::new() }
+    }
+
+    /// Gets a sender through which input sources and background tasks can
+    ///   wake the loop.
+    pub fn sender(&self) -> Sender<LoopEvent> {
+        self.sender.clone()
+    }
+
+    /// Sets a timer that fires once.
+    ///
+    /// Replaces any timer already set for the same job.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The job the timer is for.
+    /// * `delay` - How long until the timer fires.
+    pub fn set_timer(&mut self, kind: TimerKind, delay: Duration) {
+        self.add_timer(kind, delay, None);
+    }
+
+    /// Sets a timer that fires repeatedly.
+    ///
+    /// Replaces any timer already set for the same job.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The job the timer is for.
+    /// * `interval` - The time between firings.
+    pub fn set_repeating_timer(&mut self, kind: TimerKind, interval: Duration) {
+        self.add_timer(kind, interval, Some(interval));
+    }
+
+    /// Cancels the timer set for a job, if any.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The job the timer is for.
+    pub fn cancel_timer(&mut self, kind: TimerKind) {
+        self.timers.retain(|timer| timer.kind != kind);
+    }
+
+    /// Blocks until something happens.
+    pub fn wait(&mut self) -> LoopEvent {
+        loop {
+            if let Some(event) = self.fire_due_timer() {
+                return event;
+            }
+            let received = match self.next_due() {
+                Some(due) => self.receiver.recv_timeout(due.saturating_duration_since(Instant::now())),
+                // The loop holds a sender itself, so this can't disconnect
+                None => self.receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match received {
+                Ok(event) => return event,
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => unreachable!("event loop holds a sender"),
+            }
+        }
+    }
+
+    /// Gets something that has already happened, without blocking.
+    pub fn try_next(&mut self) -> Option<LoopEvent> {
+        self.receiver.try_recv().ok().or_else(|| self.fire_due_timer())
+    }
+
+    fn add_timer(&mut self, kind: TimerKind, delay: Duration, interval: Option<Duration>) {
+        self.cancel_timer(kind);
+        self.timers.push(Timer { kind, due: Instant::now() + delay, interval });
+    }
+
+    /// Gets when the next timer is due.
+    fn next_due(&self) -> Option<Instant> {
+        self.timers.iter().map(|timer| timer.due).min()
+    }
+
+    /// Fires the earliest timer that has come due, rescheduling it if it
+    ///   repeats.
+    fn fire_due_timer(&mut self) -> Option<LoopEvent> {
+        let now = Instant::now();
+        let ix = self.timers
+            .iter()
+            .enumerate()
+            .filter(|(_, timer)| timer.due <= now)
+            .min_by_key(|(_, timer)| timer.due)
+            .map(|(ix, _)| ix)?;
+
+        let kind = self.timers[ix].kind;
+        match self.timers[ix].interval {
+            // Schedule from now rather than from when it was due, so that a
+            //  long stall doesn't cause a burst of catch-up firings
+            Some(interval) => self.timers[ix].due = now + interval,
+            None => {
+                self.timers.remove(ix);
+            }
+        }
+        Some(LoopEvent::Timer(kind))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Messages sent from other threads wake the loop.
+    #[test]
+    fn wakes_for_messages() {
+        let mut events = EventLoop::new();
+        let sender = events.sender();
+        std::thread::spawn(move || sender.send(LoopEvent::Message("done".to_string())).unwrap());
+
+        assert_eq!(events.wait(), LoopEvent::Message("done".to_string()));
+        assert_eq!(events.try_next(), None);
+    }
+
+    /// Timers fire once they are due, and repeating timers fire again.
+    #[test]
+    fn fires_timers() {
+        let mut events = EventLoop::new();
+        events.set_repeating_timer(TimerKind::BarRefresh, Duration::from_millis(5));
+        assert_eq!(events.try_next(), None);
+
+        assert_eq!(events.wait(), LoopEvent::Timer(TimerKind::BarRefresh));
+        assert_eq!(events.wait(), LoopEvent::Timer(TimerKind::BarRefresh));
+
+        events.cancel_timer(TimerKind::BarRefresh);
+        std::thread::sleep(Duration::from_millis(10));
+        assert_eq!(events.try_next(), None);
+    }
+}
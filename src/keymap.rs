@@ -0,0 +1,539 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::mode::Mode;
+
+/// Something a key sequence can be bound to.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    MoveLeft,
+    MoveDown,
+    MoveUp,
+    MoveRight,
+    /// Moves down a screen line, which is part of a line when lines wrap.
+    ScreenLineDown,
+    /// Moves up a screen line, which is part of a line when lines wrap.
+    ScreenLineUp,
+    LineStart,
+    LineEnd,
+    PageDown,
+    PageUp,
+    HalfPageDown,
+    HalfPageUp,
+    GoToTop,
+    GoToBottom,
+    InsertMode,
+    /// Enters insert mode after the character under the cursor.
+    Append,
+    /// Opens a new line below the current line, in insert mode.
+    OpenLineBelow,
+    NormalMode,
+    InsertNewline,
+    InsertTab,
+    DeleteBackward,
+    DeleteForward,
+    DeleteWordBackward,
+    DeleteToLineStart,
+    Undo,
+    Redo,
+    /// Starts typing a command on the command line.
+    CommandLine,
+    /// Runs the command typed on the command line.
+    ExecuteCommand,
+    /// Completes the word before the cursor on the command line.
+    Complete,
+    HistoryPrevious,
+    HistoryNext,
+    FocusLeft,
+    FocusDown,
+    FocusUp,
+    FocusRight,
+    FocusNext,
+    /// Starts typing a pattern to search towards the end of the document.
+    SearchForward,
+    /// Starts typing a pattern to search towards the start of the document.
+    SearchBackward,
+    /// Moves to the next match of the search, in the direction it was typed.
+    NextMatch,
+    /// Moves to the next match of the search, against the direction it was
+    ///   typed.
+    PreviousMatch,
+
+    /// Runs a command line, as if it were typed after `:`.
+    Command(String),
+}
+
+/// The names by which actions are bound in the keymap file.
+const ACTION_NAMES: &[(&str, Action)] = &[
+    ("move_left", Action::MoveLeft),
+    ("move_down", Action::MoveDown),
+    ("move_up", Action::MoveUp),
+    ("move_right", Action::MoveRight),
+    ("screen_line_down", Action::ScreenLineDown),
+    ("screen_line_up", Action::ScreenLineUp),
+    ("line_start", Action::LineStart),
+    ("line_end", Action::LineEnd),
+    ("page_down", Action::PageDown),
+    ("page_up", Action::PageUp),
+    ("half_page_down", Action::HalfPageDown),
+    ("half_page_up", Action::HalfPageUp),
+    ("go_to_top", Action::GoToTop),
+    ("go_to_bottom", Action::GoToBottom),
+    ("insert_mode", Action::InsertMode),
+    ("append", Action::Append),
+    ("open_line_below", Action::OpenLineBelow),
+    ("normal_mode", Action::NormalMode),
+    ("insert_newline", Action::InsertNewline),
+    ("insert_tab", Action::InsertTab),
+    ("delete_backward", Action::DeleteBackward),
+    ("delete_forward", Action::DeleteForward),
+    ("delete_word_backward", Action::DeleteWordBackward),
+    ("delete_to_line_start", Action::DeleteToLineStart),
+    ("undo", Action::Undo),
+    ("redo", Action::Redo),
+    ("command_line", Action::CommandLine),
+    ("execute_command", Action::ExecuteCommand),
+    ("complete", Action::Complete),
+    ("history_previous", Action::HistoryPrevious),
+    ("history_next", Action::HistoryNext),
+    ("focus_left", Action::FocusLeft),
+    ("focus_down", Action::FocusDown),
+    ("focus_up", Action::FocusUp),
+    ("focus_right", Action::FocusRight),
+    ("focus_next", Action::FocusNext),
+    ("search_forward", Action::SearchForward),
+    ("search_backward", Action::SearchBackward),
+    ("next_match", Action::NextMatch),
+    ("previous_match", Action::PreviousMatch),
+];
+
+impl Action {
+    /// Gets an action from the name used for it in the keymap file. A name
+    ///   starting with `:` is a command line.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the action.
+    pub fn from_name(name: &str) -> Option<Action> {
+        if let Some(line) = name.strip_prefix(':') {
+            return Some(Action::Command(line.to_string()));
+        }
+        ACTION_NAMES
+            .iter()
+            .find(|(action_name, _)| *action_name == name)
+            .map(|(_, action)| action.clone())
+    }
+}
+
+/// The default bindings of each mode, in key notation.
+const NORMAL_BINDINGS: &[(&str, &str)] = &[
+    ("h", "move_left"), ("<Left>", "move_left"),
+    ("j", "move_down"), ("<Down>", "move_down"),
+    ("k", "move_up"), ("<Up>", "move_up"),
+    ("l", "move_right"), ("<Right>", "move_right"),
+    ("gj", "screen_line_down"), ("g<Down>", "screen_line_down"),
+    ("gk", "screen_line_up"), ("g<Up>", "screen_line_up"),
+    ("<Home>", "line_start"), ("<End>", "line_end"),
+    ("<PageDown>", "page_down"), ("<C-f>", "page_down"),
+    ("<PageUp>", "page_up"), ("<C-b>", "page_up"),
+    ("<C-d>", "half_page_down"), ("<C-u>", "half_page_up"),
+    ("gg", "go_to_top"), ("G", "go_to_bottom"),
+    ("i", "insert_mode"), ("a", "append"), ("o", "open_line_below"),
+    ("x", "delete_forward"), ("<Del>", "delete_forward"),
+    ("u", "undo"), ("<C-r>", "redo"),
+    // Go back in time, to recover from a bad run of edits
+    ("U", ":earlier 30s"),
+    (":", "command_line"),
+    ("/", "search_forward"), ("?", "search_backward"),
+    ("n", "next_match"), ("N", "previous_match"),
+    ("<C-s>", ":write"),
+    ("<C-l>", ":redraw"),
+    ("q", ":qall"),
+    ("<C-w>s", ":split"), ("<C-w>v", ":vsplit"),
+    ("<C-w>c", ":close"), ("<C-w>q", ":close"),
+    ("<C-w>h", "focus_left"), ("<C-w><Left>", "focus_left"),
+    ("<C-w>j", "focus_down"), ("<C-w><Down>", "focus_down"),
+    ("<C-w>k", "focus_up"), ("<C-w><Up>", "focus_up"),
+    ("<C-w>l", "focus_right"), ("<C-w><Right>", "focus_right"),
+    ("<C-w>w", "focus_next"),
+];
+
+const INSERT_BINDINGS: &[(&str, &str)] = &[
+    ("<Esc>", "normal_mode"),
+    ("<Enter>", "insert_newline"), ("<Tab>", "insert_tab"),
+    ("<BS>", "delete_backward"), ("<Del>", "delete_forward"),
+    ("<Left>", "move_left"), ("<Down>", "move_down"),
+    ("<Up>", "move_up"), ("<Right>", "move_right"),
+    ("<Home>", "line_start"), ("<End>", "line_end"),
+    ("<PageDown>", "page_down"), ("<PageUp>", "page_up"),
+    ("<C-s>", ":write"),
+];
+
+const COMMAND_BINDINGS: &[(&str, &str)] = &[
+    ("<Esc>", "normal_mode"), ("<C-c>", "normal_mode"),
+    ("<Enter>", "execute_command"), ("<Tab>", "complete"),
+    ("<BS>", "delete_backward"), ("<Del>", "delete_forward"),
+    ("<C-w>", "delete_word_backward"), ("<C-u>", "delete_to_line_start"),
+    ("<Left>", "move_left"), ("<Right>", "move_right"),
+    ("<Home>", "line_start"), ("<End>", "line_end"),
+    ("<Up>", "history_previous"), ("<Down>", "history_next"),
+];
+
+/// How long to wait for the rest of a key sequence, when the keys typed so
+///   far are both bound and the start of a longer binding.
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// The outcome of looking up the keys typed so far.
+#[derive(Debug, PartialEq)]
+pub enum Resolution {
+    /// The keys may be the start of a longer binding, so more are awaited.
+    Pending,
+
+    /// The leading keys, of the given count, are bound to an action.
+    Bound(Action, usize),
+
+    /// The first key starts no binding, and should be handled as typed.
+    Unbound,
+}
+
+/// Maps sequences of key presses to actions, with a separate table for each
+///   mode.
+pub struct Keymap {
+    tables: HashMap<Mode, HashMap<Vec<KeyEvent>, Action>>,
+    timeout: Duration,
+}
+
+impl Keymap {
+    /// Returns a keymap holding the default bindings.
+    pub fn new() -> Keymap {
+        let mut keymap = Keymap { tables: HashMap::new(), timeout: DEFAULT_TIMEOUT };
+        let defaults = [
+            (Mode::Normal, NORMAL_BINDINGS),
+            (Mode::Insert, INSERT_BINDINGS),
+            (Mode::Command, COMMAND_BINDINGS),
+        ];
+        for (mode, bindings) in defaults.iter() {
+            for (keys, name) in bindings.iter() {
+                let action = Action::from_name(name).expect("default binding to a known action");
+                keymap.bind(*mode, keys, action).expect("default binding in valid notation");
+            }
+        }
+        keymap
+    }
+
+    /// Gets how long to wait for the rest of an ambiguous key sequence.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Binds a key sequence to an action, replacing any existing binding.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - The mode in which the binding applies.
+    /// * `keys` - The key sequence, in key notation, such as `<C-w>s` or `gg`.
+    /// * `action` - The action to bind to.
+    pub fn bind(&mut self, mode: Mode, keys: &str, action: Action) -> Result<(), String> {
+        let keys = parse_keys(keys)?;
+        self.tables.entry(mode).or_default().insert(keys, action);
+        Ok(())
+    }
+
+    /// Removes the binding of a key sequence, so that its keys are handled
+    ///   as typed.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - The mode in which the binding applies.
+    /// * `keys` - The key sequence, in key notation.
+    pub fn unbind(&mut self, mode: Mode, keys: &str) -> Result<(), String> {
+        let keys = parse_keys(keys)?;
+        if let Some(table) = self.tables.get_mut(&mode) {
+            table.remove(&keys);
+        }
+        Ok(())
+    }
+
+    /// Looks up the keys typed so far.
+    ///
+    /// Keys that are bound but also begin a longer binding are pending until
+    ///   the sequence is complete, after which the longest bound sequence of
+    ///   leading keys wins.
+    ///
+    /// # Arguments
+    ///
+    /// * `mode` - The current input mode.
+    /// * `keys` - The keys typed so far, normalized.
+    /// * `complete` - Whether no more keys will be waited for, such as when
+    ///   the timeout has passed.
+    pub fn resolve(&self, mode: Mode, keys: &[KeyEvent], complete: bool) -> Resolution {
+        let table = match self.tables.get(&mode) {
+            Some(table) => table,
+            None => return Resolution::Unbound,
+        };
+        let is_prefix = table.keys().any(|bound| bound.len() > keys.len() && bound.starts_with(keys));
+        if is_prefix && !complete {
+            return Resolution::Pending;
+        }
+        (1..=keys.len())
+            .rev()
+            .find_map(|len| table.get(&keys[..len]).map(|action| Resolution::Bound(action.clone(), len)))
+            .unwrap_or(Resolution::Unbound)
+    }
+
+    /// Applies the overrides in a keymap file. A missing file is not an
+    ///   error.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the keymap file.
+    pub fn load(&mut self, path: &Path) -> Result<(), String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+        };
+        self.apply_config(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Applies keymap overrides written in TOML. Each mode has a table
+    ///   mapping key sequences to action names, where a name starting with
+    ///   `:` runs a command and `none` removes a binding:
+    ///
+    /// ```toml
+    /// timeout = 500
+    ///
+    /// [normal]
+    /// "<C-w>v" = ":vsplit"
+    /// "Z Z" = ":wq"
+    /// "q" = "none"
+    ///
+    /// [insert]
+    /// "jk" = "normal_mode"
+    /// ```
+    ///
+    /// Valid entries are applied even if others are invalid.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The contents of a keymap file.
+    pub fn apply_config(&mut self, text: &str) -> Result<(), String> {
+        let config: toml::Table = text.parse().map_err(|e: toml::de::Error| e.message().trim().to_string())?;
+        let mut errors = Vec::new();
+        for (key, value) in &config {
+            match (key.as_str(), value) {
+                ("timeout", toml::Value::Integer(millis)) if *millis >= 0 =>
+                    self.timeout = Duration::from_millis(*millis as u64),
+                ("timeout", _) => errors.push("timeout must be a number of milliseconds".to_string()),
+                (section, toml::Value::Table(bindings)) => match mode_from_section(section) {
+                    Some(mode) => {
+                        for (keys, action) in bindings {
+                            if let Err(e) = self.apply_binding(mode, keys, action) {
+                                errors.push(e);
+                            }
+                        }
+                    }
+                    None => errors.push(format!("Unknown mode: {}", section)),
+                },
+                (key, _) => errors.push(format!("Unknown setting: {}", key)),
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    /// Applies one entry of a mode's table in a keymap file.
+    fn apply_binding(&mut self, mode: Mode, keys: &str, action: &toml::Value) -> Result<(), String> {
+        let name = action
+            .as_str()
+            .ok_or_else(|| format!("Binding for {} must be an action name", keys))?;
+        if name == "none" {
+            return self.unbind(mode, keys);
+        }
+        let action = Action::from_name(name).ok_or_else(|| format!("Unknown action: {}", name))?;
+        self.bind(mode, keys, action)
+    }
+}
+
+/// Gets the mode whose bindings a section of the keymap file holds.
+fn mode_from_section(section: &str) -> Option<Mode> {
+    match section {
+        "normal" => Some(Mode::Normal),
+        "insert" => Some(Mode::Insert),
+        "command" => Some(Mode::Command),
+        _ => None,
+    }
+}
+
+/// Puts a key press into the form used in the keymap, so that the same key
+///   compares equal however the terminal reported it.
+///
+/// # Arguments
+///
+/// * `event` - The key press.
+pub fn normalize(event: KeyEvent) -> KeyEvent {
+    match event.code {
+        // The case of the character already says whether Shift was held
+        KeyCode::Char(_) => KeyEvent::new(event.code, event.modifiers - KeyModifiers::SHIFT),
+        _ => event,
+    }
+}
+
+/// Parses a key sequence written in key notation.
+///
+/// Characters stand for themselves, and special keys are written in angle
+///   brackets, such as `<Esc>`, `<Enter>`, `<Space>` or `<lt>` for `<`.
+///   Modifiers are prefixed inside the brackets, as in `<C-w>` or `<A-S-Left>`.
+///   Whitespace between keys is ignored, so `g g` is the same as `gg`.
+///
+/// # Arguments
+///
+/// * `notation` - The key sequence.
+pub fn parse_keys(notation: &str) -> Result<Vec<KeyEvent>, String> {
+    let mut keys = Vec::new();
+    let mut rest = notation;
+    while let Some(c) = rest.chars().next() {
+        if c == '<' {
+            if let Some(end) = rest.find('>').filter(|end| *end > 1) {
+                keys.push(parse_special_key(&rest[1..end])?);
+                rest = &rest[end + 1..];
+                continue;
+            }
+        }
+        if !c.is_whitespace() {
+            keys.push(KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE));
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+    if keys.is_empty() {
+        return Err(format!("No keys in '{}'", notation));
+    }
+    Ok(keys)
+}
+
+/// Parses the contents of a key written in angle brackets.
+fn parse_special_key(notation: &str) -> Result<KeyEvent, String> {
+    let mut modifiers = KeyModifiers::NONE;
+    let mut name = notation;
+    // A single byte before the dash is an ASCII modifier letter
+    while name.len() > 2 && name.as_bytes()[1] == b'-' {
+        modifiers |= match name.as_bytes()[0].to_ascii_uppercase() {
+            b'C' => KeyModifiers::CONTROL,
+            b'A' | b'M' => KeyModifiers::ALT,
+            b'S' => KeyModifiers::SHIFT,
+            _ => return Err(format!("Unknown modifier in <{}>", notation)),
+        };
+        name = &name[2..];
+    }
+
+    let mut chars = name.chars();
+    let code = match (chars.next(), chars.next()) {
+        (Some(c), None) => KeyCode::Char(c),
+        _ => match name.to_ascii_lowercase().as_str() {
+            "esc" => KeyCode::Esc,
+            "enter" | "cr" | "return" => KeyCode::Enter,
+            "tab" if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            "tab" => KeyCode::Tab,
+            "bs" | "backspace" => KeyCode::Backspace,
+            "del" | "delete" => KeyCode::Delete,
+            "ins" | "insert" => KeyCode::Insert,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pageup" => KeyCode::PageUp,
+            "pagedown" => KeyCode::PageDown,
+            "space" => KeyCode::Char(' '),
+            "lt" => KeyCode::Char('<'),
+            other => match other.strip_prefix('f').and_then(|n| n.parse::<u8>().ok()) {
+                Some(n) if (1..=12).contains(&n) => KeyCode::F(n),
+                _ => return Err(format!("Unknown key: <{}>", notation)),
+            },
+        },
+    };
+
+    // Terminals report Ctrl and Alt letters in lower case, unless Shift was
+    //  held too
+    let code = match code {
+        KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::Char(c.to_ascii_uppercase()),
+        KeyCode::Char(c) if modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) =>
+            KeyCode::Char(c.to_ascii_lowercase()),
+        code => code,
+    };
+    Ok(normalize(KeyEvent::new(code, modifiers)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(c: char) -> KeyEvent {
+        KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    /// Key notation covers plain characters, special keys and modifiers.
+    #[test]
+    fn parses_key_notation() {
+        assert_eq!(parse_keys("g g").unwrap(), vec![key('g'), key('g')]);
+        assert_eq!(parse_keys("<C-W>s").unwrap(), vec![
+            KeyEvent::new(KeyCode::Char('w'), KeyModifiers::CONTROL),
+            key('s'),
+        ]);
+        assert_eq!(parse_keys("<S-x><lt><Space>").unwrap(), vec![key('X'), key('<'), key(' ')]);
+        assert_eq!(parse_keys("<A-Left>").unwrap(), vec![KeyEvent::new(KeyCode::Left, KeyModifiers::ALT)]);
+        assert_eq!(parse_keys("<").unwrap(), vec![key('<')]);
+        assert!(parse_keys("<Bogus>").is_err());
+        assert!(parse_keys(" ").is_err());
+    }
+
+    /// Sequences that begin a longer binding wait for more keys, then settle
+    ///   on the longest bound sequence once complete.
+    #[test]
+    fn resolves_ambiguous_prefixes() {
+        let mut keymap = Keymap::new();
+        keymap.bind(Mode::Normal, "g", Action::GoToBottom).unwrap();
+
+        assert_eq!(keymap.resolve(Mode::Normal, &[key('g')], false), Resolution::Pending);
+        assert_eq!(keymap.resolve(Mode::Normal, &[key('g')], true), Resolution::Bound(Action::GoToBottom, 1));
+        assert_eq!(keymap.resolve(Mode::Normal, &[key('g'), key('g')], false), Resolution::Bound(Action::GoToTop, 2));
+        assert_eq!(keymap.resolve(Mode::Normal, &[key('g'), key('x')], false), Resolution::Bound(Action::GoToBottom, 1));
+        assert_eq!(keymap.resolve(Mode::Normal, &[key('z')], false), Resolution::Unbound);
+        assert_eq!(keymap.resolve(Mode::Insert, &[key('h')], false), Resolution::Unbound);
+    }
+
+    /// A keymap file overrides, extends and removes bindings, reporting the
+    ///   entries it can't apply.
+    #[test]
+    fn applies_config() {
+        let mut keymap = Keymap::new();
+        let result = keymap.apply_config(concat!(
+            "timeout = 250\n",
+            "[normal]\n",
+            "\"Z Z\" = \":wq\"\n",
+            "q = \"none\"\n",
+            "x = \"explode\"\n",
+            "[insert]\n",
+            "jk = \"normal_mode\"\n",
+        ));
+
+        assert_eq!(result, Err("Unknown action: explode".to_string()));
+        assert_eq!(keymap.timeout(), Duration::from_millis(250));
+        assert_eq!(
+            keymap.resolve(Mode::Normal, &[key('Z'), key('Z')], false),
+            Resolution::Bound(Action::Command("wq".to_string()), 2)
+        );
+        assert_eq!(keymap.resolve(Mode::Normal, &[key('q')], false), Resolution::Unbound);
+        assert_eq!(keymap.resolve(Mode::Normal, &[key('x')], false), Resolution::Bound(Action::DeleteForward, 1));
+        assert_eq!(keymap.resolve(Mode::Insert, &[key('j')], false), Resolution::Pending);
+        assert!(keymap.apply_config("[visual]\nv = \"undo\"\n").is_err());
+    }
+}
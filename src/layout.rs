@@ -0,0 +1,365 @@
+use crate::editor::{Dimensions, Location};
+
+/// Identifies an editor within the layout.
+pub type EditorId = usize;
+
+/// An area of the screen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    /// The top-left corner of the area.
+    pub origin: Location,
+
+    /// The size of the area.
+    pub dimensions: Dimensions,
+}
+
+impl Rect {
+    pub fn new(column_ix: u16, row_ix: u16, columns: u16, rows: u16) -> Rect {
+        Rect {
+            origin: Location::new(column_ix, row_ix),
+            dimensions: Dimensions::new(columns, rows),
+        }
+    }
+
+    /// Gets the index of the leftmost column of the area.
+    pub fn left(&self) -> u16 {
+        self.origin.column_ix
+    }
+
+    /// Gets the index of the top row of the area.
+    pub fn top(&self) -> u16 {
+        self.origin.row_ix
+    }
+
+    /// Gets the index of the column just right of the area.
+    pub fn right(&self) -> u16 {
+        self.origin.column_ix + self.dimensions.columns
+    }
+
+    /// Gets the index of the row just below the area.
+    pub fn bottom(&self) -> u16 {
+        self.origin.row_ix + self.dimensions.rows
+    }
+}
+
+/// The way a split divides its area between its two halves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SplitDirection {
+    /// The halves are stacked, one above the other.
+    Horizontal,
+
+    /// The halves sit side by side.
+    Vertical,
+}
+
+/// A direction in which to move focus between editors.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Direction {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// A node in the layout tree.
+enum Node {
+    /// An area displaying a single editor.
+    Leaf(EditorId),
+
+    /// An area divided between two child nodes.
+    Split {
+        direction: SplitDirection,
+        /// The fraction of the area given to the first child. Kept as a ratio
+        ///   so that resizing the screen resizes each pane in proportion.
+        ratio: f32,
+        first: Box<Node>,
+        second: Box<Node>,
+    },
+}
+
+/// Arranges editors on the screen as a tree of horizontal and vertical splits.
+///
+/// Splits leave a one cell gap between their halves, in which a separator can
+///   be drawn.
+pub struct Layout {
+    root: Node,
+}
+
+impl Layout {
+    /// Returns a layout containing a single editor.
+    ///
+    /// # Arguments
+    ///
+    /// * `editor_id` - The editor that fills the layout.
+    pub fn new(editor_id: EditorId) -> Layout {
+        Layout { root: Node::Leaf(editor_id) }
+    }
+
+    /// Splits the area of an editor in half, giving the second half to a new
+    ///   editor.
+    ///
+    /// Returns false if the target editor isn't in the layout.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The editor whose area is split.
+    /// * `new_editor` - The editor placed in the new half.
+    /// * `direction` - How the area is divided.
+    pub fn split(&mut self, target: EditorId, new_editor: EditorId, direction: SplitDirection) -> bool {
+        match find_leaf(&mut self.root, target) {
+            Some(leaf) => {
+                *leaf = Node::Split {
+                    direction,
+                    ratio: 0.5,
+                    first: Box::new(Node::Leaf(target)),
+                    second: Box::new(Node::Leaf(new_editor)),
+                };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes an editor from the layout, giving its area to its sibling.
+    ///
+    /// Returns false if the editor isn't in the layout, or is the only editor
+    ///   left.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The editor to remove.
+    pub fn close(&mut self, target: EditorId) -> bool {
+        remove_leaf(&mut self.root, target)
+    }
+
+    /// Gets the ids of the editors in the layout, in layout order.
+    pub fn editor_ids(&self) -> Vec<EditorId> {
+        self.arrange(Rect::new(0, 0, 0, 0)).into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Divides an area of the screen between the editors in the layout.
+    ///
+    /// # Arguments
+    ///
+    /// * `area` - The area of the screen available to editors.
+    pub fn arrange(&self, area: Rect) -> Vec<(EditorId, Rect)> {
+        let mut result = Vec::new();
+        arrange_node(&self.root, area, &mut result);
+        result
+    }
+
+    /// Gets the gaps left between the halves of each split, in which
+    ///   separators are drawn, along with the direction of the split.
+    ///
+    /// # Arguments
+    ///
+    /// * `area` - The area of the screen available to editors.
+    pub fn separators(&self, area: Rect) -> Vec<(Rect, SplitDirection)> {
+        let mut result = Vec::new();
+        separate_node(&self.root, area, &mut result);
+        result
+    }
+
+    /// Finds the editor next to another editor in some direction.
+    ///
+    /// Of the editors that lie in that direction and overlap the target along
+    ///   the other axis, the nearest one is chosen.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The editor to start from.
+    /// * `direction` - The direction in which to look.
+    /// * `area` - The area of the screen available to editors.
+    pub fn neighbor(&self, target: EditorId, direction: Direction, area: Rect) -> Option<EditorId> {
+        let rects = self.arrange(area);
+        let from = rects.iter().find(|(id, _)| *id == target)?.1;
+
+        rects
+            .iter()
+            .filter(|(id, _)| *id != target)
+            .filter_map(|(id, rect)| {
+                let overlaps_columns = rect.left() < from.right() && from.left() < rect.right();
+                let overlaps_rows = rect.top() < from.bottom() && from.top() < rect.bottom();
+                let distance = match direction {
+                    Direction::Left if overlaps_rows && rect.right() <= from.left() =>
+                        from.left() - rect.right(),
+                    Direction::Right if overlaps_rows && rect.left() >= from.right() =>
+                        rect.left() - from.right(),
+                    Direction::Up if overlaps_columns && rect.bottom() <= from.top() =>
+                        from.top() - rect.bottom(),
+                    Direction::Down if overlaps_columns && rect.top() >= from.bottom() =>
+                        rect.top() - from.bottom(),
+                    _ => return None,
+                };
+                Some((distance, *id))
+            })
+            .min()
+            .map(|(_, id)| id)
+    }
+}
+
+/// Finds the leaf node holding an editor.
+fn find_leaf(node: &mut Node, target: EditorId) -> Option<&mut Node> {
+    match node {
+        Node::Leaf(id) if *id == target => Some(node),
+        Node::Leaf(_) => None,
+        Node::Split { first, second, .. } => {
+            if let Some(leaf) = find_leaf(first, target) {
+                return Some(leaf);
+            }
+            find_leaf(second, target)
+        }
+    }
+}
+
+/// Removes the leaf holding an editor, replacing its parent with its sibling.
+fn remove_leaf(node: &mut Node, target: EditorId) -> bool {
+    let (first, second) = match node {
+        Node::Leaf(_) => return false,
+        Node::Split { first, second, .. } => (first, second),
+    };
+
+    let survivor = match (&**first, &**second) {
+        (Node::Leaf(id), _) if *id == target => std::mem::replace(&mut **second, Node::Leaf(0)),
+        (_, Node::Leaf(id)) if *id == target => std::mem::replace(&mut **first, Node::Leaf(0)),
+        _ => return remove_leaf(first, target) || remove_leaf(second, target),
+    };
+    *node = survivor;
+    true
+}
+
+/// Divides an area between a node and its descendants.
+fn arrange_node(node: &Node, area: Rect, result: &mut Vec<(EditorId, Rect)>) {
+    match node {
+        Node::Leaf(id) => result.push((*id, area)),
+        Node::Split { direction, ratio, first, second } => {
+            let (first_area, second_area) = divide(area, *direction, *ratio);
+            arrange_node(first, first_area, result);
+            arrange_node(second, second_area, result);
+        }
+    }
+}
+
+/// Collects the gaps between the halves of a node's splits.
+fn separate_node(node: &Node, area: Rect, result: &mut Vec<(Rect, SplitDirection)>) {
+    if let Node::Split { direction, ratio, first, second } = node {
+        let (first_area, second_area) = divide(area, *direction, *ratio);
+        let gap = match direction {
+            SplitDirection::Horizontal => Rect::new(
+                area.left(), first_area.bottom(), area.dimensions.columns,
+                second_area.top() - first_area.bottom()
+            ),
+            SplitDirection::Vertical => Rect::new(
+                first_area.right(), area.top(),
+                second_area.left() - first_area.right(), area.dimensions.rows
+            ),
+        };
+        result.push((gap, *direction));
+        separate_node(first, first_area, result);
+        separate_node(second, second_area, result);
+    }
+}
+
+/// Divides an area in two, leaving a one cell gap between the halves.
+fn divide(area: Rect, direction: SplitDirection, ratio: f32) -> (Rect, Rect) {
+    let total = match direction {
+        SplitDirection::Horizontal => area.dimensions.rows,
+        SplitDirection::Vertical => area.dimensions.columns,
+    };
+    let available = total.saturating_sub(1);
+    let first_size = ((available as f32 * ratio).round() as u16).min(available);
+    let second_size = available - first_size;
+    let second_start = first_size + total.min(1);
+
+    match direction {
+        SplitDirection::Horizontal => (
+            Rect::new(area.left(), area.top(), area.dimensions.columns, first_size),
+            Rect::new(area.left(), area.top() + second_start, area.dimensions.columns, second_size),
+        ),
+        SplitDirection::Vertical => (
+            Rect::new(area.left(), area.top(), first_size, area.dimensions.rows),
+            Rect::new(area.left() + second_start, area.top(), second_size, area.dimensions.rows),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A single editor fills the whole area.
+    #[test]
+    fn single_editor_fills_area() {
+        let layout = Layout::new(1);
+
+        assert_eq!(layout.arrange(Rect::new(0, 0, 80, 24)), vec![(1, Rect::new(0, 0, 80, 24))]);
+    }
+
+    /// Splits divide the area between editors, leaving a gap for separators.
+    #[test]
+    fn splits_divide_area() {
+        let mut layout = Layout::new(1);
+        layout.split(1, 2, SplitDirection::Vertical);
+        layout.split(2, 3, SplitDirection::Horizontal);
+
+        assert_eq!(layout.arrange(Rect::new(0, 0, 81, 25)), vec![
+            (1, Rect::new(0, 0, 40, 25)),
+            (2, Rect::new(41, 0, 40, 12)),
+            (3, Rect::new(41, 13, 40, 12)),
+        ]);
+    }
+
+    /// Separators fill the gaps between the halves of splits.
+    #[test]
+    fn finds_separators() {
+        let mut layout = Layout::new(1);
+        layout.split(1, 2, SplitDirection::Vertical);
+        layout.split(2, 3, SplitDirection::Horizontal);
+
+        assert_eq!(layout.separators(Rect::new(0, 0, 81, 25)), vec![
+            (Rect::new(40, 0, 1, 25), SplitDirection::Vertical),
+            (Rect::new(41, 12, 40, 1), SplitDirection::Horizontal),
+        ]);
+    }
+
+    /// Splits keep their proportions when the area is resized.
+    #[test]
+    fn resize_keeps_proportions() {
+        let mut layout = Layout::new(1);
+        layout.split(1, 2, SplitDirection::Vertical);
+
+        assert_eq!(layout.arrange(Rect::new(0, 0, 121, 10)), vec![
+            (1, Rect::new(0, 0, 60, 10)),
+            (2, Rect::new(61, 0, 60, 10)),
+        ]);
+    }
+
+    /// Closing an editor gives its area back to its sibling.
+    #[test]
+    fn close_collapses_split() {
+        let mut layout = Layout::new(1);
+        layout.split(1, 2, SplitDirection::Vertical);
+        layout.split(2, 3, SplitDirection::Horizontal);
+
+        assert!(layout.close(2));
+        assert_eq!(layout.editor_ids(), vec![1, 3]);
+        assert!(layout.close(1));
+        assert!(!layout.close(3));
+        assert_eq!(layout.arrange(Rect::new(0, 0, 80, 24)), vec![(3, Rect::new(0, 0, 80, 24))]);
+    }
+
+    /// Focus moves to the nearest editor in the requested direction.
+    #[test]
+    fn finds_neighbors() {
+        let mut layout = Layout::new(1);
+        layout.split(1, 2, SplitDirection::Vertical);
+        layout.split(2, 3, SplitDirection::Horizontal);
+        let area = Rect::new(0, 0, 81, 25);
+
+        assert_eq!(layout.neighbor(1, Direction::Right, area), Some(2));
+        assert_eq!(layout.neighbor(3, Direction::Left, area), Some(1));
+        assert_eq!(layout.neighbor(3, Direction::Up, area), Some(2));
+        assert_eq!(layout.neighbor(2, Direction::Down, area), Some(3));
+        assert_eq!(layout.neighbor(1, Direction::Left, area), None);
+    }
+}
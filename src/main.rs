@@ -1,11 +1,35 @@
+mod backend;
 mod bars;
+mod command;
+mod command_line;
+mod config;
 mod core_data;
 mod editor;
+mod event_loop;
+mod keymap;
+mod layout;
+mod mode;
+#[cfg(test)]
+mod scratch;
+mod screen;
 mod screens;
+mod search;
+mod settings;
+mod substitute;
+mod syntax;
 mod document;
 mod program;
+mod terminal_guard;
+mod theme;
 
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+
+use backend::CrosstermBackend;
 use core_data::{CoreData};
+use document::recovery::{self, Resolution};
+use document::TextDocument;
+use event_loop::LoopEvent;
 use program::Program;
 
 pub use crossterm::{
@@ -21,8 +45,71 @@ pub fn buffer_size() -> Result<(u16, u16)> {
 }
 
 fn main() -> Result<()> {
-    let mut stderr = std::io::stdout();
+    // Load any files passed on the command line before taking over the
+    //  terminal, so that errors can be reported on the normal screen.
+    let mut documents = Vec::new();
+    for arg in std::env::args_os().skip(1) {
+        let path = Path::new(&arg);
+
+        // Offer to recover unsaved changes left behind by a crash
+        let resolution = recovery::resolve_swap(path, &mut std::io::stdin().lock(), &mut std::io::stderr())
+            .unwrap_or_else(|e| {
+                eprintln!("stated: cannot read swap file for '{}': {}", path.display(), e);
+                Resolution::Open
+            });
+        if resolution == Resolution::Quit {
+            std::process::exit(1);
+        }
+
+        match TextDocument::open(path) {
+            Ok(mut document) => {
+                if let Resolution::Recover(content) = resolution {
+                    document.recover(&content);
+                }
+                documents.push(document);
+            }
+            Err(e) => {
+                eprintln!("stated: cannot open '{}': {}", path.display(), e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Make sure a panic never leaves the terminal in raw mode
+    terminal_guard::install_panic_hook();
+
+    let mut backend = CrosstermBackend::new(std::io::stdout());
+
+    let mut program: Program = Program::new(documents);
+    if let Some(dir) = config::config_dir() {
+        program.load_config(&dir.join("config.toml"));
+        program.load_keymap(&dir.join("keymap.toml"));
+    }
+
+    // Point out unsaved changes dumped by earlier instances, which may have
+    //  lost their terminal before they could say so. Looking through the temp
+    //  directory could be slow, so it doesn't hold up starting.
+    let sender = program.sender();
+    std::thread::spawn(move || {
+        if let Some(message) = recovery::describe_emergency_dumps(&std::env::temp_dir()) {
+            let _ = sender.send(LoopEvent::Message(message));
+        }
+    });
 
-    let mut program: Program = Program::new();
-    program.run(&mut stderr)
+    match panic::catch_unwind(AssertUnwindSafe(|| program.run(&mut backend))) {
+        Ok(result) => {
+            // Losing the terminal leaves nobody to save changes, so save
+            //  what we can, as after a crash
+            if result.is_err() {
+                program.write_emergency_dump();
+            }
+            result
+        }
+        Err(_) => {
+            // The panic hook has already restored the terminal and printed
+            //  the panic message, so all that's left is to save what we can
+            program.write_emergency_dump();
+            std::process::exit(101);
+        }
+    }
 }
@@ -0,0 +1,28 @@
+/// The input mode of the program. The mode determines how key presses are
+///   interpreted.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Mode {
+    /// Keys issue commands, such as moving the cursor.
+    Normal,
+
+    /// Keys insert text into the document.
+    Insert,
+
+    /// Keys edit a command typed on the command line.
+    Command,
+
+    /// Keys answer a question, such as whether to make a replacement.
+    Confirm,
+}
+
+impl Mode {
+    /// Gets the name of the mode, as displayed to the user.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Mode::Normal => "NORMAL",
+            Mode::Insert => "INSERT",
+            Mode::Command => "COMMAND",
+            Mode::Confirm => "CONFIRM",
+        }
+    }
+}
@@ -1,146 +1,1840 @@
-use std::io::Write;
-use std::time::Duration;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
 use crossterm::{
-    cursor,
-    event::{poll, read, Event, KeyCode},
-    execute, queue, style, terminal,
+    event::{Event, KeyCode, KeyEvent, KeyModifiers},
     Result
 };
 
 use crate::CoreData;
-use crate::bars::{Bar, PerformanceBar, StatusBar};
-use crate::editor::{Editor, Dimensions};
+use crate::backend::Backend;
+use crate::document::{recovery, DocumentHandle, LineEnding, TextDocument};
+use crate::document::swap::{self, SwapStatus};
+use crate::bars::{BarManager, CommandBar, Dock, PerformanceBar, StatusBar};
+use crate::command::{self, LineRange, Registry};
+use crate::command_line::CommandLine;
+use crate::config::FileWatch;
+use crate::editor::{graphemes, Editor, Dimensions, Location};
+use crate::event_loop::{EventLoop, LoopEvent, TimerKind};
+use crate::keymap::{self, Action, Keymap, Resolution};
+use crate::layout::{Direction, EditorId, Layout, Rect, SplitDirection};
+use crate::mode::Mode;
+use crate::screen::Screen;
 use crate::screens::home_screen;
+use crate::search::{Search, SearchDirection};
+use crate::settings::{Config, Kind, Setting, Settings, Value};
+use crate::substitute::{SubstituteArgs, Substitution};
+use crate::theme::{ColorSupport, Theme};
 
-pub struct Program<'a> {
+mod commands;
+mod search;
+mod substitute;
+
+use search::SearchOrigin;
+
+/// What the text typed on the command line is for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Prompt {
+    /// A command, typed after `:`.
+    Command,
+
+    /// A search pattern, typed after `/` or `?`.
+    Search(SearchDirection),
+}
+
+impl Prompt {
+    /// Gets the character shown before the typed text.
+    fn symbol(self) -> char {
+        match self {
+            Prompt::Command => ':',
+            Prompt::Search(direction) => direction.prompt(),
+        }
+    }
+}
+
+pub struct Program {
     core_data: CoreData,
-    // TODO: This should be a vector of editors.
-    editor: Editor<'a>,
-    bars: Vec<Box<dyn Bar>>,
+    /// The open editors, keyed by their id in the layout.
+    editors: BTreeMap<EditorId, Editor>,
+    /// The arrangement of the editors on screen.
+    layout: Layout,
+    /// The editor that receives input.
+    active_editor: EditorId,
+    /// The id to give the next editor that is opened.
+    next_editor_id: EditorId,
+    /// The size of the terminal.
+    screen_size: Dimensions,
+    /// The documents that are open in the program.
+    documents: Vec<DocumentHandle>,
+    /// The bars docked around the editors.
+    bars: BarManager,
+    /// The model of the terminal that frames are drawn into.
+    screen: Screen,
+    /// The current input mode.
+    mode: Mode,
+    /// What the command line is being typed for, in command mode.
+    prompt: Prompt,
+    /// The command being typed, in command mode.
+    command_line: CommandLine,
+    /// The search pattern being typed, in command mode. Kept apart from the
+    ///   command line so that searches have their own history.
+    search_line: CommandLine,
+    /// The search being typed, or the last search, which `n` and `N` repeat.
+    search: Option<Search>,
+    /// Whether the matches of the last search are highlighted.
+    highlight_search: bool,
+    /// Where the search being typed started from.
+    search_origin: Option<SearchOrigin>,
+    /// The last substitution, which `:s` with nothing after it repeats.
+    last_substitute: Option<SubstituteArgs>,
+    /// The substitution waiting for each replacement to be confirmed, in
+    ///   confirm mode.
+    confirming: Option<Substitution>,
+    /// The commands that can be run from the command line or bound to keys.
+    commands: Rc<Registry<Program>>,
+    /// The actions that key sequences are bound to in each mode.
+    keymap: Keymap,
+    /// Keys typed so far that may be the start of a longer key sequence.
+    pending_keys: Vec<KeyEvent>,
+    /// The settings from the config file and from `:set`.
+    config: Config,
+    /// The config file, watched so that it can be reloaded when it changes.
+    config_watch: Option<FileWatch>,
+    /// The settings that apply to the program as a whole.
+    settings: Settings,
+    /// The theme everything is drawn with, using only colors the terminal
+    ///   can show.
+    theme: Theme,
+    /// The theme name and color support the theme was loaded for, so that it
+    ///   is loaded again only when they change.
+    theme_source: Option<(String, ColorSupport)>,
+    /// Input, timers and background task messages waiting to be handled.
+    events: EventLoop,
+    /// Whether anything shown on screen may have changed since the last
+    ///   frame was drawn.
+    needs_redraw: bool,
+    /// The text of the performance bar when it was last drawn, so that the
+    ///   bar refresh timer only redraws once the text has changed.
+    performance_text: Option<String>,
+    /// Why input could no longer be read, once it has been lost. The program
+    ///   then stops, keeping its swap files.
+    input_lost: Option<String>,
     running: bool,
 }
 
-impl<'a> Program<'a> {
+impl Program {
     /// Program initialization
-    pub fn new() -> Program<'a> {
-        Program {
+    ///
+    /// # Arguments
+    ///
+    /// * `documents` - The documents to open. An empty document is created if
+    ///   none are provided.
+    pub fn new(documents: Vec<TextDocument>) -> Program {
+        let mut documents: Vec<DocumentHandle> = documents
+            .into_iter()
+            .map(TextDocument::into_handle)
+            .collect();
+        if documents.is_empty() {
+            documents.push(TextDocument::empty().into_handle());
+        }
+
+        let mut editor = Editor::new(Dimensions::default());
+        editor.set_content(documents[0].clone());
+        let mut editors = BTreeMap::new();
+        editors.insert(0, editor);
+
+        let mut program = Program {
             core_data: CoreData::new(),
-            // TODO: Should be a vector of editors
-            editor: Editor::new(Dimensions::default()),
-            bars: Vec::new(),
+            editors,
+            layout: Layout::new(0),
+            active_editor: 0,
+            next_editor_id: 1,
+            screen_size: Dimensions::default(),
+            documents,
+            bars: BarManager::new(),
+            screen: Screen::new(Dimensions::default()),
+            mode: Mode::Normal,
+            prompt: Prompt::Command,
+            command_line: CommandLine::new(),
+            search_line: CommandLine::new(),
+            search: None,
+            highlight_search: false,
+            search_origin: None,
+            last_substitute: None,
+            confirming: None,
+            commands: Rc::new(commands::registry()),
+            keymap: Keymap::new(),
+            pending_keys: Vec::new(),
+            config: Config::new(),
+            config_watch: None,
+            settings: Settings::default(),
+            theme: Theme::new(),
+            theme_source: None,
+            events: EventLoop::new(),
+            needs_redraw: true,
+            performance_text: None,
+            input_lost: None,
             running: false,
-        }
+        };
+        program.create_bars();
+        program.apply_settings();
+        program
     }
 
     /// Runs the program
-    pub fn run<W>(&mut self, w: &mut W) -> Result<()>
+    ///
+    /// # Arguments
+    ///
+    /// * `backend` - The terminal to run in.
+    pub fn run<B>(&mut self, backend: &mut B) -> Result<()>
     where
-        W: Write
+        B: Backend
     {
-        execute!(w, terminal::EnterAlternateScreen)?;
-
-        terminal::enable_raw_mode()?;
+        self.start(backend)?;
+        while self.running {
+            self.step(backend)?;
+        }
+        self.stop(backend)
+    }
 
+    /// Takes over the terminal, and lays out editors to fit it.
+    fn start<B>(&mut self, backend: &mut B) -> Result<()>
+    where
+        B: Backend
+    {
+        backend.enter()?;
+        backend.start_input(self.sender())?;
         self.running = true;
 
-        // Create bars
-        self.create_bars();
+        // Keep the uptime and frame rate in the bars ticking over
+        self.events.set_repeating_timer(TimerKind::BarRefresh, Duration::from_secs(1));
+
+        // Keep a copy of unsaved changes, in case the connection drops. How
+        //  often the copy is made is a setting, so its timer is set along with
+        //  the other settings.
+        self.start_swap_files();
+
+        if self.config_watch.is_some() {
+            self.events.set_repeating_timer(TimerKind::ConfigCheck, Duration::from_secs(2));
+        }
+
+        let size = backend.size()?;
+        self.handle_resize(size.columns, size.rows);
+        self.render(backend)
+    }
+
+    /// Sleeps until something happens, handles it along with anything else
+    ///   that has happened meanwhile, then draws a frame if needed.
+    fn step<B>(&mut self, backend: &mut B) -> Result<()>
+    where
+        B: Backend
+    {
+        let idle_start = Instant::now();
+        let event = self.events.wait();
+        self.core_data.record_idle(idle_start.elapsed());
+        self.handle_event(event);
 
-        // Render current screen
-        home_screen::render(w);
+        // Handle bursts of input, such as pasted text, before drawing
+        self.handle_pending_events();
 
+        self.render(backend)
+    }
+
+    /// Gets a sender through which background tasks can wake the program,
+    ///   such as to show a message once they finish.
+    pub fn sender(&self) -> Sender<LoopEvent> {
+        self.events.sender()
+    }
+
+    /// Handles everything that has already happened, without waiting.
+    fn handle_pending_events(&mut self) {
         while self.running {
-            // Check for user input
-            self.check_input();
-
-            // Render bars
-            self.render_bars(w);
-
-            queue!(
-                w,
-                cursor::MoveTo(self.editor.cursor_location.column_ix, self.editor.cursor_location.row_ix)
-            )?;
-
-            // Flush render queue
-            w.flush()?;
-
-            // Tick
-            self.core_data.tick();
-
-            // Update cursor location
-            self.core_data.update_cursor_location(&self.editor.cursor_location);
-        }
-        execute!(
-            w,
-            style::ResetColor,
-            cursor::Show,
-            terminal::LeaveAlternateScreen
-        )?;
-
-        terminal::disable_raw_mode()
-    }
-
-    /// Checks for user input
-    fn check_input(&mut self) {
-        if poll(Duration::from_millis(17)).unwrap() {
-            match read().unwrap() {
-                Event::Key(event) => {
-                    if event == KeyCode::Char('h').into() {
-                        // TODO: Should move cursor within active editor
-                        self.editor.move_cursor_left(1);
-                    }
-                    if event == KeyCode::Char('j').into() {
-                        // TODO: Should move cursor within active editor
-                        self.editor.move_cursor_down(1);
-                    }
-                    if event == KeyCode::Char('k').into() {
-                        // TODO: Should move cursor within active editor
-                        self.editor.move_cursor_up(1);
-                    }
-                    if event == KeyCode::Char('l').into() {
-                        // TODO: Should move cursor within active editor
-                        self.editor.move_cursor_right(1);
-                    }
-                    if event == KeyCode::Char('q').into() {
-                        self.running = false;
-                    }
+            match self.events.try_next() {
+                Some(event) => self.handle_event(event),
+                None => break,
+            }
+        }
+    }
+
+    /// Removes swap files, and hands the terminal back.
+    ///
+    /// If input was lost, the swap files are brought up to date and kept
+    ///   instead, since nobody is left to save the changes, and an error is
+    ///   returned.
+    fn stop<B>(&mut self, backend: &mut B) -> Result<()>
+    where
+        B: Backend
+    {
+        if let Some(reason) = self.input_lost.take() {
+            self.write_swap_files();
+            backend.leave()?;
+            let error = std::io::Error::other(format!("cannot read input: {}", reason));
+            return Err(crossterm::ErrorKind::IoError(error));
+        }
+        for document in &self.documents {
+            if let Err(e) = document.borrow_mut().stop_swap() {
+                self.core_data.set_message(format!("Cannot remove swap file: {}", e));
+            }
+        }
+        backend.leave()
+    }
+
+    /// Handles input, a timer or a message from a background task.
+    fn handle_event(&mut self, event: LoopEvent) {
+        match event {
+            LoopEvent::Input(Event::Key(event)) => self.handle_key(event),
+            LoopEvent::Input(Event::Mouse(_)) => return,
+            LoopEvent::Input(Event::Resize(width, height)) =>
+                self.handle_resize(width, height),
+            LoopEvent::Timer(TimerKind::BarRefresh) => {
+                // Only the performance bar shows anything that changes by
+                //  itself, so there is nothing to redraw while it is hidden
+                self.core_data.tick();
+                let text = self.bars.render("performance", &self.core_data);
+                if text.is_none() || text == self.performance_text {
+                    return;
+                }
+            }
+            LoopEvent::Timer(TimerKind::KeyTimeout) => self.resolve_keys(true),
+            LoopEvent::Timer(TimerKind::ConfigCheck) => {
+                if !self.config_watch.as_mut().is_some_and(FileWatch::changed) {
+                    return;
+                }
+                let message = match self.reload_config() {
+                    Ok(()) => "Config reloaded".to_string(),
+                    Err(e) => e,
+                };
+                self.core_data.set_message(message);
+            }
+            LoopEvent::Timer(TimerKind::SwapWrite) => {
+                self.write_swap_files();
+                // Writing swap files changes nothing on screen unless it fails
+                if self.core_data.message().is_none() {
+                    return;
+                }
+            }
+            LoopEvent::Message(message) => self.core_data.set_message(message),
+            LoopEvent::InputLost(reason) => {
+                self.input_lost = Some(reason);
+                self.running = false;
+                return;
+            }
+        }
+        // Editors sharing a document changed through another editor may have
+        //  their cursor or scrolling past its end
+        for editor in self.editors.values_mut() {
+            editor.fit_to_document();
+        }
+        self.needs_redraw = true;
+    }
+
+    /// Clears the terminal and draws every cell again on the next frame.
+    pub fn redraw(&mut self) {
+        self.screen.invalidate();
+        self.needs_redraw = true;
+    }
+
+    /// Handles a key press.
+    fn handle_key(&mut self, event: KeyEvent) {
+        // Messages only last until the next key press
+        self.core_data.clear_message();
+
+        self.pending_keys.push(keymap::normalize(event));
+        self.resolve_keys(false);
+    }
+
+    /// Turns the keys typed so far into actions, leaving any that may be the
+    ///   start of a longer key sequence pending until more keys are typed or
+    ///   the timeout passes.
+    ///
+    /// # Arguments
+    ///
+    /// * `timed_out` - Whether to stop waiting for more keys.
+    fn resolve_keys(&mut self, timed_out: bool) {
+        self.events.cancel_timer(TimerKind::KeyTimeout);
+        while !self.pending_keys.is_empty() {
+            // Looked up afresh each time, as an action may change the mode
+            match self.keymap.resolve(self.mode, &self.pending_keys, timed_out) {
+                Resolution::Pending => {
+                    self.events.set_timer(TimerKind::KeyTimeout, self.keymap.timeout());
+                    return;
+                }
+                Resolution::Bound(action, len) => {
+                    self.pending_keys.drain(..len);
+                    self.perform(action);
+                }
+                Resolution::Unbound => {
+                    let event = self.pending_keys.remove(0);
+                    self.handle_unbound_key(event);
+                }
+            }
+        }
+    }
+
+    /// Handles a key that isn't bound to anything, which types a character
+    ///   in insert mode and on the command line.
+    fn handle_unbound_key(&mut self, event: KeyEvent) {
+        if self.mode == Mode::Confirm {
+            self.confirm_replacement(event);
+            return;
+        }
+        let c = match event.code {
+            KeyCode::Char(c) if !event.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => c,
+            _ => return,
+        };
+        match self.mode {
+            Mode::Normal | Mode::Confirm => {}
+            Mode::Insert => self.editor_mut().insert_text(c.encode_utf8(&mut [0; 4])),
+            Mode::Command => {
+                self.line_mut().insert(c);
+                self.update_search();
+            }
+        }
+    }
+
+    /// Performs an action bound to a key sequence.
+    fn perform(&mut self, action: Action) {
+        if self.mode == Mode::Command {
+            self.perform_on_command_line(action);
+        } else {
+            self.perform_in_editor(action);
+        }
+    }
+
+    /// Performs an action in the active editor. Actions that only apply to
+    ///   the command line do nothing.
+    fn perform_in_editor(&mut self, action: Action) {
+        match action {
+            Action::MoveLeft => self.editor_mut().move_cursor_left(1),
+            Action::MoveDown => self.editor_mut().move_cursor_down(1),
+            Action::MoveUp => self.editor_mut().move_cursor_up(1),
+            Action::MoveRight => self.editor_mut().move_cursor_right(1),
+            Action::ScreenLineDown => self.editor_mut().move_cursor_down_screen_lines(1),
+            Action::ScreenLineUp => self.editor_mut().move_cursor_up_screen_lines(1),
+            Action::LineStart => {
+                let editor = self.editor_mut();
+                let (line_ix, _) = editor.cursor_position();
+                editor.set_cursor_position(line_ix, 0);
+            },
+            Action::LineEnd => {
+                let editor = self.editor_mut();
+                let (line_ix, _) = editor.cursor_position();
+                editor.set_cursor_position(line_ix, editor.line_len(line_ix));
+            },
+            Action::PageDown => self.editor_mut().page_down(),
+            Action::PageUp => self.editor_mut().page_up(),
+            Action::HalfPageDown => self.editor_mut().half_page_down(),
+            Action::HalfPageUp => self.editor_mut().half_page_up(),
+            Action::GoToTop => self.editor_mut().go_to_top(),
+            Action::GoToBottom => self.editor_mut().go_to_bottom(),
+            Action::InsertMode => self.set_mode(Mode::Insert),
+            Action::Append => {
+                // Append after the character under the cursor
+                self.set_mode(Mode::Insert);
+                self.editor_mut().move_cursor_right(1);
+            },
+            Action::OpenLineBelow => {
+                self.set_mode(Mode::Insert);
+                let editor = self.editor_mut();
+                let (line_ix, _) = editor.cursor_position();
+                editor.set_cursor_position(line_ix, editor.line_len(line_ix));
+                editor.insert_newline();
+            },
+            Action::NormalMode => self.set_mode(Mode::Normal),
+            Action::InsertNewline => self.editor_mut().insert_newline(),
+            Action::InsertTab => self.editor_mut().insert_text("\t"),
+            Action::DeleteBackward => self.editor_mut().delete_backward(),
+            Action::DeleteForward => self.editor_mut().delete_forward(),
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::CommandLine => self.open_command_line(Prompt::Command),
+            Action::SearchForward => self.open_search(SearchDirection::Forward),
+            Action::SearchBackward => self.open_search(SearchDirection::Backward),
+            Action::NextMatch => self.repeat_search(false),
+            Action::PreviousMatch => self.repeat_search(true),
+            Action::FocusLeft => self.focus_neighbor(Direction::Left),
+            Action::FocusDown => self.focus_neighbor(Direction::Down),
+            Action::FocusUp => self.focus_neighbor(Direction::Up),
+            Action::FocusRight => self.focus_neighbor(Direction::Right),
+            Action::FocusNext => self.focus_next(),
+            Action::Command(line) => self.run_command(&line),
+            Action::DeleteWordBackward
+            | Action::DeleteToLineStart
+            | Action::ExecuteCommand
+            | Action::Complete
+            | Action::HistoryPrevious
+            | Action::HistoryNext => {}
+        }
+    }
+
+    /// Performs an action on the command line being typed. Actions that only
+    ///   apply to editors do nothing.
+    fn perform_on_command_line(&mut self, action: Action) {
+        match action {
+            Action::NormalMode => self.cancel_command_line(),
+            Action::ExecuteCommand => self.submit_command_line(),
+            Action::DeleteBackward => {
+                // Deleting past the start of the line gives up on the command
+                if self.line().text().is_empty() {
+                    self.cancel_command_line();
+                } else {
+                    self.line_mut().delete_backward();
+                }
+            },
+            Action::DeleteForward => self.line_mut().delete_forward(),
+            Action::DeleteWordBackward => self.line_mut().delete_word_backward(),
+            Action::DeleteToLineStart => self.line_mut().delete_to_start(),
+            Action::MoveLeft => self.line_mut().move_left(),
+            Action::MoveRight => self.line_mut().move_right(),
+            Action::LineStart => self.line_mut().move_to_start(),
+            Action::LineEnd => self.line_mut().move_to_end(),
+            Action::HistoryPrevious => self.line_mut().history_previous(),
+            Action::HistoryNext => self.line_mut().history_next(),
+            Action::Complete if self.prompt == Prompt::Command => {
+                let commands = self.commands.clone();
+                self.command_line.complete(|line| commands.complete(line));
+            },
+            Action::Command(line) => self.run_command(&line),
+            _ => {}
+        }
+        self.update_search();
+    }
+
+    /// Applies the overrides in a keymap file, showing any problem with it as
+    ///   a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the keymap file.
+    pub fn load_keymap(&mut self, path: &Path) {
+        if let Err(e) = self.keymap.load(path) {
+            self.core_data.set_message(format!("Keymap: {}", e));
+        }
+    }
+
+    /// Starts typing on the command line.
+    ///
+    /// # Arguments
+    ///
+    /// * `prompt` - What the command line is for.
+    fn open_command_line(&mut self, prompt: Prompt) {
+        self.prompt = prompt;
+        self.line_mut().clear();
+        self.set_mode(Mode::Command);
+        self.bars.set_visible("command", true);
+        self.layout_editors();
+    }
+
+    /// Stops typing on the command line, returning to normal mode.
+    fn close_command_line(&mut self) {
+        self.set_mode(Mode::Normal);
+        self.bars.set_visible("command", false);
+        self.layout_editors();
+    }
+
+    /// Runs the command, or finishes the search, typed on the command line.
+    fn submit_command_line(&mut self) {
+        let line = self.line_mut().submit();
+        self.close_command_line();
+        match self.prompt {
+            Prompt::Command => self.run_command(&line),
+            Prompt::Search(direction) => self.finish_search(&line, direction),
+        }
+    }
+
+    /// Gives up on the command, or the search, typed on the command line.
+    fn cancel_command_line(&mut self) {
+        if let Prompt::Search(_) = self.prompt {
+            self.cancel_search();
+        }
+        self.close_command_line();
+    }
+
+    /// Gets the line being typed on the command line.
+    fn line(&self) -> &CommandLine {
+        match self.prompt {
+            Prompt::Command => &self.command_line,
+            Prompt::Search(_) => &self.search_line,
+        }
+    }
+
+    /// Gets the line being typed on the command line, for editing.
+    fn line_mut(&mut self) -> &mut CommandLine {
+        match self.prompt {
+            Prompt::Command => &mut self.command_line,
+            Prompt::Search(_) => &mut self.search_line,
+        }
+    }
+
+    /// Runs a command line, showing any error as a message.
+    ///
+    /// A range of lines may come before `:s`. A range on its own, such as a
+    ///   bare line number, moves the cursor to its last line.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The command line, without the leading `:`.
+    pub fn run_command(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        let result = if line.chars().all(|c| c.is_ascii_digit()) {
+            self.commands.clone().run(self, &format!("goto {}", line))
+        } else {
+            match command::parse_range(line) {
+                Ok((range, rest)) => match (range, crate::substitute::strip_name(rest.trim_start())) {
+                    (range, Some(text)) => self.substitute(range, text),
+                    (Some(range), None) if rest.trim().is_empty() => self.go_to_range_end(range),
+                    (Some(_), None) => Err("No range allowed".to_string()),
+                    (None, None) => self.commands.clone().run(self, line),
                 },
-                Event::Mouse(_) => {},
-                Event::Resize(width, height) =>
-                    self.handle_resize(width, height)
+                Err(message) => Err(message),
+            }
+        };
+        if let Err(message) = result {
+            self.core_data.set_message(message);
+        }
+    }
+
+    /// Moves the cursor to the last line of a range.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The range of lines.
+    fn go_to_range_end(&mut self, range: LineRange) -> std::result::Result<(), String> {
+        let line_ix = self.editor().cursor_position().0;
+        let line_count = self.active_document().map_or(1, |document| document.borrow().line_count());
+        let lines = range.resolve(line_ix, line_count)?;
+        self.editor_mut().go_to_line(lines.end - 1);
+        Ok(())
+    }
+
+    /// Switches the input mode.
+    fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+        self.core_data.update_mode(mode);
+
+        let editor = self.editor_mut();
+
+        // Everything typed in one visit to insert mode is undone in one step
+        if mode == Mode::Insert {
+            editor.begin_edit_group();
+        } else {
+            editor.end_edit_group();
+        }
+
+        // Only insert mode lets the cursor sit after the end of a line
+        editor.cursor_past_end = mode == Mode::Insert;
+        editor.clamp_cursor();
+    }
+
+    /// Undoes the most recent change in the active editor.
+    fn undo(&mut self) {
+        if !self.editor_mut().undo() {
+            self.core_data.set_message("Already at oldest change".to_string());
+        }
+    }
+
+    /// Redoes the most recently undone change in the active editor.
+    fn redo(&mut self) {
+        if !self.editor_mut().redo() {
+            self.core_data.set_message("Already at newest change".to_string());
+        }
+    }
+
+    /// Undoes every change made in the active editor within a recent period,
+    ///   for recovering from a bad editing session.
+    ///
+    /// # Arguments
+    ///
+    /// * `period` - How far back in time to go.
+    pub fn undo_period(&mut self, period: Duration) -> std::result::Result<(), String> {
+        if self.editor_mut().undo_period(period) {
+            Ok(())
+        } else {
+            Err(format!("No changes in the last {} seconds", period.as_secs()))
+        }
+    }
+
+    /// Saves the document in the active editor.
+    pub fn save(&mut self) -> std::result::Result<(), String> {
+        let document = self.active_document().ok_or("No document to save")?;
+        let mut document = document.borrow_mut();
+        document.save().map_err(|e| format!("Save failed: {}", e))?;
+        self.core_data.set_message(format!("Saved {}", document.path().unwrap().display()));
+        Ok(())
+    }
+
+    /// Saves the document in the active editor to a new file.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file to save to.
+    pub fn save_as(&mut self, path: &Path) -> std::result::Result<(), String> {
+        let document = self.active_document().ok_or("No document to save")?;
+        document.borrow_mut().save_as(path).map_err(|e| format!("Save failed: {}", e))?;
+        self.core_data.set_message(format!("Saved {}", path.display()));
+        self.apply_settings();
+        Ok(())
+    }
+
+    /// Opens a file in the active editor. A file that is already open is
+    ///   shown rather than opened again.
+    ///
+    /// Refuses to replace a document with unsaved changes that no other
+    ///   editor shows, unless they are discarded. Refuses to open a file that
+    ///   another instance is editing, or that has unsaved changes waiting in a
+    ///   swap file, unless the swap file is ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file to open.
+    /// * `discard` - Whether to discard the unsaved changes of the document
+    ///   being replaced.
+    /// * `ignore_swap` - Whether to ignore any swap file.
+    pub fn edit_file(&mut self, path: &Path, discard: bool, ignore_swap: bool) -> std::result::Result<(), String> {
+        let open = self.documents
+            .iter()
+            .find(|document| document.borrow().path() == Some(path))
+            .cloned();
+        let current = self.editor().content.clone();
+        let replaced = match (&current, &open) {
+            (Some(current), Some(open)) if Rc::ptr_eq(current, open) => None,
+            (Some(current), _) if !self.shown_elsewhere(current) => Some(current.clone()),
+            _ => None,
+        };
+        if let Some(replaced) = &replaced {
+            if replaced.borrow().is_dirty() && !discard {
+                return Err("No write since last change; :w first or :e! to discard".to_string());
+            }
+        }
+
+        let document = match open {
+            Some(document) => document,
+            None => {
+                match swap::inspect(path) {
+                    Ok(SwapStatus::Locked(info)) if !ignore_swap => return Err(format!(
+                        "{} is being edited by another instance (pid {}); use :editanyway to edit it anyway",
+                        path.display(), info.pid
+                    )),
+                    Ok(SwapStatus::Recoverable(info)) if !ignore_swap => return Err(format!(
+                        "Unsaved changes to {} are in {}; restart on the file to recover them, or use :editanyway to ignore them",
+                        path.display(), info.swap_path.display()
+                    )),
+                    _ => {}
+                }
+                let mut document = TextDocument::open(path)
+                    .map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+                if let Err(e) = document.start_swap() {
+                    self.core_data.set_message(format!("Cannot create swap file: {}", e));
+                }
+                let document = document.into_handle();
+                self.documents.push(document.clone());
+                document
+            }
+        };
+
+        // Discarded changes are dropped along with their document, so that
+        //  they don't hold up quitting later
+        if let Some(replaced) = replaced.filter(|replaced| replaced.borrow().is_dirty()) {
+            let _ = replaced.borrow_mut().stop_swap();
+            self.documents.retain(|document| !Rc::ptr_eq(document, &replaced));
+        }
+
+        let editor = self.editor_mut();
+        editor.set_content(document);
+        editor.set_cursor_position(0, 0);
+
+        // The file type may call for different settings
+        self.apply_settings();
+        Ok(())
+    }
+
+    /// Changes or shows a setting, written as on the `:set` command line:
+    ///   `name=value` sets a value, `name` turns a switch on (or shows a
+    ///   value), `noname` turns a switch off, `name!` flips a switch, and
+    ///   `name?` shows the setting.
+    ///
+    /// Changed settings apply to every editor, over any from the config file.
+    ///
+    /// # Arguments
+    ///
+    /// * `setting` - The setting to change or show.
+    pub fn set_option(&mut self, setting: &str) -> std::result::Result<(), String> {
+        let (name, text) = match setting.split_once('=') {
+            Some((name, text)) => (name, Some(text)),
+            None => (setting, None),
+        };
+        let (name, query) = match name.strip_suffix('?') {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+        let (name, invert) = match name.strip_suffix('!') {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+
+        // Line endings belong to the document rather than being a setting
+        if name == "lineending" || name == "le" {
+            return self.set_line_ending_option(text.filter(|_| !query));
+        }
+
+        let (key, switch) = match Setting::from_name(name) {
+            Some(key) => (key, None),
+            None => match name.strip_prefix("no").and_then(Setting::from_name) {
+                Some(key) if key.kind() == Kind::Switch => (key, Some(false)),
+                _ => return Err(format!("Unknown setting: {}", setting)),
+            },
+        };
+
+        let filetype = self.active_document().and_then(|document| document.borrow().filetype());
+        let value = match (text, switch) {
+            _ if query => None,
+            (None, None) if invert && key.kind() == Kind::Switch => match self.config.settings(filetype).get(key) {
+                Value::Switch(on) => Some(Value::Switch(!on)),
+                _ => None,
+            },
+            _ if invert => return Err(format!("Invalid setting: {}", setting)),
+            (Some(text), None) => Some(Value::parse(key.kind(), text)?),
+            (None, Some(switch)) => Some(Value::Switch(switch)),
+            (None, None) if key.kind() == Kind::Switch => Some(Value::Switch(true)),
+            (None, None) => None,
+            (Some(_), Some(_)) => return Err(format!("Invalid setting: {}", setting)),
+        };
+        match value {
+            Some(value) => {
+                self.config.set(key, value);
+                self.apply_settings();
+            }
+            None => {
+                let value = self.config.settings(filetype).get(key);
+                self.core_data.set_message(key.describe(value));
+            }
+        }
+        Ok(())
+    }
+
+    /// Converts the line endings of the document in the active editor, or
+    ///   shows them.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the line ending to convert to, or None to show
+    ///   the current line ending.
+    fn set_line_ending_option(&mut self, name: Option<&str>) -> std::result::Result<(), String> {
+        match name {
+            Some(name) => {
+                let line_ending = LineEnding::from_name(name)
+                    .ok_or_else(|| format!("Unknown line ending: {}", name))?;
+                self.set_line_ending(line_ending);
+            }
+            None => {
+                let document = self.active_document().ok_or("No document")?;
+                let name = document.borrow().line_ending().name();
+                self.core_data.set_message(format!("lineending={}", name));
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts reading settings from a config file, which is reloaded whenever
+    ///   it changes. Any problem with the file is shown as a message.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the config file.
+    pub fn load_config(&mut self, path: &Path) {
+        self.config_watch = Some(FileWatch::new(path));
+        if let Err(e) = self.reload_config() {
+            self.core_data.set_message(e);
+        }
+    }
+
+    /// Reads the config file again, along with the theme, and applies their
+    ///   settings. Valid settings are applied even if the file has problems.
+    fn reload_config(&mut self) -> std::result::Result<(), String> {
+        let result = match &self.config_watch {
+            Some(watch) => self.config.load(watch.path()).map_err(|e| format!("Config: {}", e)),
+            None => Ok(()),
+        };
+        self.theme_source = None;
+        self.apply_settings();
+        result
+    }
+
+    /// Loads the theme named in the settings, if it isn't already loaded,
+    ///   showing any problem with it as a message.
+    fn load_theme(&mut self) {
+        let support = self.settings.colors.unwrap_or_else(ColorSupport::detect);
+        let source = (self.settings.theme.clone(), support);
+        if self.theme_source.as_ref() == Some(&source) {
+            return;
+        }
+        let dir = self.config_watch
+            .as_ref()
+            .and_then(|watch| watch.path().parent())
+            .map(|dir| dir.join("themes"));
+        let mut theme = Theme::new();
+        if let Err(e) = theme.load(&source.0, dir.as_deref()) {
+            self.core_data.set_message(e);
+        }
+        self.theme = theme.downgraded(support);
+        self.theme_source = Some(source);
+    }
+
+    /// Applies the current settings throughout the program, with each editor
+    ///   getting the settings for the type of file it displays.
+    fn apply_settings(&mut self) {
+        self.settings = self.config.settings(None);
+        self.load_theme();
+
+        self.bars.set_visible("status", self.settings.status_bar);
+        self.bars.set_visible("performance", self.settings.performance_bar);
+
+        if self.settings.swap_interval.is_zero() {
+            self.events.cancel_timer(TimerKind::SwapWrite);
+        } else {
+            self.events.set_repeating_timer(TimerKind::SwapWrite, self.settings.swap_interval);
+        }
+
+        for editor in self.editors.values_mut() {
+            let filetype = editor.content.as_ref().and_then(|document| document.borrow().filetype());
+            let settings = self.config.settings(filetype);
+            editor.tab_width = settings.tab_width;
+            editor.scroll_off = settings.scroll_off;
+            editor.set_line_numbers(settings.line_numbers);
+            editor.set_wrap(settings.wrap, &settings.wrap_indicator);
+        }
+        self.layout_editors();
+    }
+
+    /// Converts the line endings of the document in the active editor.
+    ///
+    /// # Arguments
+    ///
+    /// * `line_ending` - The line ending to convert to.
+    pub fn set_line_ending(&mut self, line_ending: LineEnding) {
+        if let Some(document) = self.active_document() {
+            document.borrow_mut().set_line_ending(line_ending);
+            self.core_data.set_message(format!("Line endings set to {}", line_ending.name()));
+        }
+    }
+
+    /// Quits the program, unless there are documents with unsaved changes.
+    ///
+    /// # Arguments
+    ///
+    /// * `force` - Whether to quit even if there are unsaved changes.
+    fn quit(&mut self, force: bool) -> std::result::Result<(), String> {
+        let num_dirty = self.documents
+            .iter()
+            .filter(|document| document.borrow().is_dirty())
+            .count();
+        if num_dirty == 0 || force {
+            self.running = false;
+            Ok(())
+        } else {
+            Err(format!(
+                "{} buffer(s) have unsaved changes (use :qa! to quit anyway)",
+                num_dirty
+            ))
+        }
+    }
+
+    /// Creates swap files for every open document that has a path.
+    fn start_swap_files(&mut self) {
+        for document in &self.documents {
+            let mut document = document.borrow_mut();
+            if let Err(e) = document.start_swap() {
+                let name = document.path().map(|path| path.display().to_string()).unwrap_or_default();
+                self.core_data.set_message(format!("Cannot create swap file for {}: {}", name, e));
+            }
+        }
+    }
+
+    /// Copies the unsaved changes of every open document to its swap file.
+    fn write_swap_files(&mut self) {
+        for document in &self.documents {
+            if let Err(e) = document.borrow_mut().write_swap() {
+                self.core_data.set_message(format!("Cannot write swap file: {}", e));
+            }
+        }
+    }
+
+    /// Writes every document with unsaved changes to a recovery file, and
+    ///   reports where on stderr. Used after a crash.
+    pub fn write_emergency_dump(&self) {
+        let path = recovery::emergency_dump_path();
+        match recovery::write_emergency_dump(&self.documents, &path) {
+            Ok(0) => {}
+            Ok(num_written) => eprintln!(
+                "stated: unsaved changes to {} buffer(s) written to {}",
+                num_written, path.display()
+            ),
+            Err(e) => eprintln!(
+                "stated: cannot write unsaved changes to {}: {}", path.display(), e
+            ),
+        }
+    }
+
+    /// Copies the state of the active editor into the core data, for display
+    ///   in bars.
+    fn update_core_data(&mut self) {
+        let command_line = match self.mode {
+            Mode::Command => Some(format!("{}{}", self.prompt.symbol(), self.line().text())),
+            _ => None,
+        };
+        self.core_data.update_command_line(command_line.as_deref());
+
+        let search_matches = match (self.active_document(), self.editor().cursor_char_ix()) {
+            (Some(document), Some(char_ix)) => self.highlighted_search()
+                .map(|search| search.position(&document, char_ix)),
+            _ => None,
+        };
+        self.core_data.update_search_matches(search_matches);
+
+        let editor = &self.editors[&self.active_editor];
+        self.core_data.update_cursor_location(&editor.cursor_location);
+        self.core_data.update_dimensions(&editor.dimensions);
+
+        if let Some(document) = &editor.content {
+            let document = document.borrow();
+            self.core_data.update_file_path(document.path());
+            self.core_data.update_modified(document.is_dirty());
+            self.core_data.update_line_ending(
+                document.line_ending(),
+                document.has_mixed_line_endings()
+            );
+        }
+    }
+
+    /// Gets the active editor.
+    fn editor(&self) -> &Editor {
+        &self.editors[&self.active_editor]
+    }
+
+    /// Gets the active editor for modification.
+    fn editor_mut(&mut self) -> &mut Editor {
+        self.editors.get_mut(&self.active_editor).unwrap()
+    }
+
+    /// Gets the document displayed in the active editor.
+    fn active_document(&self) -> Option<DocumentHandle> {
+        self.editor().content.clone()
+    }
+
+    /// Gets the screen origin of the active editor, and the location of the
+    ///   cursor within it.
+    fn cursor_screen_location(&self) -> (Location, Location) {
+        let origin = self.layout
+            .arrange(self.editor_area())
+            .into_iter()
+            .find(|(id, _)| *id == self.active_editor)
+            .map_or_else(Location::default, |(_, rect)| rect.origin);
+        (origin, self.editor().cursor_location)
+    }
+
+    /// Gets where on the screen the cursor is shown: on the command line while
+    ///   a command is being typed, otherwise in the active editor.
+    fn cursor_screen_position(&self) -> Location {
+        if self.mode == Mode::Command {
+            if let Some(row_ix) = self.bars.row_of("command", &self.screen_size) {
+                let line = self.line();
+                let text: String = line.text().chars().take(line.cursor()).collect();
+                // Allow for the leading ':', '/' or '?'
+                let column_ix = 1 + graphemes::line_width(&text, 1);
+                return Location::new(column_ix.min(u16::MAX as usize) as u16, row_ix);
+            }
+        }
+        let (origin, cursor_location) = self.cursor_screen_location();
+        Location::new(
+            origin.column_ix + cursor_location.column_ix,
+            origin.row_ix + cursor_location.row_ix
+        )
+    }
+
+    /// Splits the active editor, opening a new editor on the same document.
+    ///
+    /// The new editor starts at the same cursor position, and receives focus.
+    ///
+    /// # Arguments
+    ///
+    /// * `direction` - How the active editor's area is divided.
+    pub fn split_editor(&mut self, direction: SplitDirection) {
+        let id = self.next_editor_id;
+        self.next_editor_id += 1;
+
+        let current = self.editor();
+        let mut editor = Editor::new(current.dimensions);
+        if let Some(document) = &current.content {
+            editor.set_content(document.clone());
+        }
+        editor.scroll_off = current.scroll_off;
+        editor.tab_width = current.tab_width;
+        editor.set_line_numbers(current.line_numbers());
+        editor.set_wrap(current.wrap(), current.wrap_indicator());
+        let (line_ix, column_ix) = current.cursor_position();
+        editor.set_cursor_position(line_ix, column_ix);
+
+        self.layout.split(self.active_editor, id, direction);
+        self.editors.insert(id, editor);
+        self.active_editor = id;
+        self.layout_editors();
+    }
+
+    /// Whether an editor other than the active one shows a document.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The document to look for.
+    fn shown_elsewhere(&self, document: &DocumentHandle) -> bool {
+        self.editors.iter().any(|(id, editor)| {
+            *id != self.active_editor
+                && editor.content.as_ref().is_some_and(|content| Rc::ptr_eq(content, document))
+        })
+    }
+
+    /// Closes the active editor, moving focus to the next editor. The last
+    ///   editor can't be closed.
+    pub fn close_editor(&mut self) -> std::result::Result<(), String> {
+        if !self.layout.close(self.active_editor) {
+            return Err("Cannot close the last editor".to_string());
+        }
+        self.editors.remove(&self.active_editor);
+        self.active_editor = self.layout.editor_ids()[0];
+        self.layout_editors();
+        Ok(())
+    }
+
+    /// Moves focus to the editor next to the active editor.
+    ///
+    /// # Arguments
+    ///
+    /// * `direction` - The direction in which to move focus.
+    pub fn focus_neighbor(&mut self, direction: Direction) {
+        if let Some(id) = self.layout.neighbor(self.active_editor, direction, self.editor_area()) {
+            self.active_editor = id;
+        }
+    }
+
+    /// Moves focus to the next editor in layout order, wrapping around.
+    pub fn focus_next(&mut self) {
+        let ids = self.layout.editor_ids();
+        if let Some(ix) = ids.iter().position(|id| *id == self.active_editor) {
+            self.active_editor = ids[(ix + 1) % ids.len()];
+        }
+    }
+
+    /// Gets the area of the screen available to editors.
+    fn editor_area(&self) -> Rect {
+        self.bars.editor_area(&self.screen_size)
+    }
+
+    /// Resizes every editor to fit its area in the layout.
+    fn layout_editors(&mut self) {
+        for (id, rect) in self.layout.arrange(self.editor_area()) {
+            if let Some(editor) = self.editors.get_mut(&id) {
+                editor.resize(rect.dimensions);
             }
         }
     }
 
     /// Handles window resize events
     fn handle_resize(&mut self, width: u16, height: u16) {
-        // TODO: Should resize all editors according to their allocations
-        self.editor.resize(Dimensions::new(width, height));
-        self.core_data.update_dimensions(&self.editor.dimensions);
+        self.screen_size = Dimensions::new(width, height);
+        self.screen.resize(self.screen_size);
+        self.layout_editors();
     }
 
     /// Creates status bars
     fn create_bars(&mut self) {
-        self.bars.push(Box::new(StatusBar::new(1)));
-        self.bars.push(Box::new(PerformanceBar::new(2)));
+        self.bars.add(Box::new(StatusBar::new(1, Dock::Bottom)));
+        self.bars.add(Box::new(PerformanceBar::new(2, Dock::Bottom)));
+        // The command line is shown only while a command is being typed
+        self.bars.add(Box::new(CommandBar::new(3, Dock::Bottom)));
+        self.bars.set_visible("command", false);
     }
 
-    /// Renders status bars
-    fn render_bars<W>(&mut self, w: &mut W)
+    /// Draws a frame, and sends the cells that changed to the terminal.
+    fn render<B>(&mut self, backend: &mut B) -> Result<()>
     where
-        W: Write
+        B: Backend
     {
-        // TODO: Render bars at bottom, according to their priority
-        let mut bar_row = 22; // TODO: <-- This should not be hard-coded
-        for bar in &self.bars {
-            queue!(
-                w,
-                cursor::MoveTo(0, bar_row),
-                style::SetForegroundColor(style::Color::White),
-                style::SetBackgroundColor(style::Color::Black),
-                style::Print(bar.render(&self.core_data)),
-            ).unwrap();
-            bar_row += 1;
+        if !self.needs_redraw {
+            return Ok(());
+        }
+        self.needs_redraw = false;
+        let render_start = Instant::now();
+
+        // Bring the information displayed by bars up to date
+        self.core_data.tick();
+        self.update_core_data();
+
+        self.screen.clear();
+        self.draw_editors();
+        self.draw_bars();
+
+        let changes = self.screen.changes();
+        let drawn = self.screen.needs_clear() || !changes.is_empty();
+        if drawn {
+            backend.draw(self.screen.needs_clear(), &changes)?;
+            self.screen.present();
+        }
+
+        backend.set_cursor(self.cursor_screen_position())?;
+        backend.flush()?;
+
+        if drawn {
+            self.core_data.record_frame(render_start.elapsed());
+        }
+        Ok(())
+    }
+
+    /// Draws the text of each editor, and the separators between them.
+    fn draw_editors(&mut self) {
+        let area = self.editor_area();
+        let text_style = self.theme.style("text");
+        let cursor_line_style = self.theme.style("cursor-line");
+        let match_style = self.theme.style("search-match");
+        let current_style = self.theme.style("search-current");
+        let number_style = self.theme.style("line-number");
+        let current_number_style = self.theme.style("line-number-current");
+        let show_matches = self.highlighted_search().is_some();
+        for (id, rect) in self.layout.arrange(area) {
+            let editor = &self.editors[&id];
+            self.screen.fill(rect, " ", text_style);
+
+            let cursor_rows = editor.cursor_rows();
+            for (row_ix, label) in editor.get_gutter_content().iter().enumerate() {
+                let style = if cursor_rows.contains(&(row_ix as u16)) { current_number_style } else { number_style };
+                let row_ix = rect.top() + row_ix as u16;
+                self.screen.put_str_clipped(rect.left(), row_ix, label, style, rect.right());
+            }
+
+            // Only the active editor shows where its cursor is
+            let show_cursor_line = self.settings.cursor_line && id == self.active_editor;
+            let text_left = rect.left() + editor.gutter_width();
+            for (row_ix, line) in editor.get_render_content().iter().enumerate() {
+                let style = if show_cursor_line && cursor_rows.contains(&(row_ix as u16)) {
+                    let row = Rect::new(text_left, rect.top() + row_ix as u16, rect.right() - text_left, 1);
+                    self.screen.fill(row, " ", cursor_line_style);
+                    cursor_line_style
+                } else {
+                    text_style
+                };
+                self.screen.put_str_clipped(text_left, rect.top() + row_ix as u16, line, style, rect.right());
+            }
+
+            let spans = if self.settings.syntax { editor.syntax_spans() } else { Vec::new() };
+            for (row_ix, columns, group) in spans {
+                let beneath = if show_cursor_line && cursor_rows.contains(&row_ix) { "cursor-line" } else { "text" };
+                let span = Rect::new(rect.left() + columns.start, rect.top() + row_ix, columns.end - columns.start, 1);
+                self.screen.set_style(span, self.theme.style_over(group, beneath));
+            }
+
+            let spans = match self.search.as_mut().filter(|_| show_matches) {
+                Some(search) => search::match_spans(search, editor),
+                None => Vec::new(),
+            };
+            for (row_ix, columns) in spans {
+                let span = Rect::new(rect.left() + columns.start, rect.top() + row_ix, columns.end - columns.start, 1);
+                self.screen.set_style(span, match_style);
+            }
+
+            let spans = match &self.confirming {
+                Some(substitution) => substitute::confirm_spans(substitution, editor),
+                None => Vec::new(),
+            };
+            for (row_ix, columns) in spans {
+                let span = Rect::new(rect.left() + columns.start, rect.top() + row_ix, columns.end - columns.start, 1);
+                self.screen.set_style(span, current_style);
+            }
+        }
+
+        let separator_style = self.theme.style("separator");
+        for (gap, direction) in self.layout.separators(area) {
+            let symbol = match direction {
+                SplitDirection::Horizontal => "─",
+                SplitDirection::Vertical => "│",
+            };
+            self.screen.fill(gap, symbol, separator_style);
+        }
+
+        if self.showing_home_screen() {
+            home_screen::render(&mut self.screen, area, self.theme.style("title"));
+        }
+    }
+
+    /// Whether the home screen is shown, which it is until a file is opened or
+    ///   some text is typed.
+    fn showing_home_screen(&self) -> bool {
+        if self.editors.len() > 1 {
+            return false;
+        }
+        self.editor().content.as_ref().is_none_or(|document| {
+            let document = document.borrow();
+            document.path().is_none() && document.len_chars() == 0 && !document.is_dirty()
+        })
+    }
+
+    /// Draws the visible bars.
+    fn draw_bars(&mut self) {
+        let active_style = self.theme.style("bar");
+        let inactive_style = self.theme.style("bar-inactive");
+        let width = self.screen_size.columns as usize;
+        for (bar, row_ix) in self.bars.arrange(&self.screen_size) {
+            // While a command is typed, the command line is the only bar in use
+            let style = if self.mode == Mode::Command && bar.name() != "command" {
+                inactive_style
+            } else {
+                active_style
+            };
+
+            // Fill the whole row, so that the bar reads as a solid strip
+            let mut text = bar.render(&self.core_data);
+            if bar.name() == "performance" {
+                self.performance_text = Some(text.clone());
+            }
+            let padding = width.saturating_sub(graphemes::line_width(&text, 1));
+            text.extend(std::iter::repeat_n(' ', padding));
+            self.screen.put_str(0, row_ix, &text, style);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossterm::style;
+    use crate::backend::TestBackend;
+    use crate::scratch::ScratchPath;
+    use crate::screen::Style;
+
+    /// Starts a program, feeds it the backend's scripted events, then draws a
+    ///   frame.
+    fn run_script(program: &mut Program, backend: &mut TestBackend) {
+        program.start(backend).unwrap();
+        settle(program, backend);
+    }
+
+    /// Feeds a running program any events pushed since it started, then
+    ///   draws a frame.
+    fn settle(program: &mut Program, backend: &mut TestBackend) {
+        program.handle_pending_events();
+        program.render(backend).unwrap();
+    }
+
+    /// Typed text is drawn in the editor, with the bars docked below it.
+    #[test]
+    fn draws_typed_text() {
+        let mut program = Program::new(Vec::new());
+        let mut backend = TestBackend::new(80, 10);
+        backend.push_keys("ihello\nworld");
+        backend.push_key(KeyCode::Esc);
+        run_script(&mut program, &mut backend);
+
+        assert_eq!(backend.row_text(0).trim_end(), "hello");
+        assert_eq!(backend.row_text(1).trim_end(), "world");
+        assert_eq!(backend.cursor(), Location::new(4, 1));
+        assert!(backend.row_text(8).starts_with("[Status] [NORMAL] File path: [No Name] [+]"));
+        assert!(backend.row_text(9).starts_with("[Performance]"));
+    }
+
+    /// The home screen is shown until some text is typed.
+    #[test]
+    fn home_screen_until_typing() {
+        let mut program = Program::new(Vec::new());
+        let mut backend = TestBackend::new(40, 10);
+        run_script(&mut program, &mut backend);
+        assert!(backend.row_text(4).contains("Awesome Text Editor"));
+
+        backend.push_keys("ia");
+        settle(&mut program, &mut backend);
+        assert_eq!(backend.row_text(4).trim_end(), "");
+    }
+
+    /// Splitting the editor draws a separator between the halves.
+    #[test]
+    fn draws_split_separator() {
+        let mut program = Program::new(vec![TextDocument::new("abc")]);
+        let mut backend = TestBackend::new(41, 10);
+        backend.push_key_with(KeyCode::Char('w'), KeyModifiers::CONTROL);
+        backend.push_key(KeyCode::Char('v'));
+        run_script(&mut program, &mut backend);
+
+        assert_eq!(backend.row_text(0).replace(' ', ""), "abc│abc");
+        assert_eq!(backend.cell(20, 7).symbol, "│");
+        assert_eq!(backend.cursor(), Location::new(21, 0));
+    }
+
+    /// Resizing the terminal moves the bars to its new bottom edge.
+    #[test]
+    fn resize_moves_bars() {
+        let mut program = Program::new(vec![TextDocument::new("abc")]);
+        let mut backend = TestBackend::new(40, 10);
+        backend.resize(30, 6);
+        run_script(&mut program, &mut backend);
+
+        assert!(backend.row_text(4).starts_with("[Status]"));
+        assert!(backend.row_text(5).starts_with("[Performance]"));
+    }
+
+    /// Toggling a bar off at runtime gives its row to the editors.
+    #[test]
+    fn hidden_bar_is_not_drawn() {
+        let mut program = Program::new(vec![TextDocument::new("abc")]);
+        let mut backend = TestBackend::new(40, 10);
+        backend.push_keys(":set performancebar!\n");
+        run_script(&mut program, &mut backend);
+
+        assert!(backend.row_text(9).starts_with("[Status]"));
+        assert_eq!(program.editor().dimensions, Dimensions::new(40, 9));
+
+        // Toggling again brings it back, and only switches can be toggled
+        backend.push_keys(":set performancebar!\n");
+        settle(&mut program, &mut backend);
+        assert!(backend.row_text(9).starts_with("[Performance]"));
+        assert_eq!(program.editor().dimensions, Dimensions::new(40, 8));
+        assert!(program.set_option("tabwidth!").is_err());
+    }
+
+    /// Ctrl-L clears the terminal and draws the whole frame again.
+    #[test]
+    fn redraws_whole_screen() {
+        let mut program = Program::new(vec![TextDocument::new("abc")]);
+        let mut backend = TestBackend::new(40, 10);
+        run_script(&mut program, &mut backend);
+        assert!(!program.screen.needs_clear());
+
+        backend.push_key_with(KeyCode::Char('l'), KeyModifiers::CONTROL);
+        program.handle_pending_events();
+        assert!(program.screen.needs_clear());
+        program.render(&mut backend).unwrap();
+        assert!(!program.screen.needs_clear());
+        assert_eq!(backend.row_text(0).trim_end(), "abc");
+    }
+
+    /// Messages sent by background tasks are shown in the status bar.
+    #[test]
+    fn shows_background_messages() {
+        let mut program = Program::new(vec![TextDocument::new("abc")]);
+        let mut backend = TestBackend::new(160, 10);
+        run_script(&mut program, &mut backend);
+
+        let sender = program.sender();
+        std::thread::spawn(move || sender.send(LoopEvent::Message("Task done".to_string())).unwrap())
+            .join()
+            .unwrap();
+        settle(&mut program, &mut backend);
+        assert!(backend.row_text(8).contains("[Message]: Task done"));
+    }
+
+    /// Losing input stops the program, keeping up to date swap files for
+    ///   the unsaved changes.
+    #[test]
+    fn stops_when_input_is_lost() {
+        let dir = ScratchPath::new_dir("program-input-lost");
+        let path = dir.join("file.txt");
+        std::fs::write(&path, "abc").unwrap();
+        let mut program = Program::new(vec![TextDocument::open(&path).unwrap()]);
+        let mut backend = TestBackend::new(40, 10);
+        backend.push_keys("ix");
+        run_script(&mut program, &mut backend);
+
+        program.sender().send(LoopEvent::InputLost("terminal gone".to_string())).unwrap();
+        settle(&mut program, &mut backend);
+        assert!(!program.running);
+        assert!(program.stop(&mut backend).is_err());
+        assert!(!backend.is_entered());
+
+        let swap_path = &swap::swap_paths(&path)[0];
+        assert!(std::fs::read_to_string(swap_path).unwrap().ends_with("\n\nxabc"));
+    }
+
+    /// The bar refresh timer only redraws while the performance bar is shown
+    ///   and its text has changed.
+    #[test]
+    fn bar_refresh_redraws_only_changes() {
+        let mut program = Program::new(vec![TextDocument::new("abc")]);
+        let mut backend = TestBackend::new(80, 10);
+        run_script(&mut program, &mut backend);
+
+        program.core_data.record_frame(Duration::from_millis(1));
+        program.handle_event(LoopEvent::Timer(TimerKind::BarRefresh));
+        assert!(program.needs_redraw);
+        program.render(&mut backend).unwrap();
+
+        backend.push_keys(":set performancebar!\n");
+        settle(&mut program, &mut backend);
+        program.core_data.record_frame(Duration::from_millis(1));
+        program.handle_event(LoopEvent::Timer(TimerKind::BarRefresh));
+        assert!(!program.needs_redraw);
+    }
+
+    /// Key sequences run their action once complete, and ambiguous prefixes
+    ///   run theirs once the timeout passes.
+    #[test]
+    fn resolves_key_sequences() {
+        let mut program = Program::new(vec![TextDocument::new("1\n2\n3\n")]);
+        program.keymap.apply_config("timeout = 0\n[insert]\njk = \"normal_mode\"\n").unwrap();
+        let mut backend = TestBackend::new(40, 10);
+        backend.push_keys("Ggg");
+        run_script(&mut program, &mut backend);
+        assert_eq!(program.editor().cursor_position(), (0, 0));
+
+        backend.push_keys("ijk");
+        settle(&mut program, &mut backend);
+        assert_eq!(program.mode, Mode::Normal);
+
+        backend.push_keys("ij");
+        settle(&mut program, &mut backend);
+        assert_eq!(program.mode, Mode::Insert);
+        assert_eq!(backend.row_text(0).trim_end(), "j1");
+    }
+
+    /// Bars and the cursor line are drawn in the theme's colors, and other
+    ///   bars dim while a command is typed.
+    #[test]
+    fn draws_with_theme() {
+        let mut program = Program::new(vec![TextDocument::new("abc\ndef\n")]);
+        program.run_command("set colors=16 cursorline");
+        let mut backend = TestBackend::new(40, 10);
+        backend.push_keys("j");
+        run_script(&mut program, &mut backend);
+
+        assert_eq!(backend.cell(0, 0).style, Style::default());
+        assert_eq!(backend.cell(10, 1).style.background, style::Color::DarkGrey);
+        assert_eq!(backend.cell(0, 8).style, Style::new(style::Color::White, style::Color::Black));
+
+        backend.push_keys(":");
+        settle(&mut program, &mut backend);
+        assert_eq!(backend.cell(0, 7).style.foreground, style::Color::DarkGrey);
+        assert_eq!(backend.cell(0, 9).style.foreground, style::Color::White);
+    }
+
+    /// Line numbers are drawn in a gutter left of the text, with the cursor's
+    ///   line picked out.
+    #[test]
+    fn draws_line_numbers() {
+        let mut program = Program::new(vec![TextDocument::new("abc\ndef\n")]);
+        program.run_command("set colors=16 nu=hybrid");
+        let mut backend = TestBackend::new(40, 10);
+        backend.push_keys("j");
+        run_script(&mut program, &mut backend);
+
+        assert_eq!(backend.row_text(0).trim_end(), "  1 abc");
+        assert_eq!(backend.row_text(1).trim_end(), "  2 def");
+        assert_eq!(backend.row_text(2).trim_end(), "  1");
+        assert_eq!(backend.cell(2, 0).style.foreground, style::Color::DarkGrey);
+        assert_eq!(backend.cell(2, 1).style.foreground, style::Color::Yellow);
+        assert_eq!(program.cursor_screen_position(), Location::new(4, 1));
+    }
+
+    /// Documents are drawn with their syntax highlighted, keeping the cursor
+    ///   line's background, until highlighting is turned off.
+    #[test]
+    fn highlights_syntax() {
+        let mut program = Program::new(vec![TextDocument::new("#!/bin/sh\nexit 1\n")]);
+        program.run_command("set colors=16 cursorline");
+        let mut backend = TestBackend::new(40, 10);
+        run_script(&mut program, &mut backend);
+
+        assert_eq!(backend.cell(0, 0).style.foreground, style::Color::DarkGrey);
+        assert_eq!(backend.cell(0, 0).style.background, style::Color::DarkGrey);
+        assert_eq!(backend.cell(0, 1).style.foreground, style::Color::Magenta);
+        assert_eq!(backend.cell(5, 1).style.foreground, style::Color::Cyan);
+        assert_eq!(backend.cell(4, 1).style.foreground, style::Color::Reset);
+
+        backend.push_keys(":set nosyntax\n");
+        settle(&mut program, &mut backend);
+        assert_eq!(backend.cell(0, 1).style.foreground, style::Color::Reset);
+    }
+
+    /// Long lines wrap once wrapping is set, and gj moves down a screen line.
+    #[test]
+    fn wraps_long_lines() {
+        let mut program = Program::new(vec![TextDocument::new("abcdefgh\nxy\n")]);
+        program.run_command("set wrap=char wrapindicator=+");
+        let mut backend = TestBackend::new(5, 10);
+        backend.push_keys("lgj");
+        run_script(&mut program, &mut backend);
+
+        assert_eq!(backend.row_text(0), "abcde");
+        assert_eq!(backend.row_text(1).trim_end(), "+fgh");
+        assert_eq!(backend.row_text(2).trim_end(), "xy");
+        assert_eq!(program.editor().cursor_position(), (0, 6));
+        assert_eq!(program.cursor_screen_position(), Location::new(2, 1));
+    }
+
+    /// Searching moves to the nearest match as the pattern is typed, and
+    ///   highlights and counts the matches. n and N move between them.
+    #[test]
+    fn searches_incrementally() {
+        let text: String = (0..30).map(|ix| format!("line {}\n", ix)).collect();
+        let mut program = Program::new(vec![TextDocument::new(&text)]);
+        let mut backend = TestBackend::new(120, 10);
+        backend.push_keys("/NE 2");
+        run_script(&mut program, &mut backend);
+        assert_eq!(program.editor().cursor_position(), (0, 0));
+
+        // Lowercase patterns ignore case
+        for _ in 0..4 {
+            backend.push_key(KeyCode::Backspace);
+        }
+        backend.push_keys("ne 2");
+        settle(&mut program, &mut backend);
+        assert_eq!(backend.row_text(9).trim_end(), "/ne 2");
+        assert_eq!(program.editor().cursor_position(), (2, 2));
+        assert_eq!(backend.cell(2, 2).style.background, style::Color::DarkYellow);
+        assert_eq!(backend.cell(1, 2).style.background, style::Color::Reset);
+
+        backend.push_keys("\n");
+        settle(&mut program, &mut backend);
+        assert!(backend.row_text(8).contains("[Search]: match 1 of 11"));
+
+        backend.push_keys("n");
+        settle(&mut program, &mut backend);
+        assert_eq!(program.editor().cursor_position(), (20, 2));
+        assert_eq!(program.editor().cursor_location.row_ix, 4);
+
+        backend.push_keys("NN");
+        settle(&mut program, &mut backend);
+        assert_eq!(program.editor().cursor_position(), (29, 2));
+        assert_eq!(program.core_data.message(), Some("Search hit the top, continuing at the bottom"));
+
+        // Giving up on a search puts back the cursor and the last search
+        backend.push_keys("?line");
+        settle(&mut program, &mut backend);
+        assert_eq!(program.editor().cursor_position(), (29, 0));
+        backend.push_key(KeyCode::Esc);
+        settle(&mut program, &mut backend);
+        assert_eq!(program.editor().cursor_position(), (29, 2));
+        assert!(backend.row_text(8).contains("[Search]: match 11 of 11"));
+
+        backend.push_keys(":noh\n");
+        settle(&mut program, &mut backend);
+        assert!(!backend.row_text(8).contains("[Search]"));
+    }
+
+    /// :s replaces matches in a range of lines as one undo step, and can ask
+    ///   before each replacement.
+    #[test]
+    fn substitutes_in_ranges() {
+        let text = "a = 1\nb = 2\nc = 3\n";
+        let mut program = Program::new(vec![TextDocument::new(text)]);
+        let mut backend = TestBackend::new(120, 10);
+        backend.push_keys(":%s/(\\w) = (\\d)/\\2 = \\1/\n");
+        run_script(&mut program, &mut backend);
+        assert_eq!(program.active_document().unwrap().borrow().get_content(), "1 = a\n2 = b\n3 = c\n");
+        assert_eq!(program.core_data.message(), Some("3 substitutions on 3 lines"));
+        assert_eq!(program.editor().cursor_position(), (2, 0));
+
+        backend.push_keys("u");
+        settle(&mut program, &mut backend);
+        assert_eq!(program.active_document().unwrap().borrow().get_content(), text);
+
+        backend.push_keys(":2,3s/x/y\n");
+        settle(&mut program, &mut backend);
+        assert_eq!(program.core_data.message(), Some("Pattern not found: x"));
+
+        // y replaces, n skips and q stops
+        backend.push_keys(":%s/ = /: /c\n");
+        settle(&mut program, &mut backend);
+        assert_eq!(program.mode, Mode::Confirm);
+        assert_eq!(program.core_data.message(), Some("Replace with :  (y/n/a/q/l)?"));
+        assert_eq!(backend.cell(1, 0).style.background, style::Color::Yellow);
+        backend.push_keys("ynq");
+        settle(&mut program, &mut backend);
+        assert_eq!(program.mode, Mode::Normal);
+        assert_eq!(program.active_document().unwrap().borrow().get_content(), "a: 1\nb = 2\nc = 3\n");
+        assert_eq!(program.core_data.message(), Some("1 substitution on 1 line"));
+
+        backend.push_keys("u");
+        settle(&mut program, &mut backend);
+        assert_eq!(program.active_document().unwrap().borrow().get_content(), text);
+    }
+
+    /// Quitting hands the terminal back.
+    #[test]
+    fn quit_leaves_terminal() {
+        let mut program = Program::new(Vec::new());
+        let mut backend = TestBackend::new(40, 10);
+        backend.push_keys("q");
+        program.run(&mut backend).unwrap();
+
+        assert!(!backend.is_entered());
+    }
+
+    /// The command line is shown while typing, and completes command names.
+    #[test]
+    fn types_on_command_line() {
+        let mut program = Program::new(vec![TextDocument::new("abc")]);
+        let mut backend = TestBackend::new(40, 10);
+        backend.push_keys(":wr");
+        run_script(&mut program, &mut backend);
+
+        assert_eq!(backend.row_text(9).trim_end(), ":wr");
+        assert_eq!(backend.cursor(), Location::new(3, 9));
+
+        backend.push_key(KeyCode::Tab);
+        settle(&mut program, &mut backend);
+        assert_eq!(backend.row_text(9).trim_end(), ":write");
+
+        backend.push_key(KeyCode::Esc);
+        settle(&mut program, &mut backend);
+        assert_eq!(program.mode, Mode::Normal);
+        assert!(backend.row_text(9).starts_with("[Performance]"));
+    }
+
+    /// Commands run when Enter is pressed, and errors are shown as messages.
+    #[test]
+    fn runs_typed_commands() {
+        let mut program = Program::new(vec![TextDocument::new("1\n2\n3\n4\n")]);
+        let mut backend = TestBackend::new(80, 10);
+        backend.push_keys(":set tabwidth=8 scrolloff=2\n:3\n");
+        run_script(&mut program, &mut backend);
+
+        assert_eq!(program.editor().tab_width, 8);
+        assert_eq!(program.editor().scroll_off, 2);
+        assert_eq!(program.editor().cursor_position(), (2, 0));
+
+        backend.push_keys(":bogus\n");
+        settle(&mut program, &mut backend);
+        assert_eq!(program.core_data.message(), Some("Not a command: bogus"));
+    }
+
+    /// Settings come from the config file, with overrides for file types,
+    ///   and `:set` changes outlast reloads of the file.
+    #[test]
+    fn applies_config_settings() {
+        let dir = ScratchPath::new("program-config");
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = dir.join("config.toml");
+        let notes = dir.join("notes.md");
+        std::fs::write(&config, "tabwidth = 8\nperformancebar = false\n[filetype.markdown]\ntabwidth = 2\n").unwrap();
+        std::fs::write(&notes, "# Notes\n").unwrap();
+
+        let mut program = Program::new(vec![TextDocument::new("abc")]);
+        program.load_config(&config);
+        assert_eq!(program.editor().tab_width, 8);
+        assert!(!program.bars.is_visible("performance"));
+
+        program.run_command(&format!("edit {}", notes.display()));
+        assert_eq!(program.editor().tab_width, 2);
+        program.run_command("set ts?");
+        assert_eq!(program.core_data.message(), Some("tabwidth=2"));
+
+        program.run_command("set theme=dusk colors=truecolor so=1");
+        assert_eq!(program.theme.style("bar").background, style::Color::Rgb { r: 0x83, g: 0xa5, b: 0x98 });
+
+        std::fs::write(&config, "statusbar = false\n").unwrap();
+        program.handle_event(LoopEvent::Timer(TimerKind::ConfigCheck));
+        assert_eq!(program.core_data.message(), Some("Config reloaded"));
+        assert_eq!(program.editor().tab_width, 4);
+        assert_eq!(program.editor().scroll_off, 1);
+        assert!(!program.bars.is_visible("status"));
+        assert!(program.bars.is_visible("performance"));
+
+        for document in &program.documents {
+            document.borrow_mut().stop_swap().unwrap();
         }
     }
+
+    /// :e refuses to replace unsaved changes that no other editor shows,
+    ///   and :e! discards them.
+    #[test]
+    fn edit_keeps_unsaved_changes() {
+        let dir = ScratchPath::new_dir("program-edit");
+        let other = dir.join("other.txt");
+        std::fs::write(&other, "other").unwrap();
+        let mut program = Program::new(Vec::new());
+        program.editor_mut().insert_text("typed");
+
+        program.run_command(&format!("e {}", other.display()));
+        assert_eq!(program.core_data.message(), Some("No write since last change; :w first or :e! to discard"));
+        assert_eq!(program.active_document().unwrap().borrow().get_content(), "typed");
+
+        // Another editor still shows the changes, so they aren't lost
+        program.run_command(&format!("vsplit {}", other.display()));
+        assert_eq!(program.active_document().unwrap().borrow().get_content(), "other");
+        program.run_command("close");
+
+        program.run_command(&format!("e! {}", other.display()));
+        assert_eq!(program.active_document().unwrap().borrow().get_content(), "other");
+        program.run_command("q");
+        assert!(!program.running);
+
+        for document in &program.documents {
+            document.borrow_mut().stop_swap().unwrap();
+        }
+    }
+
+    /// :w saves to a new file, and :q refuses to lose unsaved changes.
+    #[test]
+    fn writes_and_quits() {
+        let path = ScratchPath::new("program-write.txt");
+        let mut program = Program::new(vec![TextDocument::new("abc")]);
+        program.editor_mut().insert_text("x");
+
+        program.run_command("q");
+        assert_eq!(program.core_data.message(), Some("1 buffer(s) have unsaved changes (use :qa! to quit anyway)"));
+
+        program.run_command(&format!("wq {}", path.display()));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "xabc");
+        assert!(!program.running);
+    }
+
+    /// No single key quits and throws away unsaved changes.
+    #[test]
+    fn keys_keep_unsaved_changes() {
+        let mut program = Program::new(vec![TextDocument::new("abc")]);
+        let mut backend = TestBackend::new(40, 10);
+        backend.push_keys("ix");
+        backend.push_key(KeyCode::Esc);
+        backend.push_keys("Qq");
+        run_script(&mut program, &mut backend);
+
+        assert!(program.running);
+        assert!(program.core_data.message().unwrap().contains(":qa!"));
+    }
+
+    /// Editors showing the same document keep their cursors within it when
+    ///   it is changed through another editor.
+    #[test]
+    fn split_editors_follow_edits() {
+        let mut program = Program::new(vec![TextDocument::new("abc")]);
+        let mut backend = TestBackend::new(40, 10);
+        backend.push_keys("o1\n2\n3\nfour");
+        backend.push_key(KeyCode::Esc);
+        backend.push_keys(":vsplit\nG$");
+        run_script(&mut program, &mut backend);
+        let other = program.active_editor;
+        assert_eq!(program.editor().cursor_position(), (4, 3));
+
+        backend.push_key_with(KeyCode::Char('w'), KeyModifiers::CONTROL);
+        backend.push_keys("wu");
+        settle(&mut program, &mut backend);
+        assert_ne!(program.active_editor, other);
+        assert_eq!(program.active_document().unwrap().borrow().get_content(), "abc");
+        assert_eq!(program.editors[&other].cursor_position(), (0, 2));
+        assert_eq!(program.editors[&other].top_line, 0);
+    }
+
+    /// :q closes a split editor before it quits.
+    #[test]
+    fn quit_closes_split_first() {
+        let mut program = Program::new(vec![TextDocument::new("abc")]);
+        program.running = true;
+        program.run_command("vsplit");
+        assert_eq!(program.editors.len(), 2);
+
+        program.run_command("q");
+        assert_eq!(program.editors.len(), 1);
+        assert!(program.running);
+        program.run_command("q");
+        assert!(!program.running);
+    }
 }
@@ -0,0 +1,305 @@
+use std::path::Path;
+use std::time::Duration;
+
+use crate::command::{Args, Command, Completion, Registry};
+use crate::layout::SplitDirection;
+use super::Program;
+
+/// The settings that `:set` understands, offered when completing.
+pub const SETTINGS: &[&str] = &[
+    "tabwidth", "scrolloff", "lineending", "statusbar", "performancebar",
+    "swapinterval", "theme", "colors", "cursorline", "linenumbers",
+    "wrap", "wrapindicator", "syntax",
+];
+
+/// Builds the registry of commands that can be run on the program.
+pub fn registry() -> Registry<Program> {
+    let mut registry = Registry::new();
+    registry.register(Command {
+        name: "write",
+        aliases: &["w"],
+        usage: ":write [file]",
+        help: "Saves the document, to a new file if one is given",
+        min_args: 0,
+        max_args: 1,
+        completion: Completion::Path,
+        action: write,
+    });
+    registry.register(Command {
+        name: "quit",
+        aliases: &["q"],
+        usage: ":quit[!]",
+        help: "Closes the editor, or quits if it is the last; ! discards unsaved changes",
+        min_args: 0,
+        max_args: 0,
+        completion: Completion::None,
+        action: quit,
+    });
+    registry.register(Command {
+        name: "qall",
+        aliases: &["qa", "quitall"],
+        usage: ":qall[!]",
+        help: "Quits; ! discards unsaved changes",
+        min_args: 0,
+        max_args: 0,
+        completion: Completion::None,
+        action: quit_all,
+    });
+    registry.register(Command {
+        name: "wq",
+        aliases: &["x"],
+        usage: ":wq [file]",
+        help: "Saves the document, then closes the editor or quits",
+        min_args: 0,
+        max_args: 1,
+        completion: Completion::Path,
+        action: write_quit,
+    });
+    registry.register(Command {
+        name: "edit",
+        aliases: &["e"],
+        usage: ":edit[!] file",
+        help: "Opens a file in the editor; ! discards unsaved changes",
+        min_args: 1,
+        max_args: 1,
+        completion: Completion::Path,
+        action: |program, args| edit(program, args, false),
+    });
+    registry.register(Command {
+        name: "editanyway",
+        aliases: &["edita"],
+        usage: ":editanyway[!] file",
+        help: "Opens a file even if its swap file shows another instance editing it, or holds unsaved changes; ! discards unsaved changes",
+        min_args: 1,
+        max_args: 1,
+        completion: Completion::Path,
+        action: |program, args| edit(program, args, true),
+    });
+    registry.register(Command {
+        name: "split",
+        aliases: &["sp"],
+        usage: ":split [file]",
+        help: "Splits the editor in two, one above the other",
+        min_args: 0,
+        max_args: 1,
+        completion: Completion::Path,
+        action: |program, args| split(program, args, SplitDirection::Horizontal),
+    });
+    registry.register(Command {
+        name: "vsplit",
+        aliases: &["vs"],
+        usage: ":vsplit [file]",
+        help: "Splits the editor in two, side by side",
+        min_args: 0,
+        max_args: 1,
+        completion: Completion::Path,
+        action: |program, args| split(program, args, SplitDirection::Vertical),
+    });
+    registry.register(Command {
+        name: "close",
+        aliases: &["clo"],
+        usage: ":close",
+        help: "Closes the editor, unless it is the last",
+        min_args: 0,
+        max_args: 0,
+        completion: Completion::None,
+        action: |program, _| program.close_editor(),
+    });
+    registry.register(Command {
+        name: "set",
+        aliases: &["se"],
+        usage: ":set setting[=value] | nosetting | setting! | setting?",
+        help: "Changes or shows settings",
+        min_args: 1,
+        max_args: usize::MAX,
+        completion: Completion::Words(SETTINGS),
+        action: set,
+    });
+    registry.register(Command {
+        name: "goto",
+        aliases: &["go"],
+        usage: ":goto line",
+        help: "Moves the cursor to a line; :<line> does the same",
+        min_args: 1,
+        max_args: 1,
+        completion: Completion::None,
+        action: goto,
+    });
+    registry.register(Command {
+        name: "earlier",
+        aliases: &["ea"],
+        usage: ":earlier time (such as 30s, 5m or 1h)",
+        help: "Undoes every change made within a recent period",
+        min_args: 1,
+        max_args: 1,
+        completion: Completion::None,
+        action: earlier,
+    });
+    registry.register(Command {
+        name: "nohlsearch",
+        aliases: &["noh"],
+        usage: ":nohlsearch",
+        help: "Hides the highlighting of search matches until the next search",
+        min_args: 0,
+        max_args: 0,
+        completion: Completion::None,
+        action: |program, _| {
+            program.hide_search_highlight();
+            Ok(())
+        },
+    });
+    registry.register(Command {
+        name: "redraw",
+        aliases: &["redr"],
+        usage: ":redraw",
+        help: "Redraws the whole screen, for when something else has drawn over it",
+        min_args: 0,
+        max_args: 0,
+        completion: Completion::None,
+        action: |program, _| {
+            program.redraw();
+            Ok(())
+        },
+    });
+    // Command lines starting with :s are parsed by the program itself, as the
+    //  pattern isn't split into arguments; this entry gives them help
+    registry.register(Command {
+        name: "substitute",
+        aliases: &["s"],
+        usage: ":[range]s/pattern/replacement/[flags]",
+        help: "Replaces matches of a pattern; flags are g (every match on a line), c (confirm each), i and I (ignore or match case)",
+        min_args: 0,
+        max_args: 0,
+        completion: Completion::None,
+        action: |program, _| program.substitute(None, ""),
+    });
+    registry.register(Command {
+        name: "help",
+        aliases: &["h"],
+        usage: ":help [command]",
+        help: "Describes a command, or lists the commands",
+        min_args: 0,
+        max_args: 1,
+        completion: Completion::Words(&[
+            "write", "quit", "qall", "wq", "edit", "editanyway", "split", "vsplit", "close",
+            "set", "goto", "earlier", "nohlsearch", "redraw", "substitute", "help",
+        ]),
+        action: help,
+    });
+    registry
+}
+
+fn write(program: &mut Program, args: &Args) -> Result<(), String> {
+    match args.get(0) {
+        Some(path) => program.save_as(Path::new(path)),
+        None => program.save(),
+    }
+}
+
+fn quit(program: &mut Program, args: &Args) -> Result<(), String> {
+    if program.editors.len() > 1 {
+        program.close_editor()
+    } else {
+        program.quit(args.bang)
+    }
+}
+
+fn quit_all(program: &mut Program, args: &Args) -> Result<(), String> {
+    program.quit(args.bang)
+}
+
+fn write_quit(program: &mut Program, args: &Args) -> Result<(), String> {
+    write(program, args)?;
+    quit(program, &Args::default())
+}
+
+fn edit(program: &mut Program, args: &Args, ignore_swap: bool) -> Result<(), String> {
+    program.edit_file(Path::new(args.get(0).unwrap()), args.bang, ignore_swap)
+}
+
+fn split(program: &mut Program, args: &Args, direction: SplitDirection) -> Result<(), String> {
+    program.split_editor(direction);
+    match args.get(0) {
+        Some(path) => program.edit_file(Path::new(path), false, false),
+        None => Ok(()),
+    }
+}
+
+fn set(program: &mut Program, args: &Args) -> Result<(), String> {
+    for setting in &args.values {
+        program.set_option(setting)?;
+    }
+    Ok(())
+}
+
+fn goto(program: &mut Program, args: &Args) -> Result<(), String> {
+    let line_number: usize = args.get(0)
+        .unwrap()
+        .parse()
+        .map_err(|_| "Usage: :goto line".to_string())?;
+    program.editor_mut().go_to_line(line_number.saturating_sub(1));
+    Ok(())
+}
+
+fn earlier(program: &mut Program, args: &Args) -> Result<(), String> {
+    let period = parse_period(args.get(0).unwrap())
+        .ok_or_else(|| "Usage: :earlier time (such as 30s, 5m or 1h)".to_string())?;
+    program.undo_period(period)
+}
+
+fn help(program: &mut Program, args: &Args) -> Result<(), String> {
+    let registry = program.commands.clone();
+    let message = match args.get(0) {
+        Some(name) => {
+            let command = registry.find(name).ok_or_else(|| format!("Not a command: {}", name))?;
+            format!("{} - {}", command.usage, command.help)
+        }
+        None => {
+            let names: Vec<&str> = registry.commands().iter().map(|command| command.name).collect();
+            format!("Commands: {}", names.join(", "))
+        }
+    };
+    program.core_data.set_message(message);
+    Ok(())
+}
+
+/// Parses a period of time such as `30s`, `5m` or `1h`. A bare number is a
+///   number of seconds.
+fn parse_period(text: &str) -> Option<Duration> {
+    let (number, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+        Some(ix) => text.split_at(ix),
+        None => (text, "s"),
+    };
+    let number: u64 = number.parse().ok()?;
+    let seconds = match unit {
+        "s" => number,
+        "m" => number.checked_mul(60)?,
+        "h" => number.checked_mul(60 * 60)?,
+        _ => return None,
+    };
+    Some(Duration::from_secs(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::Setting;
+
+    /// Periods are given in seconds, minutes or hours.
+    #[test]
+    fn parses_periods() {
+        assert_eq!(parse_period("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_period("5m"), Some(Duration::from_secs(300)));
+        assert_eq!(parse_period("2"), Some(Duration::from_secs(2)));
+        assert_eq!(parse_period("1d"), None);
+        assert_eq!(parse_period(&format!("{}h", u64::MAX / 60)), None);
+    }
+
+    /// Every setting is offered when completing `:set`.
+    #[test]
+    fn completes_every_setting() {
+        for setting in Setting::ALL.iter() {
+            assert!(SETTINGS.contains(&setting.name()), "{} is not completed", setting.name());
+        }
+    }
+}
@@ -0,0 +1,198 @@
+use std::ops::Range;
+
+use crate::editor::{Editor, Position};
+use crate::mode::Mode;
+use crate::search::{Search, SearchDirection};
+use super::{Program, Prompt};
+
+/// Where the cursor was, and the search that was in use, when a search
+///   started being typed, so that they can be put back if it is cancelled.
+pub(super) struct SearchOrigin {
+    position: Position,
+    /// The editor's sideways scrolling and its top line.
+    scroll: (u16, usize),
+    previous: Option<Search>,
+    highlight: bool,
+}
+
+impl Program {
+    /// Starts typing a search pattern. Matches are found as the pattern is
+    ///   typed, moving the cursor to the nearest one.
+    ///
+    /// # Arguments
+    ///
+    /// * `direction` - Which way to look for matches.
+    pub(super) fn open_search(&mut self, direction: SearchDirection) {
+        let editor = self.editor();
+        self.search_origin = Some(SearchOrigin {
+            position: editor.position,
+            scroll: (editor.scroll_column, editor.top_line),
+            previous: self.search.take(),
+            highlight: self.highlight_search,
+        });
+        self.open_command_line(Prompt::Search(direction));
+    }
+
+    /// Searches for the pattern typed so far, moving the cursor to the
+    ///   nearest match from where the search started. The cursor stays where
+    ///   it started while the pattern is incomplete or has no matches.
+    pub(super) fn update_search(&mut self) {
+        let direction = match self.prompt {
+            Prompt::Search(direction) if self.mode == Mode::Command => direction,
+            _ => return,
+        };
+        let text = self.search_line.text();
+        if self.search.as_ref().map(Search::pattern) == Some(text) {
+            return;
+        }
+        self.search = match text {
+            "" => None,
+            text => Search::new(text, direction).ok(),
+        };
+        self.return_to_search_origin();
+        if self.search.is_some() {
+            self.go_to_match(direction);
+        }
+    }
+
+    /// Finishes typing a search, moving to the nearest match and highlighting
+    ///   every match. An empty pattern repeats the previous search.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The pattern that was typed.
+    /// * `direction` - Which way to look for matches.
+    pub(super) fn finish_search(&mut self, pattern: &str, direction: SearchDirection) {
+        let pattern = match (pattern, &self.search_origin) {
+            ("", Some(SearchOrigin { previous: Some(previous), .. })) => previous.pattern().to_string(),
+            ("", _) => {
+                self.cancel_search();
+                self.core_data.set_message("No previous search".to_string());
+                return;
+            }
+            (pattern, _) => pattern.to_string(),
+        };
+        let search = match Search::new(&pattern, direction) {
+            Ok(search) => search,
+            Err(e) => {
+                self.cancel_search();
+                self.core_data.set_message(e);
+                return;
+            }
+        };
+        self.return_to_search_origin();
+        self.search_origin = None;
+        self.search = Some(search);
+        self.highlight_search = true;
+        if !self.go_to_match(direction) {
+            self.core_data.set_message(format!("Pattern not found: {}", pattern));
+        }
+    }
+
+    /// Gives up on the search being typed, putting the cursor and the
+    ///   previous search back.
+    pub(super) fn cancel_search(&mut self) {
+        self.return_to_search_origin();
+        if let Some(origin) = self.search_origin.take() {
+            self.search = origin.previous;
+            self.highlight_search = origin.highlight;
+        }
+    }
+
+    /// Moves to the next match of the search, showing the matches again if
+    ///   they were hidden.
+    ///
+    /// # Arguments
+    ///
+    /// * `reverse` - Whether to look against the direction the search was
+    ///   typed in.
+    pub(super) fn repeat_search(&mut self, reverse: bool) {
+        let (pattern, direction) = match &self.search {
+            Some(search) if reverse => (search.pattern().to_string(), search.direction().reversed()),
+            Some(search) => (search.pattern().to_string(), search.direction()),
+            None => {
+                self.core_data.set_message("No previous search".to_string());
+                return;
+            }
+        };
+        self.highlight_search = true;
+        if !self.go_to_match(direction) {
+            self.core_data.set_message(format!("Pattern not found: {}", pattern));
+        }
+    }
+
+    /// Hides the highlighting of search matches until the next search.
+    pub fn hide_search_highlight(&mut self) {
+        self.highlight_search = false;
+    }
+
+    /// Gets the search whose matches are highlighted: the pattern being typed,
+    ///   or the last search unless its highlighting was hidden.
+    pub(super) fn highlighted_search(&mut self) -> Option<&mut Search> {
+        if self.highlight_search || self.search_origin.is_some() {
+            self.search.as_mut()
+        } else {
+            None
+        }
+    }
+
+    /// Moves the cursor to the nearest match of the search from the cursor,
+    ///   centering it in the editor.
+    ///
+    /// Returns false if there are no matches.
+    ///
+    /// # Arguments
+    ///
+    /// * `direction` - Which way to look.
+    fn go_to_match(&mut self, direction: SearchDirection) -> bool {
+        let document = match self.active_document() {
+            Some(document) => document,
+            None => return false,
+        };
+        let char_ix = self.editor().cursor_char_ix().unwrap_or(0);
+        let found = match &mut self.search {
+            Some(search) => search.next_match(&document, char_ix, direction),
+            None => None,
+        };
+        let (range, wrapped) = match found {
+            Some(found) => found,
+            None => return false,
+        };
+        if wrapped {
+            self.core_data.set_message(match direction {
+                SearchDirection::Forward => "Search hit the bottom, continuing at the top",
+                SearchDirection::Backward => "Search hit the top, continuing at the bottom",
+            }.to_string());
+        }
+        let editor = self.editor_mut();
+        editor.set_cursor_char_ix(range.start);
+        editor.center_cursor();
+        true
+    }
+
+    /// Puts the cursor and scrolling back to where they were when the search
+    ///   started being typed.
+    fn return_to_search_origin(&mut self) {
+        let (position, scroll) = match &self.search_origin {
+            Some(origin) => (origin.position, origin.scroll),
+            None => return,
+        };
+        let editor = self.editor_mut();
+        editor.scroll_to(scroll.0, scroll.1);
+        editor.set_cursor_position(position.line_ix, position.column_ix);
+    }
+}
+
+/// Gets where the matches of a search are drawn in an editor, as a row and a
+///   span of columns for each visible line of each visible match.
+///
+/// # Arguments
+///
+/// * `search` - The search whose matches to show.
+/// * `editor` - The editor the matches are drawn in.
+pub(super) fn match_spans(search: &mut Search, editor: &Editor) -> Vec<(u16, Range<u16>)> {
+    match &editor.content {
+        Some(document) => editor.char_spans(search.matches(document)),
+        None => Vec::new(),
+    }
+}
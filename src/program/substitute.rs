@@ -0,0 +1,171 @@
+use std::ops::Range;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::command::LineRange;
+use crate::editor::Editor;
+use crate::mode::Mode;
+use crate::search::{self, Search, SearchDirection};
+use crate::substitute::{self, Replacement, SubstituteArgs, Substitution};
+use super::Program;
+
+impl Program {
+    /// Replaces matches of a pattern in a range of lines, as one undo step.
+    ///
+    /// With the `c` flag, each replacement is confirmed in confirm mode.
+    ///
+    /// # Arguments
+    ///
+    /// * `range` - The lines to make replacements on, or None for the line
+    ///   holding the cursor.
+    /// * `text` - What follows the command's name: `/pattern/replacement/flags`.
+    ///   Nothing repeats the last substitution, without its flags. An empty
+    ///   pattern stands for the last search.
+    pub fn substitute(&mut self, range: Option<LineRange>, text: &str) -> Result<(), String> {
+        let mut args = match text {
+            "" => match &self.last_substitute {
+                Some(last) => SubstituteArgs { pattern: last.pattern.clone(), replacement: last.replacement.clone(), ..SubstituteArgs::default() },
+                None => return Err("No previous substitute".to_string()),
+            },
+            text => substitute::parse(text)?,
+        };
+        if args.pattern.is_empty() {
+            args.pattern = match &self.search {
+                Some(search) => search.pattern().to_string(),
+                None => return Err("No previous search".to_string()),
+            };
+        }
+
+        let ignore_case = args.ignore_case.unwrap_or_else(|| search::ignores_case(&args.pattern));
+        let regex = search::build_regex(&args.pattern, ignore_case)?;
+        let replacement = Replacement::parse(&args.replacement);
+        if replacement.max_group() >= regex.captures_len() {
+            return Err(format!("No such group: \\{}", replacement.max_group()));
+        }
+
+        let document = match self.active_document() {
+            Some(document) => document,
+            None => return Err("No document".to_string()),
+        };
+        let line_ix = self.editor().cursor_position().0;
+        let lines = match range {
+            Some(range) => range.resolve(line_ix, document.borrow().line_count())?,
+            None => line_ix..line_ix + 1,
+        };
+
+        // The pattern becomes the last search, so `n` finds what was replaced
+        self.search = Some(Search::new(&args.pattern, SearchDirection::Forward)?);
+        self.highlight_search = true;
+        self.last_substitute = Some(args.clone());
+
+        let mut substitution = Substitution::new(document, regex, replacement, args.global, lines);
+        if args.confirm {
+            if substitution.find_next().is_none() {
+                return Err(format!("Pattern not found: {}", args.pattern));
+            }
+            self.set_mode(Mode::Confirm);
+            self.editor_mut().begin_edit_group();
+            self.confirming = Some(substitution);
+            self.show_confirmation();
+        } else {
+            self.editor_mut().begin_edit_group();
+            substitution.replace_all();
+            self.finish_substitution(substitution);
+        }
+        Ok(())
+    }
+
+    /// Answers whether to make the replacement being confirmed: `y` makes it,
+    ///   `n` skips it, `a` makes it and every one after, `l` makes it and
+    ///   stops, and `q` or escape stops.
+    ///
+    /// # Arguments
+    ///
+    /// * `event` - The key pressed.
+    pub(super) fn confirm_replacement(&mut self, event: KeyEvent) {
+        let mut substitution = match self.confirming.take() {
+            Some(substitution) => substitution,
+            None => {
+                self.set_mode(Mode::Normal);
+                return;
+            }
+        };
+        let stop = match event.code {
+            KeyCode::Esc => true,
+            KeyCode::Char('c') if event.modifiers.contains(KeyModifiers::CONTROL) => true,
+            KeyCode::Char('q') => true,
+            KeyCode::Char('y') => {
+                substitution.replace();
+                false
+            }
+            KeyCode::Char('l') => {
+                substitution.replace();
+                true
+            }
+            KeyCode::Char('n') => {
+                substitution.skip();
+                false
+            }
+            KeyCode::Char('a') => {
+                substitution.replace_all();
+                false
+            }
+            _ => false,
+        };
+
+        if !stop && substitution.find_next().is_some() {
+            self.confirming = Some(substitution);
+            self.show_confirmation();
+        } else {
+            self.finish_substitution(substitution);
+            self.set_mode(Mode::Normal);
+        }
+    }
+
+    /// Moves the cursor to the match being confirmed, and asks whether to
+    ///   replace it.
+    fn show_confirmation(&mut self) {
+        let (range, text) = match &self.confirming {
+            Some(substitution) => match (substitution.current(), substitution.current_replacement()) {
+                (Some(range), Some(text)) => (range, text.to_string()),
+                _ => return,
+            },
+            None => return,
+        };
+        let editor = self.editor_mut();
+        editor.set_cursor_char_ix(range.start);
+        editor.center_cursor();
+        self.core_data.set_message(format!("Replace with {} (y/n/a/q/l)?", text.escape_debug()));
+    }
+
+    /// Ends the undo step of a substitution, leaving the cursor at the start
+    ///   of the last line changed, and reports what was replaced.
+    fn finish_substitution(&mut self, substitution: Substitution) {
+        if let Some(line_ix) = substitution.last_line() {
+            self.editor_mut().set_cursor_position(line_ix, 0);
+        }
+        self.editor_mut().end_edit_group();
+
+        let message = match (substitution.count(), substitution.lines_changed()) {
+            (0, _) => format!("Pattern not found: {}", substitution.pattern()),
+            (1, _) => "1 substitution on 1 line".to_string(),
+            (count, 1) => format!("{} substitutions on 1 line", count),
+            (count, lines) => format!("{} substitutions on {} lines", count, lines),
+        };
+        self.core_data.set_message(message);
+    }
+}
+
+/// Gets where the match being confirmed is drawn in an editor, as a row and
+///   a span of columns for each visible line of the match.
+///
+/// # Arguments
+///
+/// * `substitution` - The substitution being confirmed.
+/// * `editor` - The editor the match is drawn in.
+pub(super) fn confirm_spans(substitution: &Substitution, editor: &Editor) -> Vec<(u16, Range<u16>)> {
+    let current = match (&editor.content, substitution.current()) {
+        (Some(document), Some(current)) if std::rc::Rc::ptr_eq(document, substitution.document()) => current,
+        _ => return Vec::new(),
+    };
+    editor.char_spans(&[current])
+}
@@ -0,0 +1,63 @@
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+/// A path in the temp directory for one test to use, which is removed when
+///   the test ends, whether or not it passes.
+///
+/// The path holds the process id as well as the test's own name, so that
+///   tests running at the same time, or in other runs, never share a file.
+pub struct ScratchPath {
+    path: PathBuf,
+}
+
+impl ScratchPath {
+    /// Returns a path that doesn't yet exist.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the test, ending in an extension if the type
+    ///   of the file matters.
+    pub fn new(name: &str) -> ScratchPath {
+        let path = std::env::temp_dir().join(format!("stated-{}-{}", std::process::id(), name));
+        remove(&path);
+        ScratchPath { path }
+    }
+
+    /// Returns an empty directory, for tests whose files are accompanied by
+    ///   others, such as swap files, that should be removed along with them.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the test.
+    pub fn new_dir(name: &str) -> ScratchPath {
+        let dir = ScratchPath::new(name);
+        fs::create_dir(&dir).unwrap();
+        dir
+    }
+}
+
+impl Deref for ScratchPath {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for ScratchPath {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchPath {
+    fn drop(&mut self) {
+        remove(&self.path);
+    }
+}
+
+/// Removes a file or directory, if there is one.
+fn remove(path: &Path) {
+    let _ = if path.is_dir() { fs::remove_dir_all(path) } else { fs::remove_file(path) };
+}
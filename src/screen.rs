@@ -0,0 +1,305 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::editor::{graphemes, Dimensions, Location};
+use crate::layout::Rect;
+
+mod cell;
+
+pub use cell::{Cell, Style};
+
+/// A cell that differs from what the terminal shows.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Change {
+    pub location: Location,
+    pub cell: Cell,
+}
+
+/// A double-buffered model of the terminal.
+///
+/// Each frame is drawn into the back buffer, then `changes` compares it with
+///   the front buffer (what the terminal is showing), so that only the cells
+///   that changed are sent to the terminal.
+pub struct Screen {
+    size: Dimensions,
+
+    /// The cells the terminal is currently showing.
+    front: Vec<Cell>,
+
+    /// The cells of the frame being drawn.
+    back: Vec<Cell>,
+
+    /// Whether the terminal's contents are unknown, so that the next frame
+    ///   must redraw every cell.
+    invalidated: bool,
+}
+
+impl Screen {
+    /// Returns a blank screen.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The size of the terminal.
+    pub fn new(size: Dimensions) -> Screen {
+        let num_cells = size.columns as usize * size.rows as usize;
+        Screen {
+            size,
+            front: vec![Cell::default(); num_cells],
+            back: vec![Cell::default(); num_cells],
+            invalidated: true,
+        }
+    }
+
+    /// Resizes the screen. Its contents are lost, and the next frame redraws
+    ///   every cell.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The new size of the terminal.
+    pub fn resize(&mut self, size: Dimensions) {
+        *self = Screen::new(size);
+    }
+
+    /// Forces the next frame to redraw every cell, for when the terminal's
+    ///   contents may have been disturbed.
+    pub fn invalidate(&mut self) {
+        self.invalidated = true;
+    }
+
+    /// Blanks the back buffer, ready to draw a new frame.
+    pub fn clear(&mut self) {
+        for cell in self.back.iter_mut() {
+            *cell = Cell::default();
+        }
+    }
+
+    /// Gets a cell of the frame being drawn.
+    ///
+    /// # Arguments
+    ///
+    /// * `column_ix` - The column of the cell.
+    /// * `row_ix` - The row of the cell.
+    #[cfg(test)]
+    pub fn cell(&self, column_ix: u16, row_ix: u16) -> Option<&Cell> {
+        self.index(column_ix, row_ix).map(|ix| &self.back[ix])
+    }
+
+    /// Draws text on a single row, clipped to the screen.
+    ///
+    /// Tabs and control characters should be expanded before drawing; any
+    ///   left are drawn as a space or a `?`. A wide grapheme that doesn't fit
+    ///   is replaced by a space.
+    ///
+    /// Returns the number of columns drawn.
+    ///
+    /// # Arguments
+    ///
+    /// * `column_ix` - The column at which the text starts.
+    /// * `row_ix` - The row on which to draw the text.
+    /// * `text` - The text to draw.
+    /// * `style` - The colors with which to draw the text.
+    pub fn put_str(&mut self, column_ix: u16, row_ix: u16, text: &str, style: Style) -> u16 {
+        self.put_str_clipped(column_ix, row_ix, text, style, self.size.columns)
+    }
+
+    /// Draws text on a single row, clipped to a column and to the screen.
+    ///
+    /// Returns the number of columns drawn.
+    ///
+    /// # Arguments
+    ///
+    /// * `column_ix` - The column at which the text starts.
+    /// * `row_ix` - The row on which to draw the text.
+    /// * `text` - The text to draw.
+    /// * `style` - The colors with which to draw the text.
+    /// * `end_column_ix` - The column just right of the last one to draw in.
+    pub fn put_str_clipped(&mut self, column_ix: u16, row_ix: u16, text: &str, style: Style, end_column_ix: u16) -> u16 {
+        if row_ix >= self.size.rows {
+            return 0;
+        }
+        let end_column_ix = end_column_ix.min(self.size.columns);
+        let mut column = column_ix;
+        for grapheme in text.graphemes(true) {
+            if column >= end_column_ix {
+                break;
+            }
+            let width = graphemes::grapheme_width(grapheme, 0, 1) as u16;
+            if column + width > end_column_ix {
+                // Don't let a wide grapheme spill past the edge
+                self.set(column, row_ix, Cell::blank(style));
+                column += 1;
+                continue;
+            }
+            let symbol = if grapheme == "\t" {
+                " "
+            } else if grapheme.chars().any(char::is_control) {
+                "?"
+            } else {
+                grapheme
+            };
+            self.set(column, row_ix, Cell::new(symbol, style));
+            if width == 2 {
+                self.set(column + 1, row_ix, Cell::continuation(style));
+            }
+            column += width;
+        }
+        column - column_ix
+    }
+
+    /// Fills an area with copies of a symbol.
+    ///
+    /// # Arguments
+    ///
+    /// * `area` - The area to fill.
+    /// * `symbol` - The single column grapheme to fill it with.
+    /// * `style` - The colors with which to fill it.
+    pub fn fill(&mut self, area: Rect, symbol: &str, style: Style) {
+        for row_ix in area.top()..area.bottom().min(self.size.rows) {
+            for column_ix in area.left()..area.right().min(self.size.columns) {
+                self.set(column_ix, row_ix, Cell::new(symbol, style));
+            }
+        }
+    }
+
+    /// Changes the colors of the cells in an area, keeping what they show.
+    ///
+    /// # Arguments
+    ///
+    /// * `area` - The area to restyle.
+    /// * `style` - The colors with which to draw it.
+    pub fn set_style(&mut self, area: Rect, style: Style) {
+        for row_ix in area.top()..area.bottom().min(self.size.rows) {
+            for column_ix in area.left()..area.right().min(self.size.columns) {
+                if let Some(ix) = self.index(column_ix, row_ix) {
+                    self.back[ix].style = style;
+                }
+            }
+        }
+    }
+
+    /// Whether the frame being drawn differs from what the terminal shows.
+    #[cfg(test)]
+    pub fn is_dirty(&self) -> bool {
+        self.invalidated || self.front != self.back
+    }
+
+    /// Whether the terminal must be cleared before the next frame is drawn,
+    ///   because its contents are unknown.
+    pub fn needs_clear(&self) -> bool {
+        self.invalidated
+    }
+
+    /// Gets the cells of the frame being drawn that differ from what the
+    ///   terminal shows, in row order. Every cell is included if the terminal
+    ///   needs clearing.
+    ///
+    /// The second column of a wide grapheme is never included; instead the
+    ///   wide grapheme is included if either of its cells changed.
+    pub fn changes(&self) -> Vec<Change> {
+        let columns = self.size.columns as usize;
+        let mut result = Vec::new();
+        for (ix, cell) in self.back.iter().enumerate() {
+            if cell.is_continuation() {
+                continue;
+            }
+            let covers_next = ix % columns + 1 < columns && self.back[ix + 1].is_continuation();
+            let changed = self.invalidated
+                || self.front[ix] != *cell
+                || (covers_next && self.front[ix + 1] != self.back[ix + 1]);
+            if changed {
+                result.push(Change {
+                    location: Location::new((ix % columns) as u16, (ix / columns) as u16),
+                    cell: cell.clone(),
+                });
+            }
+        }
+        result
+    }
+
+    /// Records that the frame being drawn is now shown on the terminal.
+    pub fn present(&mut self) {
+        self.front.clone_from(&self.back);
+        self.invalidated = false;
+    }
+
+    /// Replaces a cell of the frame being drawn, blanking any wide grapheme
+    ///   that it overlaps.
+    fn set(&mut self, column_ix: u16, row_ix: u16, cell: Cell) {
+        let ix = match self.index(column_ix, row_ix) {
+            Some(ix) => ix,
+            None => return,
+        };
+        let columns = self.size.columns as usize;
+        if self.back[ix].is_continuation() && ix % columns > 0 {
+            let style = self.back[ix - 1].style;
+            self.back[ix - 1] = Cell::blank(style);
+        }
+        if !cell.is_continuation() && ix % columns + 1 < columns && self.back[ix + 1].is_continuation() {
+            let style = self.back[ix + 1].style;
+            self.back[ix + 1] = Cell::blank(style);
+        }
+        self.back[ix] = cell;
+    }
+
+    /// Gets the index of a cell in the buffers.
+    fn index(&self, column_ix: u16, row_ix: u16) -> Option<usize> {
+        if column_ix < self.size.columns && row_ix < self.size.rows {
+            Some(row_ix as usize * self.size.columns as usize + column_ix as usize)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Presents a frame, returning the number of cells drawn.
+    fn present_frame(screen: &mut Screen) -> usize {
+        let num_changes = screen.changes().len();
+        screen.present();
+        num_changes
+    }
+
+    /// Only the cells that changed since the last frame are drawn.
+    #[test]
+    fn presents_only_changes() {
+        let mut screen = Screen::new(Dimensions::new(10, 3));
+        screen.put_str(0, 0, "hello", Style::default());
+        assert_eq!(present_frame(&mut screen), 30);
+
+        screen.clear();
+        screen.put_str(0, 0, "help", Style::default());
+        assert!(screen.is_dirty());
+        assert_eq!(present_frame(&mut screen), 2);
+
+        screen.clear();
+        screen.put_str(0, 0, "help", Style::default());
+        assert!(!screen.is_dirty());
+        assert_eq!(present_frame(&mut screen), 0);
+    }
+
+    /// Wide graphemes cover two cells, and are blanked when overwritten.
+    #[test]
+    fn draws_wide_graphemes() {
+        let mut screen = Screen::new(Dimensions::new(5, 1));
+        assert_eq!(screen.put_str(0, 0, "日本語", Style::default()), 5);
+        assert_eq!(screen.cell(2, 0).unwrap().symbol, "本");
+        assert!(screen.cell(3, 0).unwrap().is_continuation());
+        assert_eq!(screen.cell(4, 0).unwrap().symbol, " ");
+
+        screen.put_str(1, 0, "a", Style::default());
+        assert_eq!(screen.cell(0, 0).unwrap().symbol, " ");
+        assert_eq!(screen.cell(1, 0).unwrap().symbol, "a");
+    }
+
+    /// Text is clipped to the screen and to the requested column.
+    #[test]
+    fn clips_text() {
+        let mut screen = Screen::new(Dimensions::new(5, 2));
+        assert_eq!(screen.put_str(3, 0, "abc", Style::default()), 2);
+        assert_eq!(screen.put_str_clipped(0, 1, "abc", Style::default(), 2), 2);
+        assert_eq!(screen.cell(2, 1).unwrap().symbol, " ");
+        assert_eq!(screen.put_str(0, 5, "abc", Style::default()), 0);
+    }
+}
@@ -0,0 +1,74 @@
+use crossterm::style::{Attributes, Color};
+
+/// The colors and attributes, such as bold, with which a cell is drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Style {
+    pub foreground: Color,
+    pub background: Color,
+    pub attributes: Attributes,
+}
+
+impl Style {
+    pub fn new(foreground: Color, background: Color) -> Style {
+        Style { foreground, background, attributes: Attributes::default() }
+    }
+}
+
+impl Default for Style {
+    /// The terminal's own colors.
+    fn default() -> Style {
+        Style::new(Color::Reset, Color::Reset)
+    }
+}
+
+/// A single character cell of the screen.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cell {
+    /// The grapheme cluster drawn in the cell. Empty for the second column of
+    ///   a wide grapheme, which is drawn by the cell before it.
+    pub symbol: String,
+
+    /// The colors with which the cell is drawn.
+    pub style: Style,
+}
+
+impl Cell {
+    /// Returns a cell holding a grapheme cluster.
+    ///
+    /// # Arguments
+    ///
+    /// * `symbol` - The grapheme cluster to draw.
+    /// * `style` - The colors with which to draw it.
+    pub fn new(symbol: &str, style: Style) -> Cell {
+        Cell { symbol: symbol.to_string(), style }
+    }
+
+    /// Returns a cell holding a space.
+    ///
+    /// # Arguments
+    ///
+    /// * `style` - The colors with which to draw the cell.
+    pub fn blank(style: Style) -> Cell {
+        Cell::new(" ", style)
+    }
+
+    /// Returns a cell covered by the wide grapheme in the cell before it.
+    ///
+    /// # Arguments
+    ///
+    /// * `style` - The colors of the wide grapheme.
+    pub fn continuation(style: Style) -> Cell {
+        Cell::new("", style)
+    }
+
+    /// Whether the cell is covered by the wide grapheme in the cell before it.
+    pub fn is_continuation(&self) -> bool {
+        self.symbol.is_empty()
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Cell {
+        Cell::blank(Style::default())
+    }
+}
@@ -1,16 +1,21 @@
-use std::io::Write;
-use crossterm::{cursor, queue, style};
+use crate::layout::Rect;
+use crate::screen::{Screen, Style};
 
-/// Renders the home screen.
-pub fn render<W>(w: &mut W)
-where
-    W: Write,
-{
-    queue!(
-        w,
-        cursor::MoveTo(0, 0),
-        style::Print("Stu Thompson's Awesome Text Editor")
-    ).unwrap();
+/// The title shown on the home screen.
+const TITLE: &str = "Stu Thompson's Awesome Text Editor";
 
-    w.flush().unwrap();
+/// Renders the home screen, centred in an area of the screen.
+///
+/// # Arguments
+///
+/// * `screen` - The screen to draw on.
+/// * `area` - The area in which to centre the home screen.
+/// * `title_style` - The style with which to draw the title.
+pub fn render(screen: &mut Screen, area: Rect, title_style: Style) {
+    let width = TITLE.len() as u16;
+    let column_ix = area.left() + area.dimensions.columns.saturating_sub(width) / 2;
+    let row_ix = area.top() + area.dimensions.rows / 2;
+    if area.dimensions.rows > 0 {
+        screen.put_str_clipped(column_ix, row_ix, TITLE, title_style, area.right());
+    }
 }
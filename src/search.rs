@@ -0,0 +1,258 @@
+use std::cell::RefCell;
+use std::ops::Range;
+use std::rc::{Rc, Weak};
+use regex::{Regex, RegexBuilder};
+
+use crate::document::{DocumentHandle, TextDocument};
+
+/// Which way a search looks from the cursor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SearchDirection {
+    /// Towards the end of the document, as `/` searches.
+    Forward,
+
+    /// Towards the start of the document, as `?` searches.
+    Backward,
+}
+
+impl SearchDirection {
+    /// Gets the character typed to start a search in this direction, which is
+    ///   shown before the pattern on the command line.
+    pub fn prompt(self) -> char {
+        match self {
+            SearchDirection::Forward => '/',
+            SearchDirection::Backward => '?',
+        }
+    }
+
+    /// Gets the opposite direction.
+    pub fn reversed(self) -> SearchDirection {
+        match self {
+            SearchDirection::Forward => SearchDirection::Backward,
+            SearchDirection::Backward => SearchDirection::Forward,
+        }
+    }
+}
+
+/// The matches of a pattern found in one document.
+struct Found {
+    document: Weak<RefCell<TextDocument>>,
+    /// The version of the document the matches were found in.
+    version: u64,
+    matches: Vec<Range<usize>>,
+}
+
+/// A regular expression searched for in documents.
+///
+/// The matches in each document are kept until the document changes, so that
+///   they can be drawn and counted every frame without searching again.
+pub struct Search {
+    pattern: String,
+    regex: Regex,
+    direction: SearchDirection,
+    found: Vec<Found>,
+}
+
+impl Search {
+    /// Compiles a search pattern.
+    ///
+    /// The search ignores case unless the pattern has an uppercase letter.
+    ///   `^` and `$` match at the start and end of every line.
+    ///
+    /// # Arguments
+    ///
+    /// * `pattern` - The regular expression to search for.
+    /// * `direction` - Which way `n` looks for the next match.
+    pub fn new(pattern: &str, direction: SearchDirection) -> Result<Search, String> {
+        let regex = build_regex(pattern, ignores_case(pattern))?;
+        Ok(Search { pattern: pattern.to_string(), regex, direction, found: Vec::new() })
+    }
+
+    /// Gets the pattern as it was typed.
+    pub fn pattern(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Gets the direction the search was started in.
+    pub fn direction(&self) -> SearchDirection {
+        self.direction
+    }
+
+    /// Gets the matches in a document, as char ranges in document order.
+    ///
+    /// Empty matches are left out, as there is nothing to show or move to.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The document to search.
+    pub fn matches(&mut self, document: &DocumentHandle) -> &[Range<usize>] {
+        // Forget the matches of documents that have been closed
+        self.found.retain(|found| found.document.strong_count() > 0);
+
+        let version = document.borrow().version();
+        let ix = match self.found.iter().position(|found| Weak::as_ptr(&found.document) == Rc::as_ptr(document)) {
+            Some(ix) => ix,
+            None => {
+                self.found.push(Found {
+                    document: Rc::downgrade(document),
+                    version,
+                    matches: find_matches(&self.regex, &document.borrow().get_content()),
+                });
+                self.found.len() - 1
+            }
+        };
+        let found = &mut self.found[ix];
+        if found.version != version {
+            found.version = version;
+            found.matches = find_matches(&self.regex, &document.borrow().get_content());
+        }
+        &found.matches
+    }
+
+    /// Finds the nearest match starting after (or before) a char index,
+    ///   wrapping around the end (or start) of the document.
+    ///
+    /// Returns the match, and whether the search wrapped around to find it.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The document to search.
+    /// * `char_ix` - The char index to search from, usually the cursor.
+    /// * `direction` - Which way to look.
+    pub fn next_match(&mut self, document: &DocumentHandle, char_ix: usize, direction: SearchDirection) -> Option<(Range<usize>, bool)> {
+        let matches = self.matches(document);
+        let next = match direction {
+            SearchDirection::Forward => matches.iter().find(|m| m.start > char_ix),
+            SearchDirection::Backward => matches.iter().rev().find(|m| m.start < char_ix),
+        };
+        match next {
+            Some(m) => Some((m.clone(), false)),
+            None => {
+                let wrapped = match direction {
+                    SearchDirection::Forward => matches.first(),
+                    SearchDirection::Backward => matches.last(),
+                };
+                wrapped.map(|m| (m.clone(), true))
+            }
+        }
+    }
+
+    /// Gets which match covers a char index, counting from zero, along with
+    ///   the number of matches.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The document to search.
+    /// * `char_ix` - The char index, usually the cursor.
+    pub fn position(&mut self, document: &DocumentHandle, char_ix: usize) -> (Option<usize>, usize) {
+        let matches = self.matches(document);
+        let ix = matches.partition_point(|m| m.end <= char_ix);
+        let covering = matches.get(ix).filter(|m| m.start <= char_ix).map(|_| ix);
+        (covering, matches.len())
+    }
+}
+
+/// Compiles a pattern into a regular expression in which `^` and `$` match at
+///   the start and end of every line.
+///
+/// # Arguments
+///
+/// * `pattern` - The pattern, as typed.
+/// * `ignore_case` - Whether letters match either case.
+pub fn build_regex(pattern: &str, ignore_case: bool) -> Result<Regex, String> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(ignore_case)
+        .multi_line(true)
+        .crlf(true)
+        .build()
+        .map_err(|e| {
+            // Syntax errors draw a diagram of the pattern above the reason
+            let message = e.to_string();
+            let reason = message.lines().last().unwrap_or_default();
+            format!("Invalid pattern: {}", reason.trim_start_matches("error: "))
+        })
+}
+
+/// Whether a pattern should ignore case, which it does unless it has an
+///   uppercase letter. Letters escaped with `\`, such as in `\S`, and the
+///   names of Unicode classes, such as in `\p{Lu}`, don't count.
+pub fn ignores_case(pattern: &str) -> bool {
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            // Skip a braced class name along with the escape
+            if matches!(chars.next(), Some('p') | Some('P')) && chars.clone().next() == Some('{') {
+                chars.by_ref().find(|c| *c == '}');
+            }
+        } else if c.is_uppercase() {
+            return false;
+        }
+    }
+    true
+}
+
+/// Finds the non-empty matches of a regular expression, as char ranges.
+fn find_matches(regex: &Regex, text: &str) -> Vec<Range<usize>> {
+    let mut matches = Vec::new();
+    let mut byte_ix = 0;
+    let mut char_ix = 0;
+    for m in regex.find_iter(text) {
+        if m.range().is_empty() {
+            continue;
+        }
+        char_ix += text[byte_ix..m.start()].chars().count();
+        let end = char_ix + m.as_str().chars().count();
+        matches.push(char_ix..end);
+        char_ix = end;
+        byte_ix = m.end();
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Searches ignore case unless the pattern has an uppercase letter.
+    #[test]
+    fn uses_smart_case() {
+        let document = TextDocument::new("Foo foo FOO").into_handle();
+        let mut search = Search::new("foo", SearchDirection::Forward).unwrap();
+        assert_eq!(search.matches(&document).len(), 3);
+
+        let mut search = Search::new("Foo", SearchDirection::Forward).unwrap();
+        assert_eq!(search.matches(&document).to_vec(), vec![0..3]);
+
+        let mut search = Search::new(r"\Sx?oo", SearchDirection::Forward).unwrap();
+        assert_eq!(search.matches(&document).len(), 3);
+
+        let error = Search::new("fo(", SearchDirection::Forward).err().unwrap();
+        assert!(error.starts_with("Invalid pattern: unclosed group"), "{}", error);
+    }
+
+    /// Matches are char ranges, and are found again once the document changes.
+    #[test]
+    fn finds_matches_as_chars() {
+        let document = TextDocument::new("日本 ab\nab$").into_handle();
+        let mut search = Search::new("ab$", SearchDirection::Forward).unwrap();
+        assert_eq!(search.matches(&document).to_vec(), vec![3..5]);
+        assert_eq!(search.position(&document, 4), (Some(0), 1));
+        assert_eq!(search.position(&document, 5), (None, 1));
+
+        document.borrow_mut().insert(0, "ab\n");
+        assert_eq!(search.matches(&document), &[0..2, 6..8]);
+    }
+
+    /// The next match wraps around the ends of the document.
+    #[test]
+    fn wraps_to_next_match() {
+        let document = TextDocument::new("a b a b a").into_handle();
+        let mut search = Search::new("a", SearchDirection::Forward).unwrap();
+        assert_eq!(search.next_match(&document, 0, SearchDirection::Forward), Some((4..5, false)));
+        assert_eq!(search.next_match(&document, 8, SearchDirection::Forward), Some((0..1, true)));
+        assert_eq!(search.next_match(&document, 0, SearchDirection::Backward), Some((8..9, true)));
+
+        let mut search = Search::new("c", SearchDirection::Forward).unwrap();
+        assert_eq!(search.next_match(&document, 0, SearchDirection::Forward), None);
+    }
+}
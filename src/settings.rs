@@ -0,0 +1,501 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::editor::{LineNumbers, Wrap};
+use crate::theme::ColorSupport;
+
+/// A setting that can be changed in the config file or with `:set`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Setting {
+    TabWidth,
+    ScrollOff,
+    StatusBar,
+    PerformanceBar,
+    SwapInterval,
+    Theme,
+    Colors,
+    CursorLine,
+    LineNumbers,
+    Wrap,
+    WrapIndicator,
+    Syntax,
+}
+
+/// The type of value a setting holds.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Kind {
+    Number,
+    Switch,
+    Text,
+    /// One of a fixed set of words.
+    Choice(&'static [&'static str]),
+}
+
+/// Where a setting applies.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Scope {
+    /// The whole program.
+    Global,
+
+    /// Each editor, according to the type of file it displays, so it can be
+    ///   overridden for a file type.
+    Editor,
+}
+
+impl Setting {
+    /// Every setting, in the order they are listed.
+    pub const ALL: [Setting; 12] = [
+        Setting::TabWidth,
+        Setting::ScrollOff,
+        Setting::StatusBar,
+        Setting::PerformanceBar,
+        Setting::SwapInterval,
+        Setting::Theme,
+        Setting::Colors,
+        Setting::CursorLine,
+        Setting::LineNumbers,
+        Setting::Wrap,
+        Setting::WrapIndicator,
+        Setting::Syntax,
+    ];
+
+    /// Gets the name of the setting, as written in the config file and with
+    ///   `:set`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Setting::TabWidth => "tabwidth",
+            Setting::ScrollOff => "scrolloff",
+            Setting::StatusBar => "statusbar",
+            Setting::PerformanceBar => "performancebar",
+            Setting::SwapInterval => "swapinterval",
+            Setting::Theme => "theme",
+            Setting::Colors => "colors",
+            Setting::CursorLine => "cursorline",
+            Setting::LineNumbers => "linenumbers",
+            Setting::Wrap => "wrap",
+            Setting::WrapIndicator => "wrapindicator",
+            Setting::Syntax => "syntax",
+        }
+    }
+
+    /// Gets the short name that can be used in place of the full name.
+    pub fn alias(&self) -> Option<&'static str> {
+        match self {
+            Setting::TabWidth => Some("ts"),
+            Setting::ScrollOff => Some("so"),
+            Setting::CursorLine => Some("cul"),
+            Setting::LineNumbers => Some("nu"),
+            Setting::Syntax => Some("syn"),
+            _ => None,
+        }
+    }
+
+    /// Gets the type of value the setting holds.
+    pub fn kind(&self) -> Kind {
+        match self {
+            Setting::TabWidth | Setting::ScrollOff | Setting::SwapInterval => Kind::Number,
+            Setting::StatusBar | Setting::PerformanceBar | Setting::CursorLine
+                | Setting::Syntax => Kind::Switch,
+            Setting::Theme | Setting::WrapIndicator => Kind::Text,
+            Setting::Colors => Kind::Choice(&["auto", "16", "256", "truecolor"]),
+            Setting::LineNumbers => Kind::Choice(LineNumbers::NAMES),
+            Setting::Wrap => Kind::Choice(Wrap::NAMES),
+        }
+    }
+
+    /// Gets where the setting applies.
+    pub fn scope(&self) -> Scope {
+        match self {
+            Setting::TabWidth | Setting::ScrollOff | Setting::LineNumbers
+                | Setting::Wrap | Setting::WrapIndicator => Scope::Editor,
+            _ => Scope::Global,
+        }
+    }
+
+    /// Gets a setting by its name or alias.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name or alias of the setting.
+    pub fn from_name(name: &str) -> Option<Setting> {
+        Setting::ALL
+            .iter()
+            .copied()
+            .find(|setting| setting.name() == name || setting.alias() == Some(name))
+    }
+
+    /// Describes the setting's value as it would be written with `:set`.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The value of the setting.
+    pub fn describe(&self, value: Value) -> String {
+        match value {
+            Value::Switch(true) => self.name().to_string(),
+            Value::Switch(false) => format!("no{}", self.name()),
+            Value::Number(number) => format!("{}={}", self.name(), number),
+            Value::Text(text) => format!("{}={}", self.name(), text),
+        }
+    }
+}
+
+/// The value of a setting.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Number(usize),
+    Switch(bool),
+    Text(String),
+}
+
+impl Value {
+    /// Parses a value written with `:set`.
+    ///
+    /// # Arguments
+    ///
+    /// * `kind` - The type of value expected.
+    /// * `text` - The value as written.
+    pub fn parse(kind: Kind, text: &str) -> Result<Value, String> {
+        match kind {
+            Kind::Number => text
+                .parse()
+                .map(Value::Number)
+                .map_err(|_| format!("Invalid number: {}", text)),
+            Kind::Switch => match text {
+                "on" | "true" | "yes" => Ok(Value::Switch(true)),
+                "off" | "false" | "no" => Ok(Value::Switch(false)),
+                _ => Err(format!("Invalid switch: {} (use on or off)", text)),
+            },
+            Kind::Text => Ok(Value::Text(text.to_string())),
+            Kind::Choice(choices) if choices.contains(&text) => Ok(Value::Text(text.to_string())),
+            Kind::Choice(choices) => Err(format!("Invalid value: {} (use one of {})", text, choices.join(", "))),
+        }
+    }
+
+    /// Reads a value from the config file.
+    fn from_toml(kind: Kind, value: &toml::Value) -> Result<Value, String> {
+        match (kind, value) {
+            (Kind::Number, toml::Value::Integer(number)) if *number >= 0 => Ok(Value::Number(*number as usize)),
+            (Kind::Switch, toml::Value::Boolean(switch)) => Ok(Value::Switch(*switch)),
+            (Kind::Text | Kind::Choice(_), toml::Value::String(text)) => Value::parse(kind, text),
+            (Kind::Choice(_), toml::Value::Integer(number)) => Value::parse(kind, &number.to_string()),
+            (Kind::Number, _) => Err("expected a whole number".to_string()),
+            (Kind::Switch, _) => Err("expected true or false".to_string()),
+            (Kind::Text | Kind::Choice(_), _) => Err("expected a string".to_string()),
+        }
+    }
+}
+
+/// The values of every setting, as they apply somewhere in the program.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    /// The number of columns between tab stops.
+    pub tab_width: usize,
+
+    /// The minimum number of lines to keep visible above and below the
+    ///   cursor.
+    pub scroll_off: u16,
+
+    /// Whether the status bar is shown.
+    pub status_bar: bool,
+
+    /// Whether the performance bar is shown.
+    pub performance_bar: bool,
+
+    /// How often unsaved changes are copied to swap files. Zero stops them
+    ///   being copied.
+    pub swap_interval: Duration,
+
+    /// The name of the color theme.
+    pub theme: String,
+
+    /// The colors the terminal can show, or None to detect them.
+    pub colors: Option<ColorSupport>,
+
+    /// Whether the line holding the cursor is highlighted.
+    pub cursor_line: bool,
+
+    /// How line numbers are shown beside the text.
+    pub line_numbers: LineNumbers,
+
+    /// How lines longer than an editor is wide are shown.
+    pub wrap: Wrap,
+
+    /// The text shown at the start of each screen line that carries on a
+    ///   wrapped line.
+    pub wrap_indicator: String,
+
+    /// Whether documents are drawn with their syntax highlighted.
+    pub syntax: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Settings {
+        Settings {
+            tab_width: 4,
+            scroll_off: 0,
+            status_bar: true,
+            performance_bar: true,
+            swap_interval: Duration::from_secs(4),
+            theme: "default".to_string(),
+            colors: None,
+            cursor_line: false,
+            line_numbers: LineNumbers::Off,
+            wrap: Wrap::Off,
+            wrap_indicator: "↪ ".to_string(),
+            syntax: true,
+        }
+    }
+}
+
+impl Settings {
+    /// Gets the value of a setting.
+    ///
+    /// # Arguments
+    ///
+    /// * `setting` - The setting to get.
+    pub fn get(&self, setting: Setting) -> Value {
+        match setting {
+            Setting::TabWidth => Value::Number(self.tab_width),
+            Setting::ScrollOff => Value::Number(self.scroll_off as usize),
+            Setting::StatusBar => Value::Switch(self.status_bar),
+            Setting::PerformanceBar => Value::Switch(self.performance_bar),
+            Setting::SwapInterval => Value::Number(self.swap_interval.as_secs() as usize),
+            Setting::Theme => Value::Text(self.theme.clone()),
+            Setting::Colors => Value::Text(match self.colors {
+                None => "auto".to_string(),
+                Some(ColorSupport::Basic) => "16".to_string(),
+                Some(ColorSupport::Extended) => "256".to_string(),
+                Some(ColorSupport::TrueColor) => "truecolor".to_string(),
+            }),
+            Setting::CursorLine => Value::Switch(self.cursor_line),
+            Setting::LineNumbers => Value::Text(self.line_numbers.name().to_string()),
+            Setting::Wrap => Value::Text(self.wrap.name().to_string()),
+            Setting::WrapIndicator => Value::Text(self.wrap_indicator.clone()),
+            Setting::Syntax => Value::Switch(self.syntax),
+        }
+    }
+
+    /// Changes the value of a setting. Values of the wrong type are ignored,
+    ///   and numbers are kept within the range the setting allows.
+    ///
+    /// # Arguments
+    ///
+    /// * `setting` - The setting to change.
+    /// * `value` - The new value.
+    pub fn set(&mut self, setting: Setting, value: Value) {
+        match (setting, value) {
+            (Setting::TabWidth, Value::Number(number)) => self.tab_width = number.clamp(1, 32),
+            (Setting::ScrollOff, Value::Number(number)) =>
+                self.scroll_off = number.min(u16::MAX as usize) as u16,
+            (Setting::StatusBar, Value::Switch(switch)) => self.status_bar = switch,
+            (Setting::PerformanceBar, Value::Switch(switch)) => self.performance_bar = switch,
+            (Setting::SwapInterval, Value::Number(number)) =>
+                self.swap_interval = Duration::from_secs(number as u64),
+            (Setting::Theme, Value::Text(name)) => self.theme = name,
+            (Setting::Colors, Value::Text(name)) => self.colors = ColorSupport::from_name(&name),
+            (Setting::CursorLine, Value::Switch(switch)) => self.cursor_line = switch,
+            (Setting::LineNumbers, Value::Text(name)) => {
+                if let Some(line_numbers) = LineNumbers::from_name(&name) {
+                    self.line_numbers = line_numbers;
+                }
+            }
+            (Setting::Wrap, Value::Text(name)) => {
+                if let Some(wrap) = Wrap::from_name(&name) {
+                    self.wrap = wrap;
+                }
+            }
+            (Setting::WrapIndicator, Value::Text(text)) => self.wrap_indicator = text,
+            (Setting::Syntax, Value::Switch(switch)) => self.syntax = switch,
+            _ => {}
+        }
+    }
+}
+
+/// The settings from the config file, including its overrides for file types,
+///   along with changes made with `:set`.
+///
+/// Changes made with `:set` take precedence, and outlast reloads of the
+///   config file.
+pub struct Config {
+    /// Values from the config file that apply everywhere.
+    global: Vec<(Setting, Value)>,
+
+    /// Values from the config file that apply to one type of file, keyed by
+    ///   file type.
+    filetypes: HashMap<String, Vec<(Setting, Value)>>,
+
+    /// Values changed while the program runs.
+    runtime: Vec<(Setting, Value)>,
+}
+
+impl Config {
+    pub fn new() -> Config {
+        Config { global: Vec::new(), filetypes: HashMap::new(), runtime: Vec::new() }
+    }
+
+    /// Gets the settings that apply to a type of file.
+    ///
+    /// # Arguments
+    ///
+    /// * `filetype` - The type of file, or None for settings that apply
+    ///   everywhere.
+    pub fn settings(&self, filetype: Option<&str>) -> Settings {
+        let mut settings = Settings::default();
+        let overrides = filetype.and_then(|filetype| self.filetypes.get(filetype));
+        for (setting, value) in self.global.iter().chain(overrides.into_iter().flatten()).chain(&self.runtime) {
+            settings.set(*setting, value.clone());
+        }
+        settings
+    }
+
+    /// Changes a setting while the program runs.
+    ///
+    /// # Arguments
+    ///
+    /// * `setting` - The setting to change.
+    /// * `value` - The new value.
+    pub fn set(&mut self, setting: Setting, value: Value) {
+        self.runtime.retain(|(existing, _)| *existing != setting);
+        self.runtime.push((setting, value));
+    }
+
+    /// Reads the config file, replacing the values previously read from it.
+    ///   A missing file is not an error, and leaves only the defaults.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the config file.
+    pub fn load(&mut self, path: &Path) -> Result<(), String> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+        };
+        self.apply_file(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Replaces the values read from the config file with those in some TOML:
+    ///
+    /// ```toml
+    /// tabwidth = 4
+    /// statusbar = true
+    /// theme = "dusk"
+    ///
+    /// [filetype.markdown]
+    /// tabwidth = 2
+    /// ```
+    ///
+    /// Valid values are applied even if others are invalid. If the TOML can't
+    ///   be parsed at all, the values previously read are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The contents of a config file.
+    pub fn apply_file(&mut self, text: &str) -> Result<(), String> {
+        let config: toml::Table = text.parse().map_err(|e: toml::de::Error| e.message().trim().to_string())?;
+        let mut errors = Vec::new();
+        let mut global = Vec::new();
+        let mut filetypes = HashMap::new();
+
+        for (key, value) in &config {
+            match (key.as_str(), value) {
+                ("filetype", toml::Value::Table(sections)) => {
+                    for (filetype, section) in sections {
+                        let values = match section {
+                            toml::Value::Table(values) => values,
+                            _ => {
+                                errors.push(format!("filetype.{} must be a table", filetype));
+                                continue;
+                            }
+                        };
+                        let overrides: &mut Vec<_> = filetypes.entry(filetype.clone()).or_default();
+                        for (name, value) in values {
+                            match read_entry(name, value) {
+                                Ok((setting, _)) if setting.scope() == Scope::Global => errors.push(
+                                    format!("{} can't be set for a file type", setting.name())
+                                ),
+                                Ok(entry) => overrides.push(entry),
+                                Err(e) => errors.push(e),
+                            }
+                        }
+                    }
+                }
+                (name, value) => match read_entry(name, value) {
+                    Ok(entry) => global.push(entry),
+                    Err(e) => errors.push(e),
+                },
+            }
+        }
+
+        self.global = global;
+        self.filetypes = filetypes;
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+}
+
+/// Reads one setting from the config file.
+fn read_entry(name: &str, value: &toml::Value) -> Result<(Setting, Value), String> {
+    let setting = Setting::from_name(name).ok_or_else(|| format!("Unknown setting: {}", name))?;
+    let value = Value::from_toml(setting.kind(), value).map_err(|e| format!("{}: {}", name, e))?;
+    Ok((setting, value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// File type overrides apply over the global values, and changes made
+    ///   at runtime apply over both.
+    #[test]
+    fn layers_settings() {
+        let mut config = Config::new();
+        config.apply_file(concat!(
+            "tabwidth = 8\n",
+            "theme = \"dusk\"\n",
+            "[filetype.markdown]\n",
+            "tabwidth = 2\n",
+        )).unwrap();
+
+        assert_eq!(config.settings(None).tab_width, 8);
+        assert_eq!(config.settings(Some("markdown")).tab_width, 2);
+        assert_eq!(config.settings(None).theme, "dusk");
+
+        config.set(Setting::TabWidth, Value::Number(3));
+        assert_eq!(config.settings(Some("markdown")).tab_width, 3);
+
+        // Reloading keeps runtime changes
+        config.apply_file("scrolloff = 5\n").unwrap();
+        assert_eq!(config.settings(None).scroll_off, 5);
+        assert_eq!(config.settings(None).tab_width, 3);
+        assert_eq!(config.settings(None).theme, "default");
+    }
+
+    /// Invalid entries are reported, while valid ones still apply.
+    #[test]
+    fn reports_invalid_entries() {
+        let mut config = Config::new();
+        let result = config.apply_file(concat!(
+            "tabwidth = \"wide\"\n",
+            "statusbar = false\n",
+            "colour = 1\n",
+            "[filetype.rust]\n",
+            "statusbar = true\n",
+        ));
+
+        assert_eq!(result, Err(concat!(
+            "Unknown setting: colour; ",
+            "statusbar can't be set for a file type; ",
+            "tabwidth: expected a whole number"
+        ).to_string()));
+        assert!(!config.settings(None).status_bar);
+        assert!(config.apply_file("tabwidth = ").is_err());
+        assert!(!config.settings(None).status_bar);
+    }
+}
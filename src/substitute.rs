@@ -0,0 +1,432 @@
+use std::ops::Range;
+use regex::{Captures, Regex};
+
+use crate::document::DocumentHandle;
+
+/// The parts of a `:s` command line after its name, written
+///   `/pattern/replacement/flags`. Any punctuation can stand in for `/`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SubstituteArgs {
+    /// The pattern to replace. An empty pattern stands for the last search.
+    pub pattern: String,
+
+    /// The replacement, before references to the match are filled in.
+    pub replacement: String,
+
+    /// Replace every match on a line, rather than only the first (`g`).
+    pub global: bool,
+
+    /// Ask before making each replacement (`c`).
+    pub confirm: bool,
+
+    /// Ignore case (`i`), or match it (`I`), rather than ignoring it unless
+    ///   the pattern has an uppercase letter.
+    pub ignore_case: Option<bool>,
+}
+
+/// Gets what follows the name of a `:s` command line, if the line is one.
+///
+/// # Arguments
+///
+/// * `line` - The command line, after any range.
+pub fn strip_name(line: &str) -> Option<&str> {
+    let name_len = line.find(|c: char| !c.is_ascii_alphabetic()).unwrap_or(line.len());
+    let (name, rest) = line.split_at(name_len);
+    match name {
+        "s" | "substitute" => Some(rest),
+        _ => None,
+    }
+}
+
+/// Parses the parts of a `:s` command line after its name.
+///
+/// The replacement and the closing delimiter can be left out, which replaces
+///   matches with nothing.
+///
+/// # Arguments
+///
+/// * `text` - The text after the name, starting with the delimiter.
+pub fn parse(text: &str) -> Result<SubstituteArgs, String> {
+    let mut chars = text.chars();
+    let delimiter = match chars.next() {
+        Some(c) if !c.is_alphanumeric() && !c.is_whitespace() && c != '\\' && c != '"' => c,
+        _ => return Err("Usage: :[range]s/pattern/replacement/[flags]".to_string()),
+    };
+    let pattern = take_part(&mut chars, delimiter);
+    let replacement = take_part(&mut chars, delimiter);
+
+    let mut args = SubstituteArgs { pattern, replacement, ..SubstituteArgs::default() };
+    for flag in chars.filter(|c| !c.is_whitespace()) {
+        match flag {
+            'g' => args.global = true,
+            'c' => args.confirm = true,
+            'i' => args.ignore_case = Some(true),
+            'I' => args.ignore_case = Some(false),
+            flag => return Err(format!("Unknown flag: {}", flag)),
+        }
+    }
+    Ok(args)
+}
+
+/// Takes text up to the next unescaped delimiter, removing the backslashes
+///   from escaped delimiters. Other escapes are kept for the regex or the
+///   replacement to interpret.
+fn take_part(chars: &mut std::str::Chars, delimiter: char) -> String {
+    let mut part = String::new();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c == delimiter => break,
+            '\\' => match chars.next() {
+                Some(c) if c == delimiter => part.push(c),
+                Some(c) => {
+                    part.push('\\');
+                    part.push(c);
+                }
+                None => part.push('\\'),
+            },
+            c => part.push(c),
+        }
+    }
+    part
+}
+
+/// A piece of a replacement.
+#[derive(Clone, Debug, PartialEq)]
+enum Piece {
+    Text(String),
+    /// A capture group of the match, where 0 is the whole match.
+    Group(usize),
+    /// A line break in the document's line ending.
+    LineBreak,
+}
+
+/// The text that replaces each match, which may copy parts of the match.
+///
+/// `&` and `\0` stand for the whole match, `\1` to `\9` for its capture
+///   groups, and `\n` or `\r` for a line break. `\&`, `\\` and `\t` are a
+///   literal `&`, a backslash and a tab.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Replacement {
+    pieces: Vec<Piece>,
+}
+
+impl Replacement {
+    /// Parses a replacement.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The replacement, as typed.
+    pub fn parse(text: &str) -> Replacement {
+        let mut pieces = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars();
+        while let Some(c) = chars.next() {
+            let piece = match c {
+                '&' => Piece::Group(0),
+                '\\' => match chars.next() {
+                    Some(digit @ '0'..='9') => Piece::Group(digit as usize - '0' as usize),
+                    Some('n') | Some('r') => Piece::LineBreak,
+                    Some('t') => {
+                        literal.push('\t');
+                        continue;
+                    }
+                    Some(c) => {
+                        literal.push(c);
+                        continue;
+                    }
+                    None => {
+                        literal.push('\\');
+                        continue;
+                    }
+                },
+                c => {
+                    literal.push(c);
+                    continue;
+                }
+            };
+            if !literal.is_empty() {
+                pieces.push(Piece::Text(std::mem::take(&mut literal)));
+            }
+            pieces.push(piece);
+        }
+        if !literal.is_empty() {
+            pieces.push(Piece::Text(literal));
+        }
+        Replacement { pieces }
+    }
+
+    /// Gets the highest capture group the replacement refers to.
+    pub fn max_group(&self) -> usize {
+        self.pieces
+            .iter()
+            .filter_map(|piece| match piece {
+                Piece::Group(group) => Some(*group),
+                _ => None,
+            })
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Builds the text that replaces a match.
+    ///
+    /// # Arguments
+    ///
+    /// * `captures` - The match, and its capture groups.
+    /// * `line_break` - The text of a line break in the document.
+    pub fn expand(&self, captures: &Captures, line_break: &str) -> String {
+        let mut text = String::new();
+        for piece in &self.pieces {
+            match piece {
+                Piece::Text(literal) => text.push_str(literal),
+                Piece::Group(group) => text.push_str(captures.get(*group).map_or("", |m| m.as_str())),
+                Piece::LineBreak => text.push_str(line_break),
+            }
+        }
+        text
+    }
+}
+
+/// A substitution working through a range of lines, one match at a time, so
+///   that each replacement can be confirmed.
+///
+/// Matches don't run over line breaks, as each line is searched on its own.
+pub struct Substitution {
+    document: DocumentHandle,
+    regex: Regex,
+    replacement: Replacement,
+    /// Whether every match on a line is replaced, rather than only the first.
+    global: bool,
+    /// The char index the next match is looked for from.
+    from: usize,
+    /// An empty match here is passed over, as it touches the match before.
+    skip_empty_at: Option<usize>,
+    /// The line after the last line to look in. It moves as replacements add
+    ///   or remove line breaks.
+    end_line: usize,
+    /// Whether every line has been looked through.
+    done: bool,
+    /// The match waiting to be replaced or skipped, and its replacement.
+    current: Option<(Range<usize>, String)>,
+    /// The number of replacements made.
+    count: usize,
+    /// The number of lines replacements were made on.
+    lines_changed: usize,
+    /// The line the latest replacement was made on.
+    last_line: Option<usize>,
+}
+
+impl Substitution {
+    /// Starts a substitution. Nothing is replaced until asked.
+    ///
+    /// # Arguments
+    ///
+    /// * `document` - The document to make replacements in.
+    /// * `regex` - The pattern to replace.
+    /// * `replacement` - The text to replace matches with.
+    /// * `global` - Whether to replace every match on a line.
+    /// * `lines` - The lines to make replacements on.
+    pub fn new(document: DocumentHandle, regex: Regex, replacement: Replacement, global: bool, lines: Range<usize>) -> Substitution {
+        let from = document.borrow().line_to_char(lines.start);
+        Substitution {
+            document,
+            regex,
+            replacement,
+            global,
+            from,
+            skip_empty_at: None,
+            end_line: lines.end,
+            done: lines.is_empty(),
+            current: None,
+            count: 0,
+            lines_changed: 0,
+            last_line: None,
+        }
+    }
+
+    /// Gets the document replacements are made in.
+    pub fn document(&self) -> &DocumentHandle {
+        &self.document
+    }
+
+    /// Gets the pattern being replaced.
+    pub fn pattern(&self) -> &str {
+        self.regex.as_str()
+    }
+
+    /// Gets the match waiting to be replaced or skipped.
+    pub fn current(&self) -> Option<Range<usize>> {
+        self.current.as_ref().map(|(range, _)| range.clone())
+    }
+
+    /// Gets the text that would replace the current match.
+    pub fn current_replacement(&self) -> Option<&str> {
+        self.current.as_ref().map(|(_, text)| text.as_str())
+    }
+
+    /// Gets the number of replacements made.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    /// Gets the number of lines replacements were made on.
+    pub fn lines_changed(&self) -> usize {
+        self.lines_changed
+    }
+
+    /// Gets the line the latest replacement was made on.
+    pub fn last_line(&self) -> Option<usize> {
+        self.last_line
+    }
+
+    /// Finds the next match, which becomes the current match.
+    ///
+    /// Returns None once there are no more matches.
+    pub fn find_next(&mut self) -> Option<Range<usize>> {
+        if self.current.is_none() {
+            self.current = self.search();
+        }
+        self.current()
+    }
+
+    /// Replaces the current match, then moves on.
+    pub fn replace(&mut self) {
+        let (range, text) = match self.current.take() {
+            Some(current) => current,
+            None => return,
+        };
+        let mut document = self.document.borrow_mut();
+        let line_ix = document.char_to_line(range.start);
+        let line_count = document.line_count();
+        document.replace(range.clone(), &text);
+        self.end_line = (self.end_line + document.line_count()).saturating_sub(line_count);
+        drop(document);
+
+        self.count += 1;
+        if self.last_line != Some(line_ix) {
+            self.lines_changed += 1;
+        }
+        self.last_line = Some(line_ix);
+        self.move_past(range.start + text.chars().count());
+    }
+
+    /// Leaves the current match as it is, and moves on.
+    pub fn skip(&mut self) {
+        if let Some((range, _)) = self.current.take() {
+            self.move_past(range.end);
+        }
+    }
+
+    /// Replaces the current match and every match after it.
+    pub fn replace_all(&mut self) {
+        while self.find_next().is_some() {
+            self.replace();
+        }
+    }
+
+    /// Moves on from a match that ended at a char index, to the next line
+    ///   unless every match on a line is replaced.
+    fn move_past(&mut self, char_ix: usize) {
+        self.from = char_ix;
+        self.skip_empty_at = Some(char_ix);
+        if !self.global {
+            self.next_line();
+        }
+    }
+
+    /// Moves on to the start of the line after the one being looked in.
+    fn next_line(&mut self) {
+        let document = self.document.borrow();
+        let line_ix = document.char_to_line(self.from) + 1;
+        if line_ix >= self.end_line.min(document.line_count()) {
+            self.done = true;
+        } else {
+            self.from = document.line_to_char(line_ix);
+            self.skip_empty_at = None;
+        }
+    }
+
+    /// Looks for the next match from where the substitution has got to,
+    ///   working out its replacement.
+    fn search(&mut self) -> Option<(Range<usize>, String)> {
+        while !self.done {
+            let document = self.document.borrow();
+            let line_ix = document.char_to_line(self.from);
+            let line_start = document.line_to_char(line_ix);
+            let line = document.line(line_ix);
+            let column_ix = self.from - line_start;
+            let byte_ix = line.char_indices().nth(column_ix).map_or(line.len(), |(ix, _)| ix);
+
+            if let Some(captures) = self.regex.captures_at(&line, byte_ix) {
+                let m = captures.get(0).unwrap();
+                let start = line_start + line[..m.start()].chars().count();
+                if m.range().is_empty() && self.skip_empty_at == Some(start) && m.start() < line.len() {
+                    // Look again from the next char, so an empty match can't repeat
+                    self.from = start + 1;
+                    self.skip_empty_at = None;
+                    continue;
+                }
+                if !(m.range().is_empty() && self.skip_empty_at == Some(start)) {
+                    let end = start + m.as_str().chars().count();
+                    let text = self.replacement.expand(&captures, document.line_ending().as_str());
+                    return Some((start..end, text));
+                }
+            }
+            drop(document);
+            self.next_line();
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document::TextDocument;
+    use crate::search::build_regex;
+
+    /// Substitutes through a whole document.
+    fn substitute(text: &str, pattern: &str, replacement: &str, global: bool) -> (String, usize) {
+        let document = TextDocument::new(text).into_handle();
+        let lines = 0..document.borrow().line_count();
+        let regex = build_regex(pattern, false).unwrap();
+        let mut substitution = Substitution::new(document.clone(), regex, Replacement::parse(replacement), global, lines);
+        substitution.replace_all();
+        let content = document.borrow().get_content();
+        (content, substitution.count())
+    }
+
+    /// Command lines are split into pattern, replacement and flags, with
+    ///   escaped delimiters kept in the parts.
+    #[test]
+    fn parses_command_lines() {
+        assert_eq!(strip_name("s/a/b/"), Some("/a/b/"));
+        assert_eq!(strip_name("substitute#a#b"), Some("#a#b"));
+        assert_eq!(strip_name("set"), None);
+
+        let args = parse(r"/a\/b/c\1/gc").unwrap();
+        assert_eq!(args.pattern, "a/b");
+        assert_eq!(args.replacement, r"c\1");
+        assert!(args.global && args.confirm);
+        assert_eq!(parse("#x").unwrap().replacement, "");
+        assert_eq!(parse("/a/b/z"), Err("Unknown flag: z".to_string()));
+    }
+
+    /// Replacements copy capture groups and the whole match.
+    #[test]
+    fn expands_capture_groups() {
+        assert_eq!(substitute("key = value\n", r"(\w+) = (\w+)", r"\2: \1 (&)", false).0, "value: key (key = value)\n");
+        assert_eq!(substitute("a,b\n", ",", r"\n", false).0, "a\nb\n");
+        assert_eq!(substitute("a&b\n", "&", r"\&\\", false).0, "a&\\b\n");
+    }
+
+    /// Only the first match on each line is replaced, unless every match is
+    ///   asked for, and empty matches don't repeat.
+    #[test]
+    fn replaces_matches_on_each_line() {
+        assert_eq!(substitute("aa\naa\n", "a", "b", false), ("ba\nba\n".to_string(), 2));
+        assert_eq!(substitute("aa\naa\n", "a", "b", true), ("bb\nbb\n".to_string(), 4));
+        assert_eq!(substitute("one\ntwo", "^", "# ", false).0, "# one\n# two");
+        assert_eq!(substitute("xxa", "x*", "-", true).0, "-a-");
+        assert_eq!(substitute("a\nb", "$", ";", true).0, "a;\nb;");
+    }
+}
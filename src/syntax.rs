@@ -0,0 +1,414 @@
+mod grammars;
+
+use std::ops::Range;
+use regex::Regex;
+
+/// A run of chars in a line drawn in one highlight group.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Span {
+    /// The chars of the line, counting from its start.
+    pub chars: Range<usize>,
+
+    /// The highlight group the chars are drawn in, such as `syntax.keyword`.
+    pub group: &'static str,
+}
+
+/// How a grammar picks out one kind of token, as written in the grammar
+///   tables.
+pub struct RuleDef {
+    group: &'static str,
+    pattern: &'static str,
+    end: Option<&'static str>,
+    escapes: bool,
+}
+
+/// A rule matching a token within a line. If the pattern has a capture
+///   group, only that part of the match is highlighted, though the whole
+///   match is passed over.
+const fn token(group: &'static str, pattern: &'static str) -> RuleDef {
+    RuleDef { group, pattern, end: None, escapes: false }
+}
+
+/// A rule matching a region that runs from its start pattern to its end
+///   pattern, which may be on a later line.
+const fn region(group: &'static str, start: &'static str, end: &'static str) -> RuleDef {
+    RuleDef { group, pattern: start, end: Some(end), escapes: false }
+}
+
+/// Like `region`, but a backslash stops the next char ending the region, as
+///   in a quoted string.
+const fn string(group: &'static str, start: &'static str, end: &'static str) -> RuleDef {
+    RuleDef { group, pattern: start, end: Some(end), escapes: true }
+}
+
+/// A compiled rule of a grammar.
+struct Rule {
+    group: &'static str,
+    start: Regex,
+    /// The pattern ending the region the rule starts, if it starts one. A
+    ///   match starting with a backslash is an escape, and doesn't count.
+    end: Option<Regex>,
+    escapes: bool,
+}
+
+/// Where a rule next matches in a line, as byte offsets.
+#[derive(Clone)]
+struct Found {
+    start: usize,
+    end: usize,
+    /// The part of the match that is highlighted.
+    styled: Range<usize>,
+}
+
+impl Rule {
+    /// Finds the next match of the rule's start pattern.
+    fn find(&self, line: &str, at: usize) -> Option<Found> {
+        let captures = self.start.captures_at(line, at)?;
+        let whole = captures.get(0)?;
+        let styled = captures.get(1).unwrap_or(whole).range();
+        Some(Found { start: whole.start(), end: whole.end(), styled })
+    }
+
+    /// Finds the byte offset just past the end of the rule's region, if it
+    ///   ends in the line.
+    fn find_end(&self, line: &str, mut at: usize) -> Option<usize> {
+        let end = self.end.as_ref()?;
+        while let Some(found) = end.find_at(line, at) {
+            if self.escapes && found.as_str().starts_with('\\') {
+                at = found.end();
+                continue;
+            }
+            return Some(found.end());
+        }
+        None
+    }
+}
+
+/// The rules for highlighting one type of file.
+///
+/// At each point in a line, the rule matching earliest wins, with ties going
+///   to the rule listed first. Text no rule matches is drawn as plain text.
+pub struct Grammar {
+    name: &'static str,
+    rules: Vec<Rule>,
+}
+
+impl Grammar {
+    /// Gets the grammar for a type of file, or None if there isn't one.
+    ///
+    /// # Arguments
+    ///
+    /// * `filetype` - The type of file, as detected from its name.
+    pub fn for_filetype(filetype: &str) -> Option<Grammar> {
+        let (name, defs) = grammars::GRAMMARS.iter().find(|(name, _)| *name == filetype)?;
+        let rules = defs.iter().map(|def| Rule {
+            group: def.group,
+            start: Regex::new(def.pattern).expect("Invalid grammar pattern"),
+            end: def.end.map(|end| {
+                let end = if def.escapes { format!(r"\\.|{}", end) } else { end.to_string() };
+                Regex::new(&end).expect("Invalid grammar pattern")
+            }),
+            escapes: def.escapes,
+        }).collect();
+        Some(Grammar { name, rules })
+    }
+
+    /// Gets the type of file the grammar highlights.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Splits a line into highlighted spans.
+    ///
+    /// Returns the spans, in order, along with the region left open at the
+    ///   end of the line, which the next line starts in.
+    ///
+    /// # Arguments
+    ///
+    /// * `line` - The text of the line, without its line break.
+    /// * `state` - The region open at the start of the line, as the index of
+    ///   the rule that started it.
+    pub fn highlight_line(&self, line: &str, state: Option<usize>) -> (Vec<Span>, Option<usize>) {
+        // Spans as byte ranges, turned into char ranges at the end
+        let mut spans: Vec<(Range<usize>, &'static str)> = Vec::new();
+        let mut pos = 0;
+        let mut state = state;
+
+        if let Some(rule) = state.and_then(|rule_ix| self.rules.get(rule_ix)) {
+            match rule.find_end(line, 0) {
+                Some(end) => {
+                    spans.push((0..end, rule.group));
+                    pos = end;
+                    state = None;
+                }
+                None => {
+                    if !line.is_empty() {
+                        spans.push((0..line.len(), rule.group));
+                    }
+                    return (to_char_spans(line, spans), state);
+                }
+            }
+        }
+
+        // Each rule's next match, found again only once it has been passed
+        let mut next: Vec<Option<Found>> = self.rules.iter().map(|rule| rule.find(line, pos)).collect();
+        while pos < line.len() {
+            for (rule, found) in self.rules.iter().zip(next.iter_mut()) {
+                if found.as_ref().is_some_and(|found| found.start < pos) {
+                    *found = rule.find(line, pos);
+                }
+            }
+            let best = next.iter().enumerate()
+                .filter_map(|(rule_ix, found)| found.as_ref().map(|found| (rule_ix, found)))
+                .min_by_key(|(rule_ix, found)| (found.start, *rule_ix));
+            let (rule_ix, found) = match best {
+                Some((rule_ix, found)) => (rule_ix, found.clone()),
+                None => break,
+            };
+            let rule = &self.rules[rule_ix];
+
+            if found.end == found.start {
+                // An empty match can't move on, so step past the char it's at
+                pos = found.start + line[found.start..].chars().next().map_or(1, char::len_utf8);
+                continue;
+            }
+            if rule.end.is_none() {
+                if !found.styled.is_empty() {
+                    spans.push((found.styled, rule.group));
+                }
+                pos = found.end;
+                continue;
+            }
+            match rule.find_end(line, found.end) {
+                Some(end) => {
+                    spans.push((found.start..end, rule.group));
+                    pos = end;
+                }
+                None => {
+                    spans.push((found.start..line.len(), rule.group));
+                    state = Some(rule_ix);
+                    break;
+                }
+            }
+        }
+        (to_char_spans(line, spans), state)
+    }
+}
+
+/// Turns byte ranges of a line into spans of chars.
+fn to_char_spans(line: &str, spans: Vec<(Range<usize>, &'static str)>) -> Vec<Span> {
+    // Spans are in order, so chars are counted from the end of the last one
+    let mut last = (0, 0);
+    let mut char_ix = |byte_ix: usize| {
+        last = (byte_ix, last.1 + line[last.0..byte_ix].chars().count());
+        last.1
+    };
+    spans.into_iter()
+        .map(|(bytes, group)| Span { chars: char_ix(bytes.start)..char_ix(bytes.end), group })
+        .collect()
+}
+
+/// A highlighted line, kept until the line is edited.
+struct HighlightedLine {
+    /// The region open at the start of the line.
+    start_state: Option<usize>,
+
+    /// The region left open at the end of the line.
+    end_state: Option<usize>,
+
+    spans: Vec<Span>,
+}
+
+/// Highlights the lines of a document, keeping the spans of each line so
+///   that only lines that are edited, or that start in a different region
+///   because of an edit above them, are highlighted again.
+pub struct Highlighter {
+    grammar: Grammar,
+
+    /// The highlighted lines, or None for lines not yet highlighted since
+    ///   they were loaded or edited.
+    lines: Vec<Option<HighlightedLine>>,
+
+    /// The lines before this one are known to be highlighted correctly.
+    valid_to: usize,
+
+    /// Counts the lines highlighted, to show how much work edits cause.
+    #[cfg(test)]
+    lines_highlighted: usize,
+}
+
+impl Highlighter {
+    /// Returns a new highlighter for a type of file, or None if there's no
+    ///   grammar for it.
+    ///
+    /// # Arguments
+    ///
+    /// * `filetype` - The type of file, as detected from its name.
+    pub fn new(filetype: &str) -> Option<Highlighter> {
+        Some(Highlighter {
+            grammar: Grammar::for_filetype(filetype)?,
+            lines: Vec::new(),
+            valid_to: 0,
+            #[cfg(test)]
+            lines_highlighted: 0,
+        })
+    }
+
+    /// Gets the type of file being highlighted.
+    pub fn filetype(&self) -> &'static str {
+        self.grammar.name()
+    }
+
+    /// Gets the number of lines highlighted so far.
+    #[cfg(test)]
+    pub fn lines_highlighted(&self) -> usize {
+        self.lines_highlighted
+    }
+
+    /// Notes that lines of the document have been replaced, so that they are
+    ///   highlighted again when next drawn.
+    ///
+    /// # Arguments
+    ///
+    /// * `replaced` - The lines as they were before the edit.
+    /// * `inserted` - The number of lines now in their place.
+    pub fn edit(&mut self, replaced: Range<usize>, inserted: usize) {
+        self.valid_to = self.valid_to.min(replaced.start);
+        if replaced.start >= self.lines.len() {
+            return;
+        }
+        let end = replaced.end.min(self.lines.len());
+        self.lines.splice(replaced.start..end, (0..inserted).map(|_| None));
+    }
+
+    /// Gets the spans of a range of lines, highlighting any lines that need
+    ///   it from the last line known to be correct.
+    ///
+    /// # Arguments
+    ///
+    /// * `lines` - The lines to get the spans of, which must be in the
+    ///   document.
+    /// * `line` - Gets the text of a line, without its line break.
+    pub fn highlight(&mut self, lines: Range<usize>, line: impl Fn(usize) -> String) -> Vec<Vec<Span>> {
+        let mut state = match self.valid_to {
+            0 => None,
+            valid_to => self.lines[valid_to - 1].as_ref().and_then(|line| line.end_state),
+        };
+        for line_ix in self.valid_to..lines.end {
+            if line_ix == self.lines.len() {
+                self.lines.push(None);
+            }
+            match &self.lines[line_ix] {
+                // Lines starting as they did before are unchanged
+                Some(highlighted) if highlighted.start_state == state => state = highlighted.end_state,
+                _ => {
+                    let (spans, end_state) = self.grammar.highlight_line(&line(line_ix), state);
+                    self.lines[line_ix] = Some(HighlightedLine { start_state: state, end_state, spans });
+                    #[cfg(test)]
+                    {
+                        self.lines_highlighted += 1;
+                    }
+                    state = end_state;
+                }
+            }
+        }
+        self.valid_to = self.valid_to.max(lines.end);
+
+        lines.map(|line_ix| match self.lines.get(line_ix) {
+            Some(Some(highlighted)) => highlighted.spans.clone(),
+            _ => Vec::new(),
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gets the text and group of each span of a line.
+    fn tokens(grammar: &Grammar, line: &str, state: Option<usize>) -> Vec<(String, &'static str)> {
+        let (spans, _) = grammar.highlight_line(line, state);
+        spans.into_iter()
+            .map(|span| (line.chars().skip(span.chars.start).take(span.chars.len()).collect(), span.group))
+            .collect()
+    }
+
+    /// Lines are split into tokens of their highlight groups, with captures
+    ///   narrowing what is highlighted, and chars counted rather than bytes.
+    #[test]
+    fn highlights_tokens() {
+        let rust = Grammar::for_filetype("rust").unwrap();
+        assert_eq!(tokens(&rust, "pub fn main() { let s = \"é\\\"\"; } // done", None), vec![
+            ("pub".to_string(), "syntax.keyword"),
+            ("fn".to_string(), "syntax.keyword"),
+            ("main".to_string(), "syntax.function"),
+            ("let".to_string(), "syntax.keyword"),
+            ("\"é\\\"\"".to_string(), "syntax.string"),
+            ("// done".to_string(), "syntax.comment"),
+        ]);
+        let (spans, _) = rust.highlight_line("\"é\" x", None);
+        assert_eq!(spans, vec![Span { chars: 0..3, group: "syntax.string" }]);
+
+        assert!(Grammar::for_filetype("text").is_none());
+    }
+
+    /// Regions left open at the end of a line carry on into the next.
+    #[test]
+    fn carries_regions_between_lines() {
+        let rust = Grammar::for_filetype("rust").unwrap();
+        let (spans, state) = rust.highlight_line("let x = 1; /* start", None);
+        assert_eq!(spans.last(), Some(&Span { chars: 11..19, group: "syntax.comment" }));
+        assert!(state.is_some());
+
+        let (spans, state) = rust.highlight_line("still inside", state);
+        assert_eq!(spans, vec![Span { chars: 0..12, group: "syntax.comment" }]);
+        assert!(state.is_some());
+
+        assert_eq!(tokens(&rust, "end */ fn", state), vec![
+            ("end */".to_string(), "syntax.comment"),
+            ("fn".to_string(), "syntax.keyword"),
+        ]);
+    }
+
+    /// Edits only cause the lines they touch to be highlighted again, unless
+    ///   they change the region later lines start in.
+    #[test]
+    fn highlights_incrementally() {
+        let mut text: Vec<String> = (0..100).map(|ix| format!("let x{} = {};", ix, ix)).collect();
+        let mut highlighter = Highlighter::new("rust").unwrap();
+
+        // Only lines up to those asked for are highlighted
+        highlighter.highlight(0..10, |line_ix| text[line_ix].clone());
+        assert_eq!(highlighter.lines_highlighted(), 10);
+        highlighter.highlight(0..100, |line_ix| text[line_ix].clone());
+        assert_eq!(highlighter.lines_highlighted(), 100);
+
+        // Editing a line near the top highlights just that line
+        text[1] = "let y = 2;".to_string();
+        highlighter.edit(1..2, 1);
+        let spans = highlighter.highlight(90..100, |line_ix| text[line_ix].clone());
+        assert_eq!(highlighter.lines_highlighted(), 101);
+        assert_eq!(spans[0][0], Span { chars: 0..3, group: "syntax.keyword" });
+
+        // Inserting lines shifts the lines after them
+        text.insert(2, "// new".to_string());
+        highlighter.edit(2..2, 1);
+        let spans = highlighter.highlight(0..4, |line_ix| text[line_ix].clone());
+        assert_eq!(highlighter.lines_highlighted(), 102);
+        assert_eq!(spans[2], vec![Span { chars: 0..6, group: "syntax.comment" }]);
+        assert_eq!(spans[3][1], Span { chars: 9..10, group: "syntax.number" });
+
+        // Opening a comment changes how every later line is highlighted
+        text[0] = "/* open".to_string();
+        highlighter.edit(0..1, 1);
+        let spans = highlighter.highlight(100..101, |line_ix| text[line_ix].clone());
+        assert_eq!(highlighter.lines_highlighted(), 203);
+        assert_eq!(spans[0], vec![Span { chars: 0..text[100].len(), group: "syntax.comment" }]);
+
+        // Closing it again changes them back
+        text[0] = "/* open */".to_string();
+        highlighter.edit(0..1, 1);
+        highlighter.highlight(0..101, |line_ix| text[line_ix].clone());
+        assert_eq!(highlighter.lines_highlighted(), 304);
+    }
+}
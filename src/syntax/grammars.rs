@@ -0,0 +1,163 @@
+use super::{region, string, token, RuleDef};
+
+/// The grammars, keyed by the type of file they highlight.
+pub const GRAMMARS: &[(&str, &[RuleDef])] = &[
+    ("rust", RUST),
+    ("toml", TOML),
+    ("markdown", MARKDOWN),
+    ("json", JSON),
+    ("shell", SHELL),
+];
+
+const RUST: &[RuleDef] = &[
+    region("syntax.comment", r"/\*", r"\*/"),
+    token("syntax.comment", r"//.*"),
+    region("syntax.string", r##"\bb?r#+""##, r##""#+"##),
+    region("syntax.string", r#"\bb?r""#, r#"""#),
+    string("syntax.string", r#"\bb?"|""#, r#"""#),
+    token("syntax.string", r"\bb?'(?:[^'\\]|\\u\{[0-9a-fA-F]+\}|\\.)'|'(?:[^'\\]|\\u\{[0-9a-fA-F]+\}|\\.)'"),
+    token("syntax.type", r"'[A-Za-z_]\w*"),
+    token("syntax.attribute", r"#!?\[[^\]]*\]?"),
+    token("syntax.macro", r"\b([A-Za-z_]\w*!)[\s(\[{]"),
+    token("syntax.keyword", r"\b(?:as|async|await|break|const|continue|crate|dyn|else|enum|extern|fn|for|if|impl|in|let|loop|match|mod|move|mut|pub|ref|return|self|Self|static|struct|super|trait|type|unsafe|use|where|while)\b"),
+    token("syntax.constant", r"\b(?:true|false)\b|\b[A-Z][A-Z0-9_]+\b"),
+    token("syntax.type", r"\b(?:[iu](?:8|16|32|64|128|size)|f32|f64|bool|char|str)\b|\b[A-Z]\w*"),
+    token("syntax.number", r"\b(?:0x[0-9a-fA-F_]+|0o[0-7_]+|0b[01_]+|[0-9][0-9_]*(?:\.[0-9][0-9_]*)?(?:[eE][+-]?[0-9_]+)?)(?:[iu](?:8|16|32|64|128|size)|f32|f64)?\b"),
+    token("syntax.function", r"\b([a-z_]\w*)\s*(?:::<[^>]*>)?\("),
+];
+
+const TOML: &[RuleDef] = &[
+    token("syntax.comment", r"#.*"),
+    token("syntax.type", r"^\s*\[\[?[^\]]*\]\]?"),
+    string("syntax.string", r#"""""#, r#"""""#),
+    region("syntax.string", r"'''", r"'''"),
+    token("syntax.attribute", r#"([A-Za-z0-9_.-]+|"(?:[^"\\]|\\.)*")\s*="#),
+    string("syntax.string", r#"""#, r#"""#),
+    token("syntax.string", r"'[^']*'"),
+    token("syntax.constant", r"\b(?:true|false|inf|nan)\b"),
+    token("syntax.number", r"\b\d{4}-\d{2}-\d{2}(?:[T ]\d{2}:\d{2}:\d{2}(?:\.\d+)?(?:Z|[+-]\d{2}:\d{2})?)?|\b\d{2}:\d{2}:\d{2}(?:\.\d+)?"),
+    token("syntax.number", r"[+-]?\b(?:0x[0-9a-fA-F_]+|0o[0-7_]+|0b[01_]+|\d[\d_]*(?:\.\d[\d_]*)?(?:[eE][+-]?\d+)?)\b"),
+];
+
+const MARKDOWN: &[RuleDef] = &[
+    region("syntax.code", r"^\s*```", r"^\s*```"),
+    region("syntax.comment", r"<!--", r"-->"),
+    token("syntax.heading", r"^#{1,6}(?:\s.*)?$"),
+    token("syntax.code", r"``[^`]+``|`[^`]+`"),
+    token("syntax.link", r"!?\[[^\]]*\]\([^)]*\)|<https?://[^>]+>"),
+    token("syntax.strong", r"\*\*[^*]+\*\*|\b__[^_]+__\b"),
+    token("syntax.emphasis", r"\*[^*\s][^*]*\*|\b_[^_]+_\b"),
+    token("syntax.punctuation", r"^\s*(?:[-*+]|\d+[.)])\s|^\s*>"),
+];
+
+const JSON: &[RuleDef] = &[
+    token("syntax.attribute", r#"("(?:[^"\\]|\\.)*")\s*:"#),
+    token("syntax.string", r#""(?:[^"\\]|\\.)*"?"#),
+    token("syntax.constant", r"\b(?:true|false|null)\b"),
+    token("syntax.number", r"-?\b\d+(?:\.\d+)?(?:[eE][+-]?\d+)?\b"),
+];
+
+const SHELL: &[RuleDef] = &[
+    token("syntax.variable", r"\$(?:\{[^}]*\}|[A-Za-z_]\w*|[0-9#?@*!$-])"),
+    // Only a `#` starting a word starts a comment
+    token("syntax.comment", r"\B#.*"),
+    string("syntax.string", r#"""#, r#"""#),
+    region("syntax.string", r"'", r"'"),
+    token("syntax.keyword", r"\b(?:if|then|else|elif|fi|for|while|until|do|done|case|esac|in|function|select|return|break|continue|local|export|readonly|declare|unset|shift|exit)\b"),
+    token("syntax.function", r"\b([A-Za-z_][\w-]*)\s*\(\)"),
+    token("syntax.number", r"\b\d+\b"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::super::Grammar;
+
+    /// Gets the text and group of each span of a line, highlighted with a
+    ///   file type's grammar.
+    fn tokens(filetype: &str, line: &str) -> Vec<(String, &'static str)> {
+        let grammar = Grammar::for_filetype(filetype).unwrap();
+        let (spans, _) = grammar.highlight_line(line, None);
+        spans.into_iter()
+            .map(|span| (line.chars().skip(span.chars.start).take(span.chars.len()).collect(), span.group))
+            .collect()
+    }
+
+    /// Gets the text and group of each span, as string slices.
+    fn pairs<'a>(tokens: &'a [(String, &'static str)]) -> Vec<(&'a str, &'static str)> {
+        tokens.iter().map(|(text, group)| (text.as_str(), *group)).collect()
+    }
+
+    /// Each grammar picks out the tokens of its language.
+    #[test]
+    fn highlights_each_language() {
+        assert_eq!(pairs(&tokens("rust", "#[derive(Debug)] struct Point<'a> { x: Vec<u8> }")), vec![
+            ("#[derive(Debug)]", "syntax.attribute"),
+            ("struct", "syntax.keyword"),
+            ("Point", "syntax.type"),
+            ("'a", "syntax.type"),
+            ("Vec", "syntax.type"),
+            ("u8", "syntax.type"),
+        ]);
+        assert_eq!(pairs(&tokens("rust", "println!(\"{}\", MAX + 0x1F + 'c');")), vec![
+            ("println!", "syntax.macro"),
+            ("\"{}\"", "syntax.string"),
+            ("MAX", "syntax.constant"),
+            ("0x1F", "syntax.number"),
+            ("'c'", "syntax.string"),
+        ]);
+
+        assert_eq!(pairs(&tokens("toml", "[package] # the crate")), vec![
+            ("[package]", "syntax.type"),
+            ("# the crate", "syntax.comment"),
+        ]);
+        assert_eq!(pairs(&tokens("toml", "name = \"stated\"")), vec![
+            ("name", "syntax.attribute"),
+            ("\"stated\"", "syntax.string"),
+        ]);
+        assert_eq!(pairs(&tokens("toml", "opt = { lto = true, level = 3 }")), vec![
+            ("opt", "syntax.attribute"),
+            ("lto", "syntax.attribute"),
+            ("true", "syntax.constant"),
+            ("level", "syntax.attribute"),
+            ("3", "syntax.number"),
+        ]);
+
+        assert_eq!(pairs(&tokens("markdown", "## Usage")), vec![("## Usage", "syntax.heading")]);
+        assert_eq!(pairs(&tokens("markdown", "- run `cargo` for **speed**, see [docs](x.md)")), vec![
+            ("- ", "syntax.punctuation"),
+            ("`cargo`", "syntax.code"),
+            ("**speed**", "syntax.strong"),
+            ("[docs](x.md)", "syntax.link"),
+        ]);
+
+        assert_eq!(pairs(&tokens("json", "{\"a\": [1.5, \"b\", null]}")), vec![
+            ("\"a\"", "syntax.attribute"),
+            ("1.5", "syntax.number"),
+            ("\"b\"", "syntax.string"),
+            ("null", "syntax.constant"),
+        ]);
+
+        assert_eq!(pairs(&tokens("shell", "if [ $# -gt 0 ]; then echo \"$1\" 'x'; fi # done")), vec![
+            ("if", "syntax.keyword"),
+            ("$#", "syntax.variable"),
+            ("0", "syntax.number"),
+            ("then", "syntax.keyword"),
+            ("\"$1\"", "syntax.string"),
+            ("'x'", "syntax.string"),
+            ("fi", "syntax.keyword"),
+            ("# done", "syntax.comment"),
+        ]);
+    }
+
+    /// Fenced code blocks in Markdown run over several lines.
+    #[test]
+    fn highlights_code_blocks() {
+        let grammar = Grammar::for_filetype("markdown").unwrap();
+        let (_, state) = grammar.highlight_line("```rust", None);
+        assert!(state.is_some());
+        let (spans, state) = grammar.highlight_line("# not a heading", state);
+        assert_eq!(spans[0].group, "syntax.code");
+        let (_, state) = grammar.highlight_line("```", state);
+        assert!(state.is_none());
+    }
+}
@@ -0,0 +1,54 @@
+use std::io;
+use std::panic;
+use crossterm::{cursor, execute, style, terminal};
+
+/// Restores the terminal when dropped, so that it is handed back in a usable
+///   state however the program stops using it.
+pub struct TerminalGuard {
+    _private: (),
+}
+
+impl TerminalGuard {
+    /// Switches to the alternate screen and raw mode.
+    pub fn enter() -> crossterm::Result<TerminalGuard> {
+        execute!(io::stdout(), terminal::EnterAlternateScreen)?;
+        // Create the guard before enabling raw mode, so that a failure there
+        //  still leaves the alternate screen
+        let guard = TerminalGuard { _private: () };
+        terminal::enable_raw_mode()?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/// Disables raw mode, shows the cursor and leaves the alternate screen.
+///
+/// Errors are ignored, since this runs when something has already gone wrong
+///   and there is nothing more useful to do. Restoring a terminal that is
+///   already restored is harmless.
+pub fn restore() {
+    let _ = terminal::disable_raw_mode();
+    let _ = execute!(
+        io::stdout(),
+        style::SetAttribute(style::Attribute::Reset),
+        style::ResetColor,
+        cursor::Show,
+        terminal::LeaveAlternateScreen
+    );
+}
+
+/// Installs a panic hook that restores the terminal before the panic message
+///   is printed, so that the message appears on the normal screen and the
+///   shell is left usable.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        restore();
+        default_hook(info);
+    }));
+}
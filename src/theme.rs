@@ -0,0 +1,489 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use crossterm::style::{Attribute, Attributes, Color};
+
+use crate::screen::Style;
+
+/// The themes built into the program. The default theme is the base that
+///   every other theme is laid over.
+const BUILTIN_THEMES: &[(&str, &str)] = &[
+    ("default", include_str!("../themes/default.toml")),
+    ("dusk", include_str!("../themes/dusk.toml")),
+];
+
+/// The highlight groups that the program draws with. Any group under
+///   `syntax.` may also be used, for syntax highlighting.
+pub const GROUPS: &[&str] = &[
+    "text", "bar", "bar-inactive", "cursor-line", "selection", "line-number",
+    "line-number-current", "separator", "title", "search-match",
+    "search-current", "syntax",
+];
+
+/// The style a theme gives a highlight group. Anything left out is taken from
+///   the group's parent, then from `text`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct GroupStyle {
+    foreground: Option<Color>,
+    background: Option<Color>,
+    attributes: Option<Attributes>,
+}
+
+impl GroupStyle {
+    /// Takes anything this style leaves out from another style.
+    fn fill_from(&mut self, other: &GroupStyle) {
+        self.foreground = self.foreground.or(other.foreground);
+        self.background = self.background.or(other.background);
+        self.attributes = self.attributes.or(other.attributes);
+    }
+}
+
+/// Gives a style to each of the named highlight groups that elements on
+///   screen are drawn with. Themes are read from TOML files, in the format
+///   described in `themes/default.toml`.
+#[derive(Clone, Debug)]
+pub struct Theme {
+    groups: HashMap<String, GroupStyle>,
+}
+
+impl Theme {
+    /// Returns the default theme.
+    pub fn new() -> Theme {
+        let mut theme = Theme { groups: HashMap::new() };
+        theme.apply_file(BUILTIN_THEMES[0].1).expect("default theme to be valid");
+        theme
+    }
+
+    /// Lays a theme over this one, looking first in a directory of theme
+    ///   files, then among the built-in themes.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the theme.
+    /// * `dir` - The directory holding the user's themes, if any.
+    pub fn load(&mut self, name: &str, dir: Option<&Path>) -> Result<(), String> {
+        let builtin = BUILTIN_THEMES.iter().find(|(builtin, _)| *builtin == name);
+        let text = match dir.map(|dir| dir.join(format!("{}.toml", name))) {
+            Some(path) => match fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => match builtin {
+                    Some((_, text)) => text.to_string(),
+                    None => return Err(format!("No theme named {}", name)),
+                },
+                Err(e) => return Err(format!("Cannot read {}: {}", path.display(), e)),
+            },
+            None => match builtin {
+                Some((_, text)) => text.to_string(),
+                None => return Err(format!("No theme named {}", name)),
+            },
+        };
+        self.apply_file(&text).map_err(|e| format!("Theme {}: {}", name, e))
+    }
+
+    /// Applies the groups in a theme file, replacing those already defined.
+    ///
+    /// Valid groups are applied even if others are invalid.
+    ///
+    /// # Arguments
+    ///
+    /// * `text` - The contents of a theme file.
+    pub fn apply_file(&mut self, text: &str) -> Result<(), String> {
+        let file: toml::Table = text.parse().map_err(|e: toml::de::Error| e.message().trim().to_string())?;
+        let mut errors = Vec::new();
+
+        let mut palette = HashMap::new();
+        if let Some(entries) = file.get("palette") {
+            match entries {
+                toml::Value::Table(entries) => {
+                    for (name, value) in entries {
+                        match value.as_str().and_then(parse_color) {
+                            Some(color) => {
+                                palette.insert(name.as_str(), color);
+                            }
+                            None => errors.push(format!("palette.{}: invalid color", name)),
+                        }
+                    }
+                }
+                _ => errors.push("palette must be a table".to_string()),
+            }
+        }
+
+        match file.get("groups") {
+            Some(toml::Value::Table(groups)) => {
+                for (name, value) in groups {
+                    if !GROUPS.contains(&name.as_str()) && !name.starts_with("syntax.") {
+                        errors.push(format!("Unknown group: {}", name));
+                        continue;
+                    }
+                    match read_group(value, &palette) {
+                        Ok(style) => {
+                            self.groups.insert(name.clone(), style);
+                        }
+                        Err(e) => errors.push(format!("{}: {}", name, e)),
+                    }
+                }
+            }
+            Some(_) => errors.push("groups must be a table".to_string()),
+            None => {}
+        }
+
+        for key in file.keys().filter(|key| *key != "palette" && *key != "groups") {
+            errors.push(format!("Unknown section: {}", key));
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    /// Gets the style with which to draw a highlight group.
+    ///
+    /// Anything the group leaves out is taken from its parent group, named by
+    ///   dropping the last dotted part of its name, and so on up to `text`.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The name of the highlight group.
+    pub fn style(&self, group: &str) -> Style {
+        self.style_over(group, "text")
+    }
+
+    /// Gets the style with which to draw a highlight group on top of
+    ///   another, such as syntax on the line holding the cursor.
+    ///
+    /// Anything the group and its parents leave out is taken from the group
+    ///   beneath and its parents, then from `text`.
+    ///
+    /// # Arguments
+    ///
+    /// * `group` - The name of the highlight group.
+    /// * `beneath` - The name of the group drawn beneath it.
+    pub fn style_over(&self, group: &str, beneath: &str) -> Style {
+        let mut style = GroupStyle::default();
+        for mut name in [Some(group), Some(beneath)] {
+            while let Some(current) = name {
+                if let Some(group_style) = self.groups.get(current) {
+                    style.fill_from(group_style);
+                }
+                name = current.rfind('.').map(|ix| &current[..ix]);
+            }
+        }
+        if let Some(text) = self.groups.get("text") {
+            style.fill_from(text);
+        }
+        Style {
+            foreground: style.foreground.unwrap_or(Color::Reset),
+            background: style.background.unwrap_or(Color::Reset),
+            attributes: style.attributes.unwrap_or_default(),
+        }
+    }
+
+    /// Returns a copy of the theme using only colors that a terminal can
+    ///   show, each color swapped for the nearest one available.
+    ///
+    /// # Arguments
+    ///
+    /// * `support` - The colors the terminal can show.
+    pub fn downgraded(&self, support: ColorSupport) -> Theme {
+        let mut theme = self.clone();
+        for style in theme.groups.values_mut() {
+            style.foreground = style.foreground.map(|color| support.downgrade(color));
+            style.background = style.background.map(|color| support.downgrade(color));
+        }
+        theme
+    }
+}
+
+/// Reads the style of a highlight group from a theme file.
+fn read_group(value: &toml::Value, palette: &HashMap<&str, Color>) -> Result<GroupStyle, String> {
+    let entries = value.as_table().ok_or("expected a table")?;
+    let mut style = GroupStyle::default();
+    let mut attributes = Attributes::default();
+    let mut has_attributes = false;
+    for (key, value) in entries {
+        match key.as_str() {
+            "fg" | "bg" => {
+                let text = value.as_str().ok_or_else(|| format!("{} must be a color", key))?;
+                let color = palette
+                    .get(text)
+                    .copied()
+                    .or_else(|| parse_color(text))
+                    .ok_or_else(|| format!("Invalid color: {}", text))?;
+                if key == "fg" {
+                    style.foreground = Some(color);
+                } else {
+                    style.background = Some(color);
+                }
+            }
+            "bold" | "italic" | "underline" | "reverse" => {
+                let on = value.as_bool().ok_or_else(|| format!("{} must be true or false", key))?;
+                let attribute = match key.as_str() {
+                    "bold" => Attribute::Bold,
+                    "italic" => Attribute::Italic,
+                    "underline" => Attribute::Underlined,
+                    _ => Attribute::Reverse,
+                };
+                if on {
+                    attributes.set(attribute);
+                }
+                has_attributes = true;
+            }
+            _ => return Err(format!("Unknown key: {}", key)),
+        }
+    }
+    if has_attributes {
+        style.attributes = Some(attributes);
+    }
+    Ok(style)
+}
+
+/// The range of colors a terminal can show.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColorSupport {
+    /// The 16 basic colors, whose exact shades are up to the terminal.
+    Basic,
+
+    /// The 256 color palette.
+    Extended,
+
+    /// Any 24 bit color.
+    TrueColor,
+}
+
+impl ColorSupport {
+    /// Works out the colors the terminal supports from the environment.
+    pub fn detect() -> ColorSupport {
+        ColorSupport::from_env(
+            std::env::var("COLORTERM").ok().as_deref(),
+            std::env::var("TERM").ok().as_deref()
+        )
+    }
+
+    /// Works out the colors a terminal supports from the values of the
+    ///   `COLORTERM` and `TERM` environment variables.
+    fn from_env(colorterm: Option<&str>, term: Option<&str>) -> ColorSupport {
+        match (colorterm, term) {
+            (Some("truecolor" | "24bit"), _) => ColorSupport::TrueColor,
+            (_, Some(term)) if term.contains("direct") => ColorSupport::TrueColor,
+            (_, Some(term)) if term.contains("256color") => ColorSupport::Extended,
+            _ => ColorSupport::Basic,
+        }
+    }
+
+    /// Gets a level of support by the name used for the `colors` setting.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - One of `16`, `256` or `truecolor`.
+    pub fn from_name(name: &str) -> Option<ColorSupport> {
+        match name {
+            "16" => Some(ColorSupport::Basic),
+            "256" => Some(ColorSupport::Extended),
+            "truecolor" => Some(ColorSupport::TrueColor),
+            _ => None,
+        }
+    }
+
+    /// Swaps a color the terminal can't show for the nearest one it can.
+    ///
+    /// # Arguments
+    ///
+    /// * `color` - The color to show.
+    pub fn downgrade(&self, color: Color) -> Color {
+        match (self, color) {
+            (ColorSupport::Basic, Color::Rgb { r, g, b }) => nearest_basic((r, g, b)),
+            (ColorSupport::Basic, Color::AnsiValue(index)) => nearest_basic(palette_rgb(index)),
+            (ColorSupport::Extended, Color::Rgb { r, g, b }) => Color::AnsiValue(nearest_palette((r, g, b))),
+            _ => color,
+        }
+    }
+}
+
+/// The 16 basic colors, with the shades xterm gives them, in palette order.
+const BASIC_COLORS: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (205, 0, 0)),
+    (Color::DarkGreen, (0, 205, 0)),
+    (Color::DarkYellow, (205, 205, 0)),
+    (Color::DarkBlue, (0, 0, 238)),
+    (Color::DarkMagenta, (205, 0, 205)),
+    (Color::DarkCyan, (0, 205, 205)),
+    (Color::Grey, (229, 229, 229)),
+    (Color::DarkGrey, (127, 127, 127)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (92, 92, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// The levels of each channel in the 6x6x6 color cube of the 256 color
+///   palette.
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// Gets the shade of a color in the 256 color palette.
+fn palette_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => BASIC_COLORS[index as usize].1,
+        16..=231 => {
+            let ix = index - 16;
+            (CUBE_LEVELS[(ix / 36) as usize], CUBE_LEVELS[(ix / 6 % 6) as usize], CUBE_LEVELS[(ix % 6) as usize])
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+/// Measures how different two colors look, roughly.
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let channel = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    channel(a.0, b.0) + channel(a.1, b.1) + channel(a.2, b.2)
+}
+
+/// Finds the basic color nearest to a shade.
+fn nearest_basic(rgb: (u8, u8, u8)) -> Color {
+    BASIC_COLORS
+        .iter()
+        .min_by_key(|(_, basic)| distance(rgb, *basic))
+        .map(|(color, _)| *color)
+        .unwrap()
+}
+
+/// Finds the entry of the 256 color palette nearest to a shade, from the
+///   color cube and the grey ramp. The basic colors are skipped, as
+///   terminals often change their shades.
+fn nearest_palette(rgb: (u8, u8, u8)) -> u8 {
+    let nearest_level = |channel: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|ix| (CUBE_LEVELS[*ix] as i32 - channel as i32).abs())
+            .unwrap() as u8
+    };
+    let cube = 16 + 36 * nearest_level(rgb.0) + 6 * nearest_level(rgb.1) + nearest_level(rgb.2);
+
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let grey = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+
+    if distance(rgb, palette_rgb(grey)) < distance(rgb, palette_rgb(cube)) {
+        grey
+    } else {
+        cube
+    }
+}
+
+/// The names of the basic colors, as written in theme files.
+const COLOR_NAMES: &[(&str, Color)] = &[
+    ("reset", Color::Reset),
+    ("black", Color::Black),
+    ("darkgrey", Color::DarkGrey),
+    ("red", Color::Red),
+    ("darkred", Color::DarkRed),
+    ("green", Color::Green),
+    ("darkgreen", Color::DarkGreen),
+    ("yellow", Color::Yellow),
+    ("darkyellow", Color::DarkYellow),
+    ("blue", Color::Blue),
+    ("darkblue", Color::DarkBlue),
+    ("magenta", Color::Magenta),
+    ("darkmagenta", Color::DarkMagenta),
+    ("cyan", Color::Cyan),
+    ("darkcyan", Color::DarkCyan),
+    ("white", Color::White),
+    ("grey", Color::Grey),
+];
+
+/// Parses a color, written as a name such as `darkblue`, as `#rrggbb`, or as
+///   a number from the 256 color palette.
+///
+/// # Arguments
+///
+/// * `text` - The color as written.
+pub fn parse_color(text: &str) -> Option<Color> {
+    let text = text.trim().to_ascii_lowercase();
+    if let Some(hex) = text.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return None;
+        }
+        let channel = |ix: usize| u8::from_str_radix(&hex[ix..ix + 2], 16).ok();
+        return Some(Color::Rgb { r: channel(0)?, g: channel(2)?, b: channel(4)? });
+    }
+    if let Ok(index) = text.parse::<u8>() {
+        return Some(Color::AnsiValue(index));
+    }
+    let text = text.replace("gray", "grey");
+    COLOR_NAMES.iter().find(|(name, _)| *name == text).map(|(_, color)| *color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Colors are written as names, palette numbers or hex triples.
+    #[test]
+    fn parses_colors() {
+        assert_eq!(parse_color("DarkGray"), Some(Color::DarkGrey));
+        assert_eq!(parse_color("208"), Some(Color::AnsiValue(208)));
+        assert_eq!(parse_color("#ff8000"), Some(Color::Rgb { r: 255, g: 128, b: 0 }));
+        assert_eq!(parse_color("#ff80"), None);
+        assert_eq!(parse_color("mauve"), None);
+    }
+
+    /// Groups take what they leave out from their parents, then from any
+    ///   group beneath them, then from text, and user themes only replace the
+    ///   groups they define.
+    #[test]
+    fn resolves_groups() {
+        let mut theme = Theme::new();
+        theme.apply_file(concat!(
+            "[palette]\n",
+            "ink = \"#102030\"\n",
+            "[groups]\n",
+            "text = { fg = \"ink\", bg = \"black\" }\n",
+            "\"syntax.keyword\" = { fg = \"red\", bold = true }\n",
+        )).unwrap();
+
+        let mut bold = Attributes::default();
+        bold.set(Attribute::Bold);
+        assert_eq!(theme.style("syntax.keyword.control"), Style {
+            foreground: Color::Red,
+            background: Color::Black,
+            attributes: bold,
+        });
+        assert_eq!(theme.style("syntax.unknown").foreground, Color::Rgb { r: 0x10, g: 0x20, b: 0x30 });
+        assert_eq!(theme.style("bar"), Style::new(Color::White, Color::Black));
+        assert_eq!(theme.style_over("syntax.keyword", "cursor-line").background, Color::DarkGrey);
+
+        let result = theme.apply_file("[groups]\nbar = { fg = \"nope\" }\nsidebar = {}\n");
+        assert_eq!(result, Err("bar: Invalid color: nope; Unknown group: sidebar".to_string()));
+    }
+
+    /// Built-in themes are found by name, and unknown names are reported.
+    #[test]
+    fn loads_builtin_themes() {
+        let mut theme = Theme::new();
+        theme.load("dusk", None).unwrap();
+        assert_eq!(theme.style("bar").background, Color::Rgb { r: 0x83, g: 0xa5, b: 0x98 });
+        assert!(theme.load("nonesuch", None).is_err());
+    }
+
+    /// Colors are swapped for the nearest the terminal can show.
+    #[test]
+    fn downgrades_colors() {
+        let orange = Color::Rgb { r: 0xff, g: 0x87, b: 0x00 };
+        assert_eq!(ColorSupport::TrueColor.downgrade(orange), orange);
+        assert_eq!(ColorSupport::Extended.downgrade(orange), Color::AnsiValue(208));
+        assert_eq!(ColorSupport::Extended.downgrade(Color::Rgb { r: 0x30, g: 0x30, b: 0x30 }), Color::AnsiValue(236));
+        assert_eq!(ColorSupport::Basic.downgrade(orange), Color::DarkYellow);
+        assert_eq!(ColorSupport::Basic.downgrade(Color::AnsiValue(4)), Color::DarkBlue);
+        assert_eq!(ColorSupport::Basic.downgrade(Color::Blue), Color::Blue);
+
+        assert_eq!(ColorSupport::from_env(Some("truecolor"), Some("xterm")), ColorSupport::TrueColor);
+        assert_eq!(ColorSupport::from_env(None, Some("screen-256color")), ColorSupport::Extended);
+        assert_eq!(ColorSupport::from_env(None, Some("linux")), ColorSupport::Basic);
+    }
+}